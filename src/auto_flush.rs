@@ -0,0 +1,342 @@
+use crate::sink::CollectorSink;
+use crate::{Outcome, Recorder, RequestResponseData, TrackedClient};
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex as AsyncMutex, Notify};
+use tokio::task::JoinHandle;
+
+// Столько запечатанных записей будят сброс, не дожидаясь таймера
+pub const DEFAULT_AUTO_FLUSH_THRESHOLD: usize = 1000;
+
+// Можно ли автосбросу забрать запись
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum Seal {
+    // запрос в пути, запись запечатается при завершении
+    Open,
+    // запись заведена внутри sealing: обёртка ещё дописывает её и запечатает, закончив
+    Held,
+    #[default]
+    Sealed,
+}
+
+// Ключ и seq записей, заведённых внутри sealing
+type ScopedEntries = Mutex<Vec<(String, u64)>>;
+
+tokio::task_local! {
+    static SEAL_SCOPE: Arc<ScopedEntries>;
+}
+
+fn lock_keys(keys: &ScopedEntries) -> MutexGuard<'_, Vec<(String, u64)>> {
+    keys.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Печать новой записи (begin_entry)
+pub(crate) fn initial_seal(key: &str, seq: u64) -> Seal {
+    match SEAL_SCOPE.try_with(|keys| lock_keys(keys).push((key.to_string(), seq))) {
+        Ok(()) => Seal::Held,
+        Err(_) => Seal::Open,
+    }
+}
+
+// seq последней записи под key, заведённой в текущем sealing; для обёрток, которым
+// отправка вернула ошибку вместо LoggedText
+pub(crate) fn scoped_seq(key: &str) -> Option<u64> {
+    SEAL_SCOPE
+        .try_with(|keys| lock_keys(keys).iter().rev().find(|(k, _)| k == key).map(|(_, seq)| *seq))
+        .ok()
+        .flatten()
+}
+
+// Запись завершена; держит её обёртка — печать подождёт конца sealing
+pub(crate) fn seal_finished(auto_flush: &Mutex<AutoFlush>, entry: &mut RequestResponseData) {
+    if entry.seal == Seal::Open {
+        entry.seal = Seal::Sealed;
+        note_sealed(auto_flush);
+    }
+}
+
+type Collector = Arc<AsyncMutex<IndexMap<String, RequestResponseData>>>;
+
+struct AutoFlushTask {
+    threshold: usize,
+    // запечатано с прошлого пробуждения
+    sealed: usize,
+    wake: Arc<Notify>,
+    // отброшенный отправитель — сигнал задаче сбросить всё и выйти
+    stop: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+#[derive(Default)]
+pub(crate) struct AutoFlush {
+    task: Option<AutoFlushTask>,
+}
+
+// Последний клон клиента отброшен: задача делает последний сброс сама
+impl Drop for AutoFlush {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.as_mut() {
+            task.stop.take();
+        }
+    }
+}
+
+fn lock_auto_flush(auto_flush: &Mutex<AutoFlush>) -> MutexGuard<'_, AutoFlush> {
+    auto_flush.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Вызывается при каждой печати записи
+pub(crate) fn note_sealed(auto_flush: &Mutex<AutoFlush>) {
+    if let Some(task) = lock_auto_flush(auto_flush).task.as_mut() {
+        task.sealed += 1;
+        if task.sealed >= task.threshold {
+            task.sealed = 0;
+            task.wake.notify_one();
+        }
+    }
+}
+
+// Ответ записан или запрос не удался, и никакая обёртка запись больше не дописывает
+pub(crate) fn finalized(entry: &RequestResponseData) -> bool {
+    entry.outcome != Outcome::Pending && entry.seal == Seal::Sealed
+}
+
+// Забирает завершённые записи, остальные остаются на своих местах: их finish ещё придёт
+pub(crate) fn take_finalized(coll: &mut IndexMap<String, RequestResponseData>) -> IndexMap<String, RequestResponseData> {
+    let mut taken = IndexMap::new();
+    for (key, entry) in std::mem::take(coll) {
+        if finalized(&entry) {
+            taken.insert(key, entry);
+        } else {
+            coll.insert(key, entry);
+        }
+    }
+    taken
+}
+
+// Копия завершённых записей для выгрузки, после которой их убирает remove_taken
+pub(crate) fn finalized_entries(coll: &IndexMap<String, RequestResponseData>) -> IndexMap<String, RequestResponseData> {
+    coll.iter()
+        .filter(|(_, entry)| finalized(entry))
+        .map(|(key, entry)| (key.clone(), entry.clone()))
+        .collect()
+}
+
+// Убирает выгруженные записи; запись, заново заведённую под тем же ключом, seq отличает
+pub(crate) fn remove_taken(coll: &mut IndexMap<String, RequestResponseData>, taken: &IndexMap<String, RequestResponseData>) {
+    for (key, entry) in taken {
+        if coll.get(key).is_some_and(|current| current.seq == entry.seq) {
+            coll.shift_remove(key);
+        }
+    }
+}
+
+async fn flush_finalized(collector: &Collector, sink: &dyn CollectorSink) {
+    let mut taken: Vec<_> = take_finalized(&mut *collector.lock().await).into_iter().collect();
+    if taken.is_empty() {
+        return;
+    }
+    taken.sort_by_key(|(_, entry)| entry.seq);
+    for (key, entry) in &taken {
+        sink.record(key, entry).await;
+    }
+    sink.flush().await;
+}
+
+async fn run(
+    collector: Collector,
+    sink: Arc<dyn CollectorSink>,
+    interval: Duration,
+    wake: Arc<Notify>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = wake.notified() => {}
+            _ = &mut stop => break,
+        }
+        flush_finalized(&collector, &*sink).await;
+    }
+    flush_finalized(&collector, &*sink).await;
+}
+
+// Печатает записи sealing, когда обёртка закончила или её future отброшен
+struct SealGuard {
+    recorder: Option<Recorder>,
+    keys: Arc<ScopedEntries>,
+}
+
+impl SealGuard {
+    async fn seal(mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            recorder.seal_held(&self.keys).await;
+        }
+    }
+}
+
+impl Drop for SealGuard {
+    fn drop(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let keys = self.keys.clone();
+            handle.spawn(async move { recorder.seal_held(&keys).await });
+        }
+    }
+}
+
+impl Recorder {
+    async fn seal_held(&self, keys: &ScopedEntries) {
+        let keys = std::mem::take(&mut *lock_keys(keys));
+        let mut coll = self.collector.lock().await;
+        for (key, seq) in &keys {
+            let Some(entry) = coll
+                .get_mut(key)
+                .filter(|entry| entry.issued_seq == *seq && entry.seal == Seal::Held)
+            else {
+                continue;
+            };
+            // ответ ещё читается (потоковое тело): печать при завершении
+            if entry.outcome == Outcome::Pending {
+                entry.seal = Seal::Open;
+            } else {
+                entry.seal = Seal::Sealed;
+                note_sealed(&self.auto_flush);
+            }
+        }
+    }
+}
+
+impl TrackedClient {
+    // Для обёрток, дописывающих запись после её завершения: записи, заведённые внутри fut,
+    // автосброс не заберёт, пока fut не закончится. Вложенный вызов работает во внешнем.
+    pub(crate) async fn sealing<T>(&self, fut: impl Future<Output = T>) -> T {
+        if SEAL_SCOPE.try_with(|_| ()).is_ok() {
+            return fut.await;
+        }
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let guard = SealGuard { recorder: Some(self.recorder()), keys: keys.clone() };
+        let output = SEAL_SCOPE.scope(keys, fut).await;
+        guard.seal().await;
+        output
+    }
+
+    pub fn enable_auto_flush(&self, interval: Duration, sink: Arc<dyn CollectorSink>) -> Result<()> {
+        self.enable_auto_flush_with(interval, DEFAULT_AUTO_FLUSH_THRESHOLD, sink)
+    }
+
+    // Фоновая задача забирает завершённые записи из коллектора в sink раз в interval и как
+    // только запечатается threshold записей; записи без ответа и те, что ещё дописывает
+    // обёртка (retry, download, endpoint и др.), остаются. Прежняя задача
+    // останавливается с последним сбросом, как и при Drop последнего клона клиента.
+    pub fn enable_auto_flush_with(&self, interval: Duration, threshold: usize, sink: Arc<dyn CollectorSink>) -> Result<()> {
+        let runtime = tokio::runtime::Handle::try_current()
+            .map_err(|_| anyhow!("enable_auto_flush must be called inside a Tokio runtime"))?;
+        let wake = Arc::new(Notify::new());
+        let (stop, stop_rx) = oneshot::channel();
+        let handle = runtime.spawn(run(
+            self.collector.clone(),
+            sink,
+            interval.max(Duration::from_millis(1)),
+            wake.clone(),
+            stop_rx,
+        ));
+        let task = AutoFlushTask { threshold: threshold.max(1), sealed: 0, wake, stop: Some(stop), handle };
+        // прежняя задача получает сигнал при отбрасывании своего stop
+        lock_auto_flush(&self.auto_flush).task.replace(task);
+        Ok(())
+    }
+
+    // Останавливает задачу, дождавшись последнего сброса; false — автосброс не был включён
+    pub async fn disable_auto_flush(&self) -> bool {
+        let Some(task) = lock_auto_flush(&self.auto_flush).task.take() else {
+            return false;
+        };
+        let AutoFlushTask { stop, handle, .. } = task;
+        drop(stop);
+        let _ = handle.await;
+        true
+    }
+
+    pub fn auto_flush_enabled(&self) -> bool {
+        lock_auto_flush(&self.auto_flush).task.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::MemorySink;
+    use crate::test_support::{TestResponse, TestServer};
+
+    async fn wait_for(sink: &MemorySink, len: usize) {
+        for _ in 0..200 {
+            if sink.len().await >= len {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("sink has {} entries, expected {}", sink.len().await, len);
+    }
+
+    #[tokio::test]
+    async fn held_entries_wait_for_the_wrapper() {
+        let server = TestServer::start(|req| async move { TestResponse::ok(req.path) }).await;
+        let client = TrackedClient::new().unwrap();
+        let sink = MemorySink::new();
+        client.enable_auto_flush(Duration::from_millis(10), Arc::new(sink.clone())).unwrap();
+
+        client
+            .sealing(async {
+                let held = client.tracked_send_text("held", client.inner.get(server.url("/held"))).await.unwrap();
+                client.tracked_send("plain-inside", client.inner.get(server.url("/inside"))).await.unwrap();
+                // дольше любого таймера: запись всё равно ждёт конца обёртки
+                tokio::time::sleep(Duration::from_millis(150)).await;
+                assert!(sink.is_empty().await);
+                client.update_entry("held", held.seq, |entry| entry.notes.push("late".to_string())).await;
+            })
+            .await;
+        client.tracked_send("plain", client.inner.get(server.url("/plain"))).await.unwrap();
+
+        wait_for(&sink, 3).await;
+        let flushed = sink.snapshot().await;
+        assert_eq!(flushed["held"].notes, ["late"]);
+        assert!(client.collector.lock().await.is_empty());
+        assert!(client.disable_auto_flush().await);
+    }
+
+    #[tokio::test]
+    async fn pending_and_cancelled_scopes() {
+        let server = TestServer::start(|req| async move { TestResponse::ok(req.path) }).await;
+        let client = TrackedClient::new().unwrap();
+        let sink = MemorySink::new();
+        client.enable_auto_flush_with(Duration::from_millis(10), 1, Arc::new(sink.clone())).unwrap();
+
+        // тело ещё не прочитано: запись в пути и после конца обёртки
+        let resp = client
+            .sealing(client.tracked_send_streamed("stream", client.inner.get(server.url("/stream"))))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(sink.is_empty().await);
+        assert_eq!(resp.text().await.unwrap(), "/stream");
+        wait_for(&sink, 1).await;
+
+        // отброшенная обёртка тоже печатает свои записи
+        let scoped = client.sealing(async {
+            client.tracked_send("dropped", client.inner.get(server.url("/dropped"))).await.unwrap();
+            std::future::pending::<()>().await;
+        });
+        let _ = tokio::time::timeout(Duration::from_millis(100), scoped).await;
+        wait_for(&sink, 2).await;
+        assert!(sink.snapshot().await.contains_key("dropped"));
+    }
+}
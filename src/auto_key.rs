@@ -0,0 +1,36 @@
+use crate::{LoggedText, SendOptions, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::{Request, RequestBuilder};
+use std::sync::atomic::Ordering;
+
+impl TrackedClient {
+    // Следующий номер запроса этого клиента; монотонно растёт
+    pub(crate) fn next_seq(&self) -> u64 {
+        self.seq.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // {method}_{host}{path}_{seq}; номер уникален в пределах клиента
+    fn auto_key(&self, req: &Request) -> String {
+        let url = req.url();
+        format!(
+            "{}_{}{}_{}",
+            req.method().as_str(),
+            url.host_str().unwrap_or(""),
+            url.path(),
+            self.next_seq()
+        )
+    }
+
+    // Ключ придумывается сам и возвращается в LoggedText.key
+    pub async fn tracked_send_auto(&self, builder: RequestBuilder) -> Result<LoggedText> {
+        self.tracked_send_auto_with(builder, &SendOptions::default()).await
+    }
+
+    pub async fn tracked_send_auto_with(&self, builder: RequestBuilder, opts: &SendOptions) -> Result<LoggedText> {
+        let req = builder
+            .build()
+            .context("Failed to build request")?;
+        let key = self.auto_key(&req);
+        self.send_logged(&key, req, opts).await
+    }
+}
@@ -0,0 +1,400 @@
+use crate::errors::EntryError;
+use crate::{ErrorKind, LoggedText, Outcome, SendOptions, TrackedClient};
+use anyhow::{anyhow, Context, Result};
+use futures_util::stream::{self, StreamExt};
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+
+static BATCH_SEQ: AtomicU64 = AtomicU64::new(1);
+
+fn next_batch_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    format!("batch-{}-{}", millis, BATCH_SEQ.fetch_add(1, Ordering::Relaxed))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BatchProgress {
+    pub batch_id: String,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub in_flight: usize,
+    pub remaining: usize,
+    pub elapsed_ms: u64,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CancelState {
+    Running,
+    // новые запросы не отправляем, начатые дожидаемся
+    StopDispatch,
+    // новые не отправляем, начатые обрываем
+    AbortInFlight,
+}
+
+pub type BatchResults = Vec<(String, Result<LoggedText>)>;
+
+// Живой хэндл пакета: прогресс, отмена и итоговые результаты в исходном порядке
+pub struct BatchHandle {
+    id: String,
+    started: Instant,
+    progress: watch::Receiver<BatchProgress>,
+    cancel: watch::Sender<CancelState>,
+    task: JoinHandle<BatchResults>,
+}
+
+impl BatchHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn progress(&self) -> BatchProgress {
+        let mut progress = self.progress.borrow().clone();
+        progress.elapsed_ms = self.started.elapsed().as_millis() as u64;
+        progress
+    }
+
+    // Канал для UI: новое значение приходит при каждом изменении счётчиков
+    pub fn watch(&self) -> watch::Receiver<BatchProgress> {
+        self.progress.clone()
+    }
+
+    // Прекращает отправку новых запросов; abort_in_flight — ещё и обрывает начатые
+    pub fn cancel(&self, abort_in_flight: bool) {
+        let state = if abort_in_flight { CancelState::AbortInFlight } else { CancelState::StopDispatch };
+        self.cancel.send_modify(|current| {
+            if *current != CancelState::AbortInFlight {
+                *current = state;
+            }
+        });
+    }
+
+    pub async fn await_all(self) -> Result<BatchResults> {
+        self.task.await.context("Batch dispatcher task failed")
+    }
+}
+
+fn update(tx: &watch::Sender<BatchProgress>, started: Instant, f: impl FnOnce(&mut BatchProgress)) {
+    tx.send_modify(|p| {
+        f(p);
+        p.elapsed_ms = started.elapsed().as_millis() as u64;
+    });
+}
+
+// Оборванная задача не успевает записать ни ответ, ни ошибку — иначе запись осталась бы
+// Pending навсегда и не досталась бы автосбросу
+async fn fail_cancelled(client: &TrackedClient, batch_id: &str, key: &str) {
+    let pending = client
+        .collector
+        .lock()
+        .await
+        .get(key)
+        .filter(|e| e.batch_id.as_deref() == Some(batch_id) && e.outcome == Outcome::Pending)
+        .map(|e| e.issued_seq);
+    if let Some(seq) = pending {
+        let error = EntryError::new("Batch request cancelled".to_string(), ErrorKind::Other);
+        client.recorder().fail(key, seq, None, error).await;
+    }
+}
+
+impl TrackedClient {
+    // Пакет без фоновой задачи: не больше max_concurrency запросов одновременно,
+    // ошибка одного не прерывает остальные, результаты — в исходном порядке
+    pub async fn tracked_send_all(
+        &self,
+        requests: Vec<(String, RequestBuilder)>,
+        max_concurrency: usize,
+    ) -> BatchResults {
+        let batch_id = next_batch_id();
+        let mut results: Vec<(usize, String, Result<LoggedText>)> = stream::iter(requests.into_iter().enumerate())
+            .map(|(idx, (key, builder))| {
+                let opts = SendOptions { batch_id: Some(batch_id.clone()), ..SendOptions::default() };
+                async move {
+                    let result = self.tracked_send_with(&key, builder, &opts).await;
+                    (idx, key, result)
+                }
+            })
+            .buffer_unordered(max_concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(idx, _, _)| *idx);
+        results.into_iter().map(|(_, key, result)| (key, result)).collect()
+    }
+
+    // Запускает пакет в фоне и сразу возвращает хэндл (нужен tokio runtime).
+    // Каждая запись пакета получает batch_id.
+    pub fn tracked_send_all_with_handle(
+        &self,
+        requests: Vec<(String, RequestBuilder)>,
+        max_concurrency: usize,
+    ) -> BatchHandle {
+        let id = next_batch_id();
+        let started = Instant::now();
+        let total = requests.len();
+        let (progress_tx, progress_rx) = watch::channel(BatchProgress {
+            batch_id: id.clone(),
+            total,
+            remaining: total,
+            ..Default::default()
+        });
+        let (cancel_tx, mut cancel_rx) = watch::channel(CancelState::Running);
+        let client = self.clone();
+        let batch_id = id.clone();
+        let cancel_keepalive = cancel_tx.clone();
+        let max_concurrency = max_concurrency.max(1);
+
+        let task = tokio::spawn(async move {
+            let _cancel_keepalive = cancel_keepalive;
+            let keys: Vec<String> = requests.iter().map(|(k, _)| k.clone()).collect();
+            let mut results: Vec<Option<Result<LoggedText>>> = (0..total).map(|_| None).collect();
+            let mut set = JoinSet::new();
+            let mut task_index = HashMap::new();
+            let mut pending = requests.into_iter().enumerate();
+
+            loop {
+                let state = *cancel_rx.borrow();
+                if state == CancelState::AbortInFlight {
+                    set.abort_all();
+                }
+                if state != CancelState::Running {
+                    update(&progress_tx, started, |p| p.cancelled = true);
+                }
+
+                let can_dispatch = state == CancelState::Running && set.len() < max_concurrency;
+                if can_dispatch {
+                    if let Some((idx, (key, builder))) = pending.next() {
+                        let client = client.clone();
+                        let opts = SendOptions { batch_id: Some(batch_id.clone()), ..SendOptions::default() };
+                        let handle = set.spawn(async move { client.tracked_send_with(&key, builder, &opts).await });
+                        task_index.insert(handle.id(), idx);
+                        update(&progress_tx, started, |p| {
+                            p.in_flight += 1;
+                            p.remaining -= 1;
+                        });
+                        continue;
+                    }
+                }
+                if set.is_empty() {
+                    break;
+                }
+
+                tokio::select! {
+                    joined = set.join_next_with_id() => {
+                        let (idx, result) = match joined {
+                            Some(Ok((id, result))) => (task_index.remove(&id), result),
+                            Some(Err(e)) => {
+                                let idx = task_index.remove(&e.id());
+                                if e.is_cancelled() {
+                                    if let Some(idx) = idx {
+                                        fail_cancelled(&client, &batch_id, &keys[idx]).await;
+                                    }
+                                }
+                                (idx, Err(anyhow!("Batch request cancelled: {}", e)))
+                            }
+                            None => continue,
+                        };
+                        let ok = result.is_ok();
+                        if let Some(idx) = idx {
+                            results[idx] = Some(result);
+                        }
+                        update(&progress_tx, started, |p| {
+                            p.in_flight -= 1;
+                            if ok { p.completed += 1 } else { p.failed += 1 }
+                        });
+                    }
+                    // отправитель живёт и внутри задачи, поэтому ошибки здесь не бывает
+                    _ = cancel_rx.changed() => {}
+                }
+            }
+
+            // ключ записи мог получить суффикс (KeyCollisionPolicy) — добиваем по batch_id
+            if *cancel_rx.borrow() == CancelState::AbortInFlight {
+                let orphaned: Vec<String> = client
+                    .collector
+                    .lock()
+                    .await
+                    .iter()
+                    .filter(|(_, e)| e.batch_id.as_deref() == Some(batch_id.as_str()) && e.outcome == Outcome::Pending)
+                    .map(|(k, _)| k.clone())
+                    .collect();
+                for key in orphaned {
+                    fail_cancelled(&client, &batch_id, &key).await;
+                }
+            }
+
+            keys.into_iter()
+                .zip(results)
+                .map(|(key, result)| {
+                    let result = result.unwrap_or_else(|| Err(anyhow!("Batch cancelled before dispatch")));
+                    (key, result)
+                })
+                .collect()
+        });
+
+        BatchHandle { id, started, progress: progress_rx, cancel: cancel_tx, task }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Semaphore;
+
+    // /slow отвечает, только когда тест выдаст разрешение
+    async fn gated_server() -> (TestServer, Arc<Semaphore>) {
+        let gate = Arc::new(Semaphore::new(0));
+        let server_gate = gate.clone();
+        let server = TestServer::start(move |req| {
+            let gate = server_gate.clone();
+            async move {
+                if req.path.starts_with("/slow") {
+                    gate.acquire().await.unwrap().forget();
+                }
+                TestResponse::ok("done")
+            }
+        })
+        .await;
+        (server, gate)
+    }
+
+    // порт, на котором никто не слушает
+    async fn closed_url() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{}/", addr)
+    }
+
+    async fn wait_for(handle: &BatchHandle, f: impl FnMut(&BatchProgress) -> bool) {
+        let mut rx = handle.watch();
+        tokio::time::timeout(Duration::from_secs(10), rx.wait_for(f))
+            .await
+            .expect("progress did not arrive in time")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mixed_batch_reports_progress_while_in_flight() {
+        let (server, gate) = gated_server().await;
+        let client = TrackedClient::new().unwrap();
+        let requests = vec![
+            ("ok1".to_string(), client.inner.get(server.url("/ok1"))),
+            ("slow1".to_string(), client.inner.get(server.url("/slow1"))),
+            ("bad".to_string(), client.inner.get(closed_url().await)),
+            ("slow2".to_string(), client.inner.get(server.url("/slow2"))),
+            ("ok2".to_string(), client.inner.get(server.url("/ok2"))),
+        ];
+        let handle = client.tracked_send_all_with_handle(requests, 5);
+
+        wait_for(&handle, |p| p.completed == 2 && p.failed == 1).await;
+        let progress = handle.progress();
+        assert_eq!(progress.total, 5);
+        assert_eq!(progress.in_flight, 2);
+        assert_eq!(progress.remaining, 0);
+        assert!(!progress.cancelled);
+
+        gate.add_permits(1);
+        wait_for(&handle, |p| p.completed == 3).await;
+        let progress = handle.progress();
+        assert_eq!((progress.completed, progress.failed, progress.in_flight), (3, 1, 1));
+
+        gate.add_permits(1);
+        let batch_id = handle.id().to_string();
+        let results = handle.await_all().await.unwrap();
+        let keys: Vec<&str> = results.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["ok1", "slow1", "bad", "slow2", "ok2"]);
+        let failed: Vec<&str> = results.iter().filter(|(_, r)| r.is_err()).map(|(k, _)| k.as_str()).collect();
+        assert_eq!(failed, ["bad"]);
+
+        let coll = client.collector.lock().await;
+        assert!(coll.values().all(|e| e.batch_id.as_deref() == Some(batch_id.as_str())));
+        assert_eq!(coll["bad"].outcome, Outcome::TransportError);
+        assert_eq!(coll["slow2"].outcome, Outcome::Success);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_dispatch_and_aborted_entries_are_not_pending() {
+        let (server, _gate) = gated_server().await;
+        let client = TrackedClient::new().unwrap();
+        let requests = (0..4)
+            .map(|i| (format!("slow{}", i), client.inner.get(server.url(&format!("/slow{}", i)))))
+            .collect();
+        let handle = client.tracked_send_all_with_handle(requests, 2);
+
+        // оба запроса должны дойти до сервера, прежде чем их оборвут
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while server.requests().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let progress = handle.progress();
+        assert_eq!((progress.in_flight, progress.remaining), (2, 2));
+
+        handle.cancel(true);
+        let results = handle.await_all().await.unwrap();
+        assert!(results.iter().all(|(_, r)| r.is_err()));
+
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.len(), 2);
+        for entry in coll.values() {
+            assert_eq!(entry.outcome, Outcome::TransportError);
+            assert_eq!(entry.error.as_deref(), Some("Batch request cancelled"));
+        }
+    }
+
+    #[tokio::test]
+    async fn send_all_caps_concurrency_and_keeps_input_order() {
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (server_active, server_peak) = (active.clone(), peak.clone());
+        let server = TestServer::start(move |req| {
+            let (active, peak) = (server_active.clone(), server_peak.clone());
+            async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                // чем меньше номер, тем дольше ответ: завершаются в обратном порядке
+                let n: u64 = req.path.trim_start_matches("/r").parse().unwrap_or(0);
+                tokio::time::sleep(Duration::from_millis(20 + (10 - n) * 15)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                TestResponse::ok(format!("body {}", n))
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let mut requests: Vec<(String, RequestBuilder)> = (0..10)
+            .map(|i| (format!("r{}", i), client.inner.get(server.url(&format!("/r{}", i)))))
+            .collect();
+        requests.insert(5, ("bad".to_string(), client.inner.get(closed_url().await)));
+
+        let results = client.tracked_send_all(requests, 3).await;
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert!(peak.load(Ordering::SeqCst) >= 2);
+
+        let keys: Vec<&str> = results.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["r0", "r1", "r2", "r3", "r4", "bad", "r5", "r6", "r7", "r8", "r9"]);
+        for (key, result) in &results {
+            match key.as_str() {
+                "bad" => assert!(result.is_err()),
+                _ => assert_eq!(result.as_ref().unwrap().body, format!("body {}", &key[1..])),
+            }
+        }
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.len(), 11);
+        assert_eq!(coll["bad"].outcome, Outcome::TransportError);
+    }
+}
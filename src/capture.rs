@@ -0,0 +1,211 @@
+use crate::errors::EntryError;
+use crate::limits::{decode_text, looks_binary};
+use crate::{sha256_hex, timestamp, BodyEncoding, BodyTruncation, FailedPhase, ResponseData, ResponseHead, TrackedClient};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use futures_util::StreamExt;
+use reqwest::{RequestBuilder, Response, ResponseBuilderExt};
+use std::time::Instant;
+
+impl TrackedClient {
+    // Как tracked_send, но вызывающему отдаётся обычный Response. С set_capture_body тело
+    // вычитывается заранее и попадает в запись, а Response собирается заново из тех же байт.
+    pub async fn tracked_send_response(&self, key: &str, builder: RequestBuilder) -> Result<Response> {
+        let req = self.build_tracked(key, builder).await?;
+        self.tracked_execute(key, req).await
+    }
+
+    // Тело уложилось в max_bytes — пишется целиком; иначе в запись идут первые max_bytes
+    // с пометкой об обрезке, а вызывающий дочитывает остаток из живого потока.
+    pub(crate) async fn capture_response(
+        &self,
+        key: &str,
+        seq: u64,
+        wal_id: Option<&str>,
+        resp: Response,
+        start: Instant,
+        max_bytes: usize,
+    ) -> Result<Response> {
+        let head = Self::response_head(&resp);
+        let ttfb_ms = start.elapsed().as_millis() as u64;
+        let headers_at = Instant::now();
+        let url = resp.url().clone();
+        let mut rebuilt = http::Response::builder().status(resp.status()).version(resp.version()).url(url.clone());
+        if let Some(map) = rebuilt.headers_mut() {
+            *map = resp.headers().clone();
+        }
+
+        let mut stream = resp.bytes_stream();
+        let mut chunks: Vec<Bytes> = Vec::new();
+        let mut buffered = 0usize;
+        let mut complete = false;
+        while buffered <= max_bytes {
+            match stream.next().await {
+                Some(Ok(chunk)) => {
+                    buffered += chunk.len();
+                    chunks.push(chunk);
+                }
+                Some(Err(e)) => {
+                    // статус и заголовки уже пришли, их не теряем
+                    let partial = head.without_body(url.to_string(), start, ttfb_ms)?;
+                    let error = EntryError::from_error(&e).in_phase(FailedPhase::BodyRead);
+                    self.finish_entry_with_error(key, seq, wal_id, partial, error).await?;
+                    let message = format!("Failed to read response body: {}", e);
+                    return Err(anyhow::Error::new(e).context(message));
+                }
+                None => {
+                    complete = true;
+                    break;
+                }
+            }
+        }
+
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = head;
+        let full = chunks.concat();
+        // обрезано, только если за лимитом действительно остались байты
+        let truncated_at = (!complete).then_some(max_bytes);
+        let raw = &full[..full.len().min(max_bytes)];
+        let content_type = headers.get("content-type").map(String::as_str);
+        let (body, body_encoding) = if looks_binary(raw, content_type) {
+            self.store_binary(raw)
+        } else {
+            (decode_text(raw, content_type), BodyEncoding::Utf8)
+        };
+        let response_body_sha256 = (complete && !raw.is_empty() && self.settings().hash_bodies).then(|| sha256_hex(raw));
+        let (response_time, response_time_ms) = timestamp()?;
+        let resp_data = ResponseData {
+            status,
+            headers,
+            body,
+            body_encoding,
+            response_body_bytes: if complete { buffered as u64 } else { 0 },
+            response_body_sha256,
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(url.to_string()),
+            response_time,
+            response_time_ms,
+            duration_ms: start.elapsed().as_millis() as u64,
+            ttfb_ms: Some(ttfb_ms),
+            body_ms: Some(headers_at.elapsed().as_millis() as u64),
+            body_truncated: truncated_at.is_some(),
+            truncated_by: truncated_at.map(|_| BodyTruncation::CaptureLimit),
+            body_truncated_at: truncated_at,
+            ..Default::default()
+        };
+        self.finish_entry(key, seq, wal_id, resp_data).await?;
+
+        let body = if complete {
+            reqwest::Body::from(full)
+        } else {
+            let head = futures_util::stream::iter(chunks.into_iter().map(Ok::<Bytes, reqwest::Error>));
+            reqwest::Body::wrap_stream(head.chain(stream))
+        };
+        let rebuilt = rebuilt.body(body).map_err(|e| anyhow!("Failed to rebuild response: {}", e))?;
+        Ok(Response::from(rebuilt))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{TestResponse, TestServer};
+    use crate::{BodyTruncation, FailedPhase, TrackedClient};
+
+    fn client_with_cap(max_bytes: usize) -> TrackedClient {
+        let client = TrackedClient::new().unwrap();
+        client.set_capture_body(true, max_bytes);
+        client
+    }
+
+    #[tokio::test]
+    async fn captured_body_is_logged_and_still_readable() {
+        let server = TestServer::start(|_| async { TestResponse::ok(r#"{"ok":true}"#).header("Content-Type", "application/json") }).await;
+        let client = client_with_cap(1024);
+        let resp = client.tracked_send_response("k", client.inner.get(server.url("/data"))).await.unwrap();
+        assert_eq!(resp.url().as_str(), server.url("/data"));
+        assert_eq!(resp.headers()["content-type"], "application/json");
+
+        let entry = client.collector.lock().await.get("k").cloned().unwrap();
+        let logged = entry.response_data.unwrap();
+        assert_eq!(logged.body, r#"{"ok":true}"#);
+        assert!(!logged.body_truncated);
+
+        let value: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(value["ok"], true);
+    }
+
+    #[tokio::test]
+    async fn body_equal_to_cap_is_not_truncated() {
+        let server = TestServer::start(|_| async { TestResponse::ok("0123456789") }).await;
+        let client = client_with_cap(10);
+        let resp = client.tracked_send_response("k", client.inner.get(server.url("/"))).await.unwrap();
+        let entry = client.collector.lock().await.get("k").cloned().unwrap();
+        let logged = entry.response_data.unwrap();
+        assert_eq!(logged.body, "0123456789");
+        assert!(!logged.body_truncated);
+        assert_eq!(logged.truncated_by, None);
+        assert_eq!(resp.text().await.unwrap(), "0123456789");
+    }
+
+    #[tokio::test]
+    async fn oversized_body_falls_back_to_pass_through() {
+        let body = "x".repeat(64 * 1024);
+        let expected = body.clone();
+        let server = TestServer::start(move |_| {
+            let body = body.clone();
+            async move { TestResponse::ok(body) }
+        })
+        .await;
+        let client = client_with_cap(100);
+        let resp = client.tracked_send_response("k", client.inner.get(server.url("/"))).await.unwrap();
+        let entry = client.collector.lock().await.get("k").cloned().unwrap();
+        let logged = entry.response_data.unwrap();
+        assert_eq!(logged.body.len(), 100);
+        assert!(logged.body_truncated);
+        assert_eq!(logged.truncated_by, Some(BodyTruncation::CaptureLimit));
+        assert_eq!(resp.text().await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn body_read_error_keeps_status_headers_and_source() {
+        let server = TestServer::start(|_| async {
+            TestResponse::new(502).header("X-Trace", "t1").body("x".repeat(1000)).cut_after(10)
+        })
+        .await;
+        let client = client_with_cap(4096);
+        let err = client.tracked_send_response("k", client.inner.get(server.url("/"))).await.unwrap_err();
+        assert!(err.chain().any(|cause| cause.downcast_ref::<reqwest::Error>().is_some()), "{:#}", err);
+
+        let entry = client.collector.lock().await.get("k").cloned().unwrap();
+        assert!(entry.error.is_some());
+        assert_eq!(entry.failed_phase, Some(FailedPhase::BodyRead));
+        let logged = entry.response_data.unwrap();
+        assert_eq!(logged.status, 502);
+        assert_eq!(logged.headers["x-trace"], "t1");
+    }
+
+    #[tokio::test]
+    async fn without_capture_body_execute_leaves_body_unread() {
+        let server = TestServer::start(|_| async { TestResponse::ok("hello") }).await;
+        let client = TrackedClient::new().unwrap();
+        let resp = client.tracked_send_response("k", client.inner.get(server.url("/"))).await.unwrap();
+        let entry = client.collector.lock().await.get("k").cloned().unwrap();
+        assert_eq!(entry.response_data.unwrap().body, "");
+        assert_eq!(resp.text().await.unwrap(), "hello");
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{LoggedText, SendOptions, TrackedClient};
+use anyhow::Result;
+use reqwest::header::{HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use reqwest::RequestBuilder;
+use std::collections::{HashMap, VecDeque};
+
+const DEFAULT_VALIDATOR_CACHE_CAPACITY: usize = 256;
+
+struct CachedResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+// Валидаторы и тело последнего 200 по URL; при переполнении вытесняется самый старый
+pub(crate) struct ValidatorCache {
+    capacity: usize,
+    entries: HashMap<String, CachedResponse>,
+    order: VecDeque<String>,
+}
+
+impl Default for ValidatorCache {
+    fn default() -> Self {
+        ValidatorCache {
+            capacity: DEFAULT_VALIDATOR_CACHE_CAPACITY,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+}
+
+impl ValidatorCache {
+    fn touch(&mut self, url: &str) {
+        self.order.retain(|u| u != url);
+        self.order.push_back(url.to_string());
+    }
+
+    fn insert(&mut self, url: &str, cached: CachedResponse) {
+        self.entries.insert(url.to_string(), cached);
+        self.touch(url);
+        self.shrink();
+    }
+
+    fn shrink(&mut self) {
+        while self.entries.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl TrackedClient {
+    fn validators(&self) -> std::sync::MutexGuard<'_, ValidatorCache> {
+        self.validators.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_validator_cache_capacity(&self, capacity: usize) {
+        let mut cache = self.validators();
+        cache.capacity = capacity;
+        cache.shrink();
+    }
+
+    pub fn clear_validator_cache(&self) {
+        let mut cache = self.validators();
+        cache.entries.clear();
+        cache.order.clear();
+    }
+
+    // Подставляет If-None-Match / If-Modified-Since из прошлого ответа по этому URL.
+    // На 304 возвращается тело из кэша, статус остаётся 304.
+    pub async fn tracked_send_conditional(&self, key: &str, builder: RequestBuilder) -> Result<LoggedText> {
+        self.sealing(self.send_conditional(key, builder)).await
+    }
+
+    async fn send_conditional(&self, key: &str, builder: RequestBuilder) -> Result<LoggedText> {
+        let mut req = self.build_tracked(key, builder).await?;
+        let url = req.url().to_string();
+        let mut conditional = false;
+        if let Some(cached) = self.validators().entries.get(&url) {
+            let headers = req.headers_mut();
+            if let Some(etag) = cached.etag.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.entry(IF_NONE_MATCH).or_insert(etag);
+                conditional = true;
+            }
+            if let Some(date) = cached.last_modified.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.entry(IF_MODIFIED_SINCE).or_insert(date);
+                conditional = true;
+            }
+        }
+
+        let mut logged = self.send_logged(key, req, &SendOptions::default()).await?;
+
+        if logged.status == 304 && conditional {
+            let body = {
+                let mut cache = self.validators();
+                cache.touch(&url);
+                cache.entries.get(&url).map(|c| c.body.clone())
+            };
+            if let Some(body) = body {
+                logged.body = body;
+                self.update_entry(&logged.key, logged.seq, |entry| {
+                    if let Some(resp) = entry.response_data.as_mut() {
+                        resp.served_from_cache = true;
+                    }
+                })
+                .await;
+            }
+        } else if logged.status == 200 {
+            let etag = logged.headers.get("etag").cloned();
+            let last_modified = logged.headers.get("last-modified").cloned();
+            if etag.is_some() || last_modified.is_some() {
+                self.validators().insert(
+                    &url,
+                    CachedResponse {
+                        etag,
+                        last_modified,
+                        body: logged.body.clone(),
+                    },
+                );
+            }
+        }
+        Ok(logged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    // Сервер с ETag по версии ресурса: на совпавший If-None-Match отвечает 304
+    async fn etag_server(version: Arc<AtomicU64>) -> TestServer {
+        TestServer::start(move |req| {
+            let v = version.load(Ordering::SeqCst);
+            async move {
+                let etag = format!("\"v{}\"", v);
+                if req.header("if-none-match") == Some(etag.as_str()) {
+                    return TestResponse::new(304).header("ETag", &etag);
+                }
+                TestResponse::ok(format!("content {}{}", v, req.path)).header("ETag", &etag)
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn not_modified_serves_the_cached_body() {
+        let version = Arc::new(AtomicU64::new(1));
+        let server = etag_server(version.clone()).await;
+        let client = TrackedClient::new().unwrap();
+        let url = server.url("/res");
+
+        let first = client.tracked_send_conditional("a", client.inner.get(&url)).await.unwrap();
+        assert_eq!((first.status, first.body.as_str()), (200, "content 1/res"));
+
+        let second = client.tracked_send_conditional("b", client.inner.get(&url)).await.unwrap();
+        assert_eq!((second.status, second.body.as_str()), (304, "content 1/res"));
+        assert_eq!(server.requests()[1].header("if-none-match"), Some("\"v1\""));
+        let entry = client.collector.lock().await["b"].clone();
+        let resp = entry.response_data.unwrap();
+        assert_eq!(resp.status, 304);
+        assert!(resp.served_from_cache);
+
+        version.store(2, Ordering::SeqCst);
+        let third = client.tracked_send_conditional("c", client.inner.get(&url)).await.unwrap();
+        assert_eq!((third.status, third.body.as_str()), (200, "content 2/res"));
+        assert!(!client.collector.lock().await["c"].response_data.as_ref().unwrap().served_from_cache);
+    }
+
+    #[tokio::test]
+    async fn last_modified_is_sent_back() {
+        let server = TestServer::start(|req| async move {
+            if req.header("if-modified-since").is_some() {
+                return TestResponse::new(304);
+            }
+            TestResponse::ok("dated").header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send_conditional("a", client.inner.get(server.url("/"))).await.unwrap();
+        let again = client.tracked_send_conditional("b", client.inner.get(server.url("/"))).await.unwrap();
+        assert_eq!((again.status, again.body.as_str()), (304, "dated"));
+        assert_eq!(server.requests()[1].header("if-modified-since"), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+    }
+
+    #[tokio::test]
+    async fn cache_is_bounded_and_clearable() {
+        let server = etag_server(Arc::new(AtomicU64::new(1))).await;
+        let client = TrackedClient::new().unwrap();
+        client.set_validator_cache_capacity(2);
+        for path in ["/a", "/b", "/c"] {
+            client.tracked_send_conditional(path, client.inner.get(server.url(path))).await.unwrap();
+        }
+        // /a вытеснен — запрос уходит без валидатора
+        client.tracked_send_conditional("a2", client.inner.get(server.url("/a"))).await.unwrap();
+        assert_eq!(server.requests()[3].header("if-none-match"), None);
+        let c2 = client.tracked_send_conditional("c2", client.inner.get(server.url("/c"))).await.unwrap();
+        assert_eq!(c2.status, 304);
+
+        client.clear_validator_cache();
+        let c3 = client.tracked_send_conditional("c3", client.inner.get(server.url("/c"))).await.unwrap();
+        assert_eq!(c3.status, 200);
+        assert_eq!(server.requests()[5].header("if-none-match"), None);
+    }
+}
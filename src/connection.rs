@@ -0,0 +1,40 @@
+use crate::TrackedClient;
+use hyper_util::client::legacy::connect::HttpInfo;
+use reqwest::Response;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+// Соединения (локальный адрес, удалённый адрес), по которым уже пришёл ответ
+pub(crate) type SeenConnections = HashSet<(SocketAddr, SocketAddr)>;
+
+// Пометка в extensions ответа, ставится в execute_tracked
+#[derive(Debug, Clone, Copy)]
+struct ConnectionReused(bool);
+
+impl TrackedClient {
+    // reqwest не сообщает, взято ли соединение из пула, поэтому признак выводится из
+    // пары адресов TCP: та же пара, что у прошлого ответа, — то же соединение.
+    // Это оценка: после закрытия ОС может выдать тот же локальный порт снова (ложное true),
+    // а без HttpInfo (нестандартный коннектор) признака нет вовсе.
+    // Через прокси удалённый адрес — прокси, то есть речь о соединении с ним.
+    pub(crate) fn mark_connection(&self, resp: &mut Response) {
+        let Some(info) = resp.extensions().get::<HttpInfo>() else {
+            return;
+        };
+        let id = (info.local_addr(), info.remote_addr());
+        let fresh = self
+            .connections
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id);
+        resp.extensions_mut().insert(ConnectionReused(!fresh));
+    }
+}
+
+pub(crate) fn local_addr(resp: &Response) -> Option<String> {
+    resp.extensions().get::<HttpInfo>().map(|info| info.local_addr().to_string())
+}
+
+pub(crate) fn connection_reused(resp: &Response) -> Option<bool> {
+    resp.extensions().get::<ConnectionReused>().map(|r| r.0)
+}
@@ -0,0 +1,156 @@
+use crate::cookies::load_cookie_store;
+use crate::save::write_atomic;
+use crate::{dump_cookie_store, TrackedClient};
+use anyhow::{bail, Context, Result};
+use cookie_store::CookieStore;
+use reqwest::Client;
+use reqwest_cookie_store::CookieStoreMutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex as AsyncMutex;
+
+// Не чаще одной записи файла кук за это время
+pub const DEFAULT_COOKIE_FILE_DEBOUNCE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct CookieFileOptions {
+    pub debounce: Duration,
+    // повреждённый файл не ошибка, а пустое хранилище; файл перезапишется при первом изменении
+    pub reset_if_corrupt: bool,
+}
+
+impl Default for CookieFileOptions {
+    fn default() -> Self {
+        CookieFileOptions { debounce: DEFAULT_COOKIE_FILE_DEBOUNCE, reset_if_corrupt: false }
+    }
+}
+
+impl CookieFileOptions {
+    pub fn new() -> Self {
+        CookieFileOptions::default()
+    }
+
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn reset_if_corrupt(mut self, reset: bool) -> Self {
+        self.reset_if_corrupt = reset;
+        self
+    }
+}
+
+pub(crate) struct CookieFile {
+    path: PathBuf,
+    debounce: Duration,
+    // dump_cookies, совпадающий с содержимым файла
+    saved: String,
+    last_write: Option<Instant>,
+    // отложенная запись уже запланирована
+    scheduled: bool,
+    // записи по очереди: более поздняя всегда несёт более свежий дамп
+    writing: Arc<AsyncMutex<()>>,
+}
+
+fn lock_cookie_file(file: &Mutex<Option<CookieFile>>) -> MutexGuard<'_, Option<CookieFile>> {
+    file.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// После каждого ответа: хранилище разошлось с файлом — запись, не раньше debounce после прошлой
+pub(crate) fn note_cookies(file: &Arc<Mutex<Option<CookieFile>>>, cookie_store: &Arc<CookieStoreMutex>) {
+    let mut guard = lock_cookie_file(file);
+    let Some(state) = guard.as_mut().filter(|s| !s.scheduled) else {
+        return;
+    };
+    match dump_cookie_store(cookie_store) {
+        Ok(dump) if dump != state.saved => {}
+        _ => return,
+    }
+    // без рантайма записать нечем; попробуем после следующего ответа
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    let delay = state
+        .last_write
+        .map(|at| state.debounce.saturating_sub(at.elapsed()))
+        .unwrap_or_default();
+    state.scheduled = true;
+    let (file, cookie_store) = (file.clone(), cookie_store.clone());
+    runtime.spawn(async move {
+        tokio::time::sleep(delay).await;
+        // ошибка записи оставляет файл прежним, следующее изменение попробует снова
+        let _ = persist(&file, &cookie_store).await;
+    });
+}
+
+// Ok(false) — файла кук нет или он уже совпадает с хранилищем
+async fn persist(file: &Mutex<Option<CookieFile>>, cookie_store: &CookieStoreMutex) -> Result<bool> {
+    let Some((path, writing)) = lock_cookie_file(file).as_mut().map(|s| {
+        s.scheduled = false;
+        (s.path.clone(), s.writing.clone())
+    }) else {
+        return Ok(false);
+    };
+    let _writing = writing.lock().await;
+    let dump = dump_cookie_store(cookie_store)?;
+    if lock_cookie_file(file).as_ref().is_some_and(|s| s.saved == dump) {
+        return Ok(false);
+    }
+    write_atomic(&path, dump.as_bytes()).await?;
+    if let Some(state) = lock_cookie_file(file).as_mut() {
+        state.saved = dump;
+        state.last_write = Some(Instant::now());
+    }
+    Ok(true)
+}
+
+fn load_cookie_file(path: &Path, opts: &CookieFileOptions) -> Result<CookieStore> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(CookieStore::new(None)),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read cookie file {}", path.display())),
+    };
+    match load_cookie_store(&text) {
+        Ok(store) => Ok(store),
+        Err(_) if opts.reset_if_corrupt => Ok(CookieStore::new(None)),
+        Err(e) => bail!(
+            "Cookie file {} is corrupted ({:#}); delete it or use CookieFileOptions::reset_if_corrupt(true) to start with an empty jar",
+            path.display(),
+            e
+        ),
+    }
+}
+
+impl TrackedClient {
+    pub fn with_cookie_file(path: impl AsRef<Path>) -> Result<Self> {
+        TrackedClient::with_cookie_file_opts(path, CookieFileOptions::default())
+    }
+
+    // Клиент без прокси с куками из path (если файл есть); после ответов, изменивших
+    // хранилище, файл атомарно переписывается в формате dump_cookies не чаще opts.debounce
+    pub fn with_cookie_file_opts(path: impl AsRef<Path>, opts: CookieFileOptions) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let jar = Arc::new(CookieStoreMutex::new(load_cookie_file(&path, &opts)?));
+        let provider = jar.clone();
+        let client = TrackedClient::assemble(move || Client::builder().cookie_provider(provider.clone()), jar, None)?;
+        // загруженное считается уже сохранённым: файл трогается только при изменении
+        let saved = dump_cookie_store(&client.cookie_store)?;
+        *lock_cookie_file(&client.cookie_file) = Some(CookieFile {
+            path,
+            debounce: opts.debounce,
+            saved,
+            last_write: None,
+            scheduled: false,
+            writing: Arc::new(AsyncMutex::new(())),
+        });
+        Ok(client)
+    }
+
+    // Записать изменения сейчас, не дожидаясь debounce (например, перед выходом из CLI);
+    // false — файл кук не задан или уже актуален
+    pub async fn flush_cookie_file(&self) -> Result<bool> {
+        persist(&self.cookie_file, &self.cookie_store).await
+    }
+}
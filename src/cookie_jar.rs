@@ -0,0 +1,515 @@
+use crate::TrackedClient;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use cookie_store::{Cookie, CookieDomain, CookieExpiration, CookieStore};
+use indexmap::IndexMap;
+use reqwest::Url;
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+use std::sync::MutexGuard;
+
+// Кука хранилища в собственном формате крейта, не зависящем от сериализации cookie_store
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CookieRecord {
+    pub name: String,
+    pub value: String,
+    // без ведущей точки
+    pub domain: String,
+    pub path: String,
+    // None — сессионная
+    pub expires: Option<DateTime<Utc>>,
+    pub secure: bool,
+    pub http_only: bool,
+    // без атрибута Domain: только для этого хоста, без поддоменов
+    pub host_only: bool,
+}
+
+impl CookieRecord {
+    pub(crate) fn from_store(cookie: &Cookie<'_>) -> Self {
+        CookieRecord {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: String::from(&cookie.domain),
+            path: String::from(&cookie.path),
+            expires: match &cookie.expires {
+                CookieExpiration::AtUtc(at) => DateTime::from_timestamp(at.unix_timestamp(), 0),
+                CookieExpiration::SessionEnd => None,
+            },
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            host_only: matches!(cookie.domain, CookieDomain::HostOnly(_)),
+        }
+    }
+
+    // Обратно в формат cookie_store, как если бы кука пришла с её домена
+    pub(crate) fn to_store(&self) -> Result<Cookie<'static>> {
+        let opts = CookieOpts {
+            path: Some(self.path.clone()),
+            expires: self.expires,
+            secure: self.secure,
+            http_only: self.http_only,
+        };
+        let domain = (!self.host_only).then_some(self.domain.as_str());
+        let header = set_cookie_header(&self.name, &self.value, domain, &opts)?;
+        Cookie::parse(header, &domain_url(&self.domain)?)
+            .map(Cookie::into_owned)
+            .map_err(|e| anyhow!("Invalid cookie record '{}' for {}: {}", self.name, self.domain, e))
+    }
+}
+
+// Атрибуты куки для set_cookie; домен — хост URL (host-only)
+#[derive(Debug, Clone, Default)]
+pub struct CookieOpts {
+    // None — "/"
+    pub path: Option<String>,
+    // None — сессионная
+    pub expires: Option<DateTime<Utc>>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl CookieOpts {
+    pub fn new() -> Self {
+        CookieOpts::default()
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn expires(mut self, expires: DateTime<Utc>) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+}
+
+// token из RFC 6265 / RFC 7230
+fn valid_cookie_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b))
+}
+
+// cookie-octet, допускаются обрамляющие кавычки
+fn valid_cookie_value(value: &str) -> bool {
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+    inner
+        .bytes()
+        .all(|b| b.is_ascii_graphic() && !matches!(b, b'"' | b',' | b';' | b'\\'))
+}
+
+// Set-Cookie из проверенных частей; domain задаёт куку для домена с поддоменами
+pub(crate) fn set_cookie_header(name: &str, value: &str, domain: Option<&str>, opts: &CookieOpts) -> Result<String> {
+    if !valid_cookie_name(name) {
+        bail!("Invalid cookie name '{}'", name);
+    }
+    if !valid_cookie_value(value) {
+        bail!("Invalid value for cookie '{}'", name);
+    }
+    let path = opts.path.as_deref().unwrap_or("/");
+    if !path.starts_with('/') || path.bytes().any(|b| b == b';' || b.is_ascii_control()) {
+        bail!("Invalid cookie path '{}'", path);
+    }
+    let mut header = format!("{}={}; Path={}", name, value, path);
+    if let Some(domain) = domain {
+        header.push_str(&format!("; Domain={}", normalize_domain(domain)));
+    }
+    if let Some(expires) = opts.expires {
+        header.push_str(&format!("; Expires={}", expires.format("%a, %d %b %Y %H:%M:%S GMT")));
+    }
+    if opts.secure {
+        header.push_str("; Secure");
+    }
+    if opts.http_only {
+        header.push_str("; HttpOnly");
+    }
+    Ok(header)
+}
+
+// "api.example.com" или ".example.com" -> URL для сверки по правилам домена кук
+pub(crate) fn domain_url(domain: &str) -> Result<Url> {
+    let host = normalize_domain(domain);
+    Url::parse(&format!("https://{}/", host)).with_context(|| format!("Invalid cookie domain '{}'", domain))
+}
+
+fn normalize_domain(domain: &str) -> String {
+    domain.trim().trim_start_matches('.').to_ascii_lowercase()
+}
+
+// Домен куки совпадает с domain (уже нормализованным) или является его поддоменом
+fn in_domain(cookie_domain: &str, domain: &str) -> bool {
+    cookie_domain
+        .strip_suffix(domain)
+        .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+}
+
+// Сначала самый точный домен, затем самый длинный путь — как при отправке
+fn most_specific<'a>(candidates: impl Iterator<Item = &'a Cookie<'static>>) -> Option<&'a Cookie<'static>> {
+    candidates.max_by_key(|c| (String::from(&c.domain).len(), String::from(&c.path).len()))
+}
+
+// Истёкшие куки остаются в хранилище и попадают в dump_cookies, пока их не удалить
+pub(crate) fn prune_expired(cookie_store: &CookieStoreMutex) -> Result<usize> {
+    let mut store = cookie_store
+        .lock()
+        .map_err(|e| anyhow!("Cookie store lock error: {}", e))?;
+    let expired: Vec<(String, String, String)> = store
+        .iter_any()
+        .filter(|c| c.is_expired())
+        .map(|c| (String::from(&c.domain), String::from(&c.path), c.name().to_string()))
+        .collect();
+    for (domain, path, name) in &expired {
+        store.remove(domain, path, name);
+    }
+    Ok(expired.len())
+}
+
+impl TrackedClient {
+    pub(crate) fn lock_cookie_store(&self) -> Result<MutexGuard<'_, CookieStore>> {
+        self.cookie_store
+            .lock()
+            .map_err(|e| anyhow!("Cookie store lock error: {}", e))
+    }
+
+    // Что хранилище приложит к запросу на url: с учётом домена, пути, Secure и истечения
+    pub fn cookies_for_url(&self, url: &Url) -> Result<Vec<(String, String)>> {
+        let store = self.lock_cookie_store()?;
+        Ok(store
+            .get_request_values(url)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect())
+    }
+
+    // Заголовок Cookie в том виде, в каком его собирает reqwest_cookie_store: без
+    // percent-encoding, в порядке хранилища; None — подходящих кук нет
+    pub fn cookie_header_for_url(&self, url: &Url) -> Result<Option<String>> {
+        let header = self
+            .cookies_for_url(url)?
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        Ok((!header.is_empty()).then_some(header))
+    }
+
+    // Кладёт куку так, будто её прислал url: следующий подходящий tracked_send её отправит
+    pub fn set_cookie(&self, url: &Url, name: &str, value: &str, opts: CookieOpts) -> Result<()> {
+        let header = set_cookie_header(name, value, None, &opts)?;
+        self.lock_cookie_store()?
+            .parse(&header, url)
+            .map_err(|e| anyhow!("Failed to set cookie '{}' for {}: {}", name, url, e))?;
+        Ok(())
+    }
+
+    // "k=v; k2=v2" из браузера — сессионными куками хоста url с путём "/"; при повторе имени
+    // побеждает последнее. Сегменты без "=" или с недопустимым именем пропускаются, значения
+    // берутся как есть (браузер отдаёт и пробелы, и кавычки). Возвращает число импортированных
+    pub fn import_cookie_header(&self, header: &str, url: &Url) -> Result<usize> {
+        let mut pairs: IndexMap<&str, &str> = IndexMap::new();
+        for segment in header.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            match segment.split_once('=') {
+                Some((name, value)) if valid_cookie_name(name.trim()) => {
+                    pairs.insert(name.trim(), value.trim());
+                }
+                _ => {}
+            }
+        }
+        // разбор до блокировки: хранилище либо получает куку целиком, либо не трогается
+        let cookies: Vec<Cookie<'static>> = pairs
+            .iter()
+            .filter_map(|(name, value)| Cookie::parse(format!("{}={}; Path=/", name, value), url).ok())
+            .map(Cookie::into_owned)
+            .collect();
+        let mut store = self.lock_cookie_store()?;
+        Ok(cookies
+            .into_iter()
+            .filter(|cookie| store.insert(cookie.clone(), url).is_ok())
+            .count())
+    }
+
+    pub fn get_cookie(&self, domain: &str, name: &str) -> Result<Option<String>> {
+        Ok(self.get_cookie_full(domain, name)?.map(|c| c.value))
+    }
+
+    // Живая кука, которая подошла бы хосту domain: своя (host-only) или заданная для
+    // родительского домена (Domain=.example.com подходит api.example.com); путь любой
+    pub fn get_cookie_full(&self, domain: &str, name: &str) -> Result<Option<CookieRecord>> {
+        let url = domain_url(domain)?;
+        let store = self.lock_cookie_store()?;
+        let found = most_specific(
+            store
+                .iter_unexpired()
+                .filter(|c| c.name() == name && c.domain.matches(&url)),
+        );
+        Ok(found.map(CookieRecord::from_store))
+    }
+
+    // Все куки хранилища, включая истёкшие (как dump_cookies), в формате крейта
+    pub fn cookies(&self) -> Result<Vec<CookieRecord>> {
+        let store = self.lock_cookie_store()?;
+        Ok(store.iter_any().map(CookieRecord::from_store).collect())
+    }
+
+    pub fn prune_expired_cookies(&self) -> Result<usize> {
+        prune_expired(&self.cookie_store)
+    }
+
+    // Точное совпадение домена (без ведущей точки), пути и имени
+    pub fn remove_cookie(&self, domain: &str, path: &str, name: &str) -> Result<bool> {
+        let mut store = self.lock_cookie_store()?;
+        Ok(store.remove(&normalize_domain(domain), path, name).is_some())
+    }
+
+    // Сколько кук было в хранилище, включая истёкшие
+    pub fn clear_cookies(&self) -> Result<usize> {
+        let mut store = self.lock_cookie_store()?;
+        let count = store.iter_any().count();
+        store.clear();
+        Ok(count)
+    }
+
+    // Куки домена и его поддоменов; остальные сайты не трогаются
+    pub fn clear_cookies_for_domain(&self, domain: &str) -> Result<usize> {
+        let domain = normalize_domain(domain);
+        let mut store = self.lock_cookie_store()?;
+        let keys: Vec<(String, String, String)> = store
+            .iter_any()
+            .map(|c| (String::from(&c.domain), String::from(&c.path), c.name().to_string()))
+            .filter(|(d, _, _)| in_domain(d, &domain))
+            .collect();
+        for (d, path, name) in &keys {
+            store.remove(d, path, name);
+        }
+        Ok(keys.len())
+    }
+
+    // Как dump_cookies, но только куки домена и его поддоменов
+    pub fn dump_cookies_for_domain(&self, domain: &str) -> Result<String> {
+        self.dump_cookies_for_domains(&[domain])
+    }
+
+    pub fn dump_cookies_for_domains(&self, domains: &[&str]) -> Result<String> {
+        let domains: Vec<String> = domains.iter().map(|d| normalize_domain(d)).collect();
+        let store = self.lock_cookie_store()?;
+        let cookies: Vec<&Cookie<'static>> = store
+            .iter_any()
+            .filter(|c| {
+                let cookie_domain = String::from(&c.domain);
+                domains.iter().any(|d| in_domain(&cookie_domain, d))
+            })
+            .collect();
+        serde_json::to_string(&cookies).context("Failed to serialize cookies array to string")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    // Кука, как будто её прислал from
+    fn receive(client: &TrackedClient, from: &str, set_cookie: &str) {
+        client.lock_cookie_store().unwrap().parse(set_cookie, &url(from)).unwrap();
+    }
+
+    #[test]
+    fn get_cookie_follows_domain_rules() {
+        let client = TrackedClient::new().unwrap();
+        receive(
+            &client,
+            "https://www.example.com/login",
+            "session_id=abc; Domain=.example.com; Path=/; Secure; HttpOnly; Expires=Wed, 01 Jan 2120 00:00:00 GMT",
+        );
+        receive(&client, "https://www.example.com/", "host_only=1; Path=/app");
+
+        assert_eq!(client.get_cookie("api.example.com", "session_id").unwrap().as_deref(), Some("abc"));
+        assert_eq!(client.get_cookie("example.com", "session_id").unwrap().as_deref(), Some("abc"));
+        assert_eq!(client.get_cookie(".EXAMPLE.com", "session_id").unwrap().as_deref(), Some("abc"));
+        assert_eq!(client.get_cookie("badexample.com", "session_id").unwrap(), None);
+        assert_eq!(client.get_cookie("example.org", "session_id").unwrap(), None);
+        assert_eq!(client.get_cookie("api.example.com", "missing").unwrap(), None);
+
+        let full = client.get_cookie_full("api.example.com", "session_id").unwrap().unwrap();
+        assert_eq!(full.domain, "example.com");
+        assert_eq!(full.path, "/");
+        assert_eq!(full.expires, DateTime::from_timestamp(4733510400, 0));
+        assert!(full.secure && full.http_only && !full.host_only);
+
+        // host-only кука не видна поддоменам и соседям
+        let host_only = client.get_cookie_full("www.example.com", "host_only").unwrap().unwrap();
+        assert!(host_only.host_only && host_only.expires.is_none());
+        assert_eq!(host_only.path, "/app");
+        assert_eq!(client.get_cookie("api.example.com", "host_only").unwrap(), None);
+        assert_eq!(client.get_cookie("sub.www.example.com", "host_only").unwrap(), None);
+    }
+
+    #[test]
+    fn most_specific_domain_wins() {
+        let client = TrackedClient::new().unwrap();
+        receive(&client, "https://api.example.com/", "id=parent; Domain=example.com");
+        receive(&client, "https://api.example.com/", "id=own");
+        assert_eq!(client.get_cookie("api.example.com", "id").unwrap().as_deref(), Some("own"));
+        assert_eq!(client.get_cookie("www.example.com", "id").unwrap().as_deref(), Some("parent"));
+    }
+
+    #[tokio::test]
+    async fn set_cookie_is_sent_on_the_next_request() {
+        let server = TestServer::start(|_| async { TestResponse::ok("ok") }).await;
+        let client = TrackedClient::new().unwrap();
+        let api = url(&server.url("/api/login"));
+        client.set_cookie(&api, "token", "sms-123", CookieOpts::new().path("/api").http_only(true)).unwrap();
+        client.set_cookie(&api, "lang", "ru", CookieOpts::new()).unwrap();
+        let dump = client.dump_cookies().unwrap();
+        assert!(dump.contains("token=sms-123") && dump.contains("lang=ru"), "{}", dump);
+
+        client.tracked_send("me", client.inner.get(server.url("/api/me"))).await.unwrap();
+        client.tracked_send("home", client.inner.get(server.url("/"))).await.unwrap();
+        let coll = client.collector.lock().await;
+        let sent = &coll["me"].request_data.cookies;
+        assert_eq!(sent.get("token").map(String::as_str), Some("sms-123"));
+        assert_eq!(sent.get("lang").map(String::as_str), Some("ru"));
+        // кука с путём /api на другие пути не уходит
+        let sent = &coll["home"].request_data.cookies;
+        assert!(!sent.contains_key("token"));
+        assert_eq!(sent.get("lang").map(String::as_str), Some("ru"));
+    }
+
+    #[test]
+    fn set_cookie_rejects_invalid_parts() {
+        let client = TrackedClient::new().unwrap();
+        let site = url("https://example.com/");
+        let err = client.set_cookie(&site, "bad name", "v", CookieOpts::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid cookie name 'bad name'");
+        let err = client.set_cookie(&site, "", "v", CookieOpts::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid cookie name ''");
+        let err = client.set_cookie(&site, "sid", "a;b", CookieOpts::new()).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value for cookie 'sid'");
+        let err = client.set_cookie(&site, "sid", "v", CookieOpts::new().path("api")).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid cookie path 'api'");
+        let err = client.set_cookie(&site, "sid", "v", CookieOpts::new().path("/a;Domain=evil.com")).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid cookie path '/a;Domain=evil.com'");
+        assert!(client.cookies().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn removed_cookies_leave_the_next_request() {
+        let server = TestServer::start(|req| async move {
+            match req.path.as_str() {
+                "/login" => TestResponse::ok("in")
+                    .header("Set-Cookie", "session=s1; Path=/")
+                    .header("Set-Cookie", "theme=dark; Path=/")
+                    .header("Set-Cookie", "scoped=1; Path=/api"),
+                _ => TestResponse::ok("ok"),
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        receive(&client, "https://www.example.com/", "other=keep; Domain=example.com");
+        client.tracked_send("login", client.inner.get(server.url("/login"))).await.unwrap();
+
+        assert!(client.remove_cookie("127.0.0.1", "/", "session").unwrap());
+        assert!(!client.remove_cookie("127.0.0.1", "/", "session").unwrap());
+        // путь должен совпасть точно
+        assert!(!client.remove_cookie("127.0.0.1", "/", "scoped").unwrap());
+
+        client.tracked_send("after-remove", client.inner.get(server.url("/api/me"))).await.unwrap();
+        {
+            let coll = client.collector.lock().await;
+            let sent = &coll["after-remove"].request_data.cookies;
+            assert!(!sent.contains_key("session"));
+            assert_eq!(sent.get("theme").map(String::as_str), Some("dark"));
+            assert_eq!(sent.get("scoped").map(String::as_str), Some("1"));
+        }
+        let sent_header = server.requests().last().unwrap().header("cookie").unwrap_or_default().to_string();
+        assert!(!sent_header.contains("session="), "{}", sent_header);
+        let dump = client.dump_cookies().unwrap();
+        assert!(dump.contains("theme=dark") && !dump.contains("session=s1"), "{}", dump);
+
+        // только 127.0.0.1: кука example.com остаётся
+        assert_eq!(client.clear_cookies_for_domain("127.0.0.1").unwrap(), 2);
+        assert_eq!(client.get_cookie("www.example.com", "other").unwrap().as_deref(), Some("keep"));
+        client.tracked_send("after-clear", client.inner.get(server.url("/api/me"))).await.unwrap();
+        assert!(client.collector.lock().await["after-clear"].request_data.cookies.is_empty());
+        assert_eq!(server.requests().last().unwrap().header("cookie"), None);
+
+        assert_eq!(client.clear_cookies().unwrap(), 1);
+        assert!(client.cookies().unwrap().is_empty());
+        assert_eq!(client.clear_cookies().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn expired_cookies_survive_dump_until_pruned() {
+        let server = TestServer::start(|req| async move {
+            match req.path.as_str() {
+                "/login" => TestResponse::ok("in")
+                    .header("Set-Cookie", "short=1; Path=/; Max-Age=1")
+                    .header("Set-Cookie", "long=2; Path=/; Max-Age=3600"),
+                _ => TestResponse::ok("ok"),
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("login", client.inner.get(server.url("/login"))).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let names = |client: &TrackedClient| {
+            let mut names: Vec<String> = client.cookies().unwrap().into_iter().map(|c| c.name).collect();
+            names.sort();
+            names
+        };
+
+        // dump_cookies сохраняет и истёкшие, загрузка тоже их не отбрасывает
+        let dump = client.dump_cookies().unwrap();
+        let loaded = TrackedClient::from_cookie_json(&dump).unwrap();
+        assert_eq!(names(&loaded), ["long", "short"]);
+        assert_eq!(loaded.prune_expired_cookies().unwrap(), 1);
+        assert_eq!(loaded.prune_expired_cookies().unwrap(), 0);
+        assert_eq!(names(&loaded), ["long"]);
+
+        let auto = TrackedClient::from_cookie_json(&dump).unwrap();
+        auto.tracked_send("before", auto.inner.get(server.url("/ping"))).await.unwrap();
+        assert_eq!(names(&auto), ["long", "short"]);
+        auto.set_prune_expired_cookies(true);
+        auto.tracked_send("after", auto.inner.get(server.url("/ping"))).await.unwrap();
+        assert_eq!(names(&auto), ["long"]);
+        assert!(!auto.dump_cookies().unwrap().contains("short=1"));
+    }
+
+    #[tokio::test]
+    async fn pasted_cookie_header_is_imported_tolerantly() {
+        let server = TestServer::start(|_| async { TestResponse::ok("ok") }).await;
+        let client = TrackedClient::new().unwrap();
+        let target = url(&server.url("/"));
+        let pasted = r#"  a=1; ; b=x y ;junk; c="q" ;a=2; d=1,2; =v; e f=1;"#;
+        assert_eq!(client.import_cookie_header(pasted, &target).unwrap(), 4);
+
+        client.tracked_send("next", client.inner.get(server.url("/me"))).await.unwrap();
+        let sent = client.collector.lock().await["next"].request_data.cookies.clone();
+        let mut sent: Vec<(&str, &str)> = sent.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        sent.sort();
+        assert_eq!(sent, [("a", "2"), ("b", "x y"), ("c", "\"q\""), ("d", "1,2")]);
+
+        let record = client.get_cookie_full("127.0.0.1", "a").unwrap().unwrap();
+        assert!(record.host_only && record.expires.is_none());
+        assert_eq!(record.path, "/");
+        assert_eq!(client.import_cookie_header(" ; ;junk", &target).unwrap(), 0);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::cookies::{cookie_snapshot, CookieChange, CookieChanges, CookieSnapshot};
+use crate::TrackedClient;
+use anyhow::Result;
+use reqwest_cookie_store::CookieStoreMutex;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+pub type CookieChangeCallback = Arc<dyn Fn(CookieChange) + Send + Sync>;
+
+#[derive(Default)]
+pub(crate) struct CookieWatch {
+    callbacks: Vec<CookieChangeCallback>,
+    // хранилище на момент последней проверки
+    last: CookieSnapshot,
+}
+
+fn lock_watch(watch: &Mutex<CookieWatch>) -> MutexGuard<'_, CookieWatch> {
+    watch.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// После каждого ответа: сверяет хранилище с прошлой проверкой и зовёт подписчиков.
+// Снимок берётся под замком watch, чтобы параллельные ответы не откатывали last назад;
+// колбэки — уже без замков хранилища, коллектора и watch
+pub(crate) fn notify_cookie_changes(watch: &Mutex<CookieWatch>, cookie_store: &CookieStoreMutex) -> Result<()> {
+    let (callbacks, changes) = {
+        let mut watch = lock_watch(watch);
+        if watch.callbacks.is_empty() {
+            return Ok(());
+        }
+        let current = cookie_snapshot(cookie_store)?;
+        let changes = CookieChanges::between(&watch.last, &current);
+        watch.last = current;
+        (watch.callbacks.clone(), changes)
+    };
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let CookieChanges { added, modified, removed } = changes;
+    for change in added.into_iter().chain(modified).chain(removed) {
+        for callback in &callbacks {
+            // паника подписчика не должна ронять запрос
+            let _ = catch_unwind(AssertUnwindSafe(|| callback(change.clone())));
+        }
+    }
+    Ok(())
+}
+
+impl TrackedClient {
+    // cb получает каждую добавленную, изменённую или удалённую куку (old_value/new_value = None
+    // у новой/удалённой), замеченную после очередного ответа. Куки, уже лежавшие в хранилище
+    // при первой подписке, добавленными не считаются
+    pub fn on_cookie_change(&self, cb: CookieChangeCallback) -> Result<()> {
+        let mut watch = lock_watch(&self.cookie_watch);
+        if watch.callbacks.is_empty() {
+            watch.last = cookie_snapshot(&self.cookie_store)?;
+        }
+        watch.callbacks.push(cb);
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+use crate::cookie_jar::CookieRecord;
+use crate::{msk_offset, TrackedClient};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::DateTime;
+use cookie_store::{Cookie, CookieStore, RawCookie};
+use reqwest::Client;
+use reqwest_cookie_store::CookieStoreMutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// Значения живых кук по (домен, путь, имя)
+pub(crate) type CookieSnapshot = BTreeMap<(String, String, String), String>;
+
+pub(crate) fn cookie_snapshot(cookie_store: &CookieStoreMutex) -> Result<CookieSnapshot> {
+    let store = cookie_store
+        .lock()
+        .map_err(|e| anyhow!("Cookie store lock error: {}", e))?;
+    Ok(store
+        .iter_unexpired()
+        .map(|c| {
+            let id = (String::from(&c.domain), String::from(&c.path), c.name().to_string());
+            (id, c.value().to_string())
+        })
+        .collect())
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CookieChange {
+    pub domain: String,
+    pub path: String,
+    pub name: String,
+    // None у добавленной (old) и удалённой (new) куки
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+// Что поменялось в хранилище кук от начала запроса до записи ответа
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieChanges {
+    pub added: Vec<CookieChange>,
+    pub modified: Vec<CookieChange>,
+    pub removed: Vec<CookieChange>,
+}
+
+impl CookieChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    pub(crate) fn between(before: &CookieSnapshot, after: &CookieSnapshot) -> Self {
+        let change = |(domain, path, name): &(String, String, String), old: Option<&String>, new: Option<&String>| {
+            CookieChange {
+                domain: domain.clone(),
+                path: path.clone(),
+                name: name.clone(),
+                old_value: old.cloned(),
+                new_value: new.cloned(),
+            }
+        };
+        let mut changes = CookieChanges::default();
+        for (id, new) in after {
+            match before.get(id) {
+                None => changes.added.push(change(id, None, Some(new))),
+                Some(old) if old != new => changes.modified.push(change(id, Some(old), Some(new))),
+                Some(_) => {}
+            }
+        }
+        for (id, old) in before {
+            if !after.contains_key(id) {
+                changes.removed.push(change(id, Some(old), None));
+            }
+        }
+        changes
+    }
+}
+
+// Один заголовок Set-Cookie ответа. Если разобрать не удалось, name и value пусты,
+// а исходная строка лежит в raw вместе с parse_error.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetCookieInfo {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    // RFC3339 (МСК)
+    pub expires: Option<String>,
+    // в секундах
+    pub max_age: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+    // "Strict", "Lax" или "None"
+    pub same_site: Option<String>,
+    #[serde(default)]
+    pub raw: Option<String>,
+    #[serde(default)]
+    pub parse_error: Option<String>,
+}
+
+impl SetCookieInfo {
+    pub(crate) fn parse(raw: &str) -> Self {
+        let cookie = match RawCookie::parse(raw) {
+            Ok(cookie) => cookie,
+            Err(e) => {
+                return SetCookieInfo {
+                    raw: Some(raw.to_string()),
+                    parse_error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+        };
+        let expires = cookie
+            .expires_datetime()
+            .and_then(|t| DateTime::from_timestamp(t.unix_timestamp(), 0))
+            .zip(msk_offset().ok())
+            .map(|(t, msk)| t.with_timezone(&msk).to_rfc3339());
+        SetCookieInfo {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: cookie.domain().map(str::to_string),
+            path: cookie.path().map(str::to_string),
+            expires,
+            max_age: cookie.max_age().map(|d| d.whole_seconds()),
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            same_site: cookie.same_site().map(|s| s.to_string()),
+            raw: None,
+            parse_error: None,
+        }
+    }
+}
+
+// Текущая версия конверта и формат элементов внутри него
+pub const COOKIE_ENVELOPE_VERSION: u64 = 1;
+pub const COOKIE_ENVELOPE_FORMAT: &str = "cookie_store_json";
+
+// Порядок полей фиксирован: {"v":1,"format":"cookie_store_json","cookies":[...]}
+#[derive(Serialize)]
+struct CookieEnvelope<'a> {
+    v: u64,
+    format: &'a str,
+    cookies: Vec<&'a Cookie<'static>>,
+}
+
+// Элементы в формате cookie_store (с raw_cookie) или CookieRecord из cookies()
+fn cookies_from_values(values: Vec<Value>) -> Result<Vec<Cookie<'static>>> {
+    values
+        .into_iter()
+        .map(|v| {
+            if v.get("raw_cookie").is_none() && v.get("name").is_some() {
+                let record: CookieRecord = serde_json::from_value(v).context("Invalid cookie record format")?;
+                return record.to_store();
+            }
+            serde_json::from_value(v).context("Invalid cookie JSON format")
+        })
+        .collect()
+}
+
+fn cookies_from_envelope(obj: serde_json::Map<String, Value>) -> Result<Vec<Cookie<'static>>> {
+    let version = obj
+        .get("v")
+        .and_then(Value::as_u64)
+        .context("Cookie envelope version must be a non-negative integer")?;
+    if version > COOKIE_ENVELOPE_VERSION {
+        bail!(
+            "Unsupported cookie envelope version {} (max supported {})",
+            version,
+            COOKIE_ENVELOPE_VERSION
+        );
+    }
+    match obj.get("format").and_then(Value::as_str) {
+        Some(COOKIE_ENVELOPE_FORMAT) => {}
+        Some(other) => bail!("Unsupported cookie envelope format: {}", other),
+        None => bail!("Cookie envelope is missing the format field"),
+    }
+    match obj.get("cookies") {
+        Some(Value::Array(arr)) => cookies_from_values(arr.clone()),
+        _ => bail!("Cookie envelope is missing the cookies array"),
+    }
+}
+
+// Разбирает любой из поддерживаемых форматов: конверт, массив (dump_cookies) или NDJSON
+pub(crate) fn parse_cookies(cookie_json: &str) -> Result<Vec<Cookie<'static>>> {
+    let trimmed = cookie_json.trim();
+    if trimmed.is_empty() {
+        return Ok(Vec::new());
+    }
+    if trimmed.starts_with('[') {
+        let arr: Vec<Value> = serde_json::from_str(trimmed)
+            .map_err(|e| anyhow!("Failed to load cookies JSON array: {}", e))?;
+        return cookies_from_values(arr);
+    }
+    // Однострочный NDJSON тоже выглядит как объект, конверт отличаем по полю "v"
+    if let Ok(Value::Object(obj)) = serde_json::from_str::<Value>(trimmed) {
+        if obj.contains_key("v") {
+            return cookies_from_envelope(obj);
+        }
+    }
+    let values = trimmed
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| anyhow!("Failed to load cookies JSON: {}", e))
+        })
+        .collect::<Result<Vec<Value>>>()?;
+    cookies_from_values(values)
+}
+
+pub(crate) fn load_cookie_store(cookie_json: &str) -> Result<CookieStore> {
+    let cookies = parse_cookies(cookie_json)?;
+    CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, anyhow::Error>), true)
+}
+
+impl TrackedClient {
+    // Клиент без прокси с куками из любого поддерживаемого формата
+    pub fn from_cookie_json(cookie_json: &str) -> Result<Self> {
+        let jar = Arc::new(CookieStoreMutex::new(load_cookie_store(cookie_json)?));
+        let provider = jar.clone();
+        TrackedClient::assemble(move || Client::builder().cookie_provider(provider.clone()), jar, None)
+    }
+
+    // Куки в версионированном конверте, переживающем смену формата cookie_store
+    pub fn dump_cookies_versioned(&self) -> Result<String> {
+        let store = self.cookie_store
+            .lock()
+            .map_err(|e| anyhow!("Cookie store lock error: {}", e))?;
+
+        let envelope = CookieEnvelope {
+            v: COOKIE_ENVELOPE_VERSION,
+            format: COOKIE_ENVELOPE_FORMAT,
+            cookies: store.iter_any().collect(),
+        };
+        serde_json::to_string(&envelope).context("Failed to serialize cookie envelope")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use reqwest::Url;
+
+    const COOKIE: &str = r#"{"raw_cookie":"sid=abc; Path=/","path":["/",true],"domain":{"HostOnly":"example.com"},"expires":"SessionEnd"}"#;
+
+    fn envelope_v1() -> String {
+        format!(r#"{{"v":1,"format":"cookie_store_json","cookies":[{}]}}"#, COOKIE)
+    }
+
+    #[test]
+    fn versioned_dump_pins_v1_bytes() {
+        let client = TrackedClient::from_cookie_json(COOKIE).unwrap();
+        assert_eq!(client.dump_cookies_versioned().unwrap(), envelope_v1());
+    }
+
+    #[test]
+    fn every_format_loads_the_same_cookies() {
+        let expected = format!("[{}]", COOKIE);
+        for input in [COOKIE.to_string(), format!("\n{}\n\n", COOKIE), expected.clone(), envelope_v1()] {
+            let client = TrackedClient::from_cookie_json(&input).unwrap();
+            assert_eq!(client.dump_cookies().unwrap(), expected, "input: {}", input);
+        }
+    }
+
+    #[tokio::test]
+    async fn from_redis_cookies_accepts_envelope() {
+        let client = TrackedClient::from_redis_cookies("http://127.0.0.1:9".to_string(), &envelope_v1())
+            .await
+            .unwrap();
+        assert_eq!(client.dump_cookies_versioned().unwrap(), envelope_v1());
+    }
+
+    #[test]
+    fn unknown_version_names_both_versions() {
+        let future = format!(r#"{{"v":2,"format":"cookie_store_json","cookies":[{}]}}"#, COOKIE);
+        let err = TrackedClient::from_cookie_json(&future).err().unwrap().to_string();
+        assert_eq!(err, "Unsupported cookie envelope version 2 (max supported 1)");
+    }
+
+    #[test]
+    fn unknown_format_is_rejected() {
+        let err = TrackedClient::from_cookie_json(r#"{"v":1,"format":"netscape","cookies":[]}"#)
+            .err()
+            .unwrap()
+            .to_string();
+        assert_eq!(err, "Unsupported cookie envelope format: netscape");
+    }
+
+    #[test]
+    fn empty_input_is_an_empty_jar() {
+        let client = TrackedClient::from_cookie_json("  \n").unwrap();
+        assert_eq!(client.dump_cookies().unwrap(), "[]");
+    }
+
+    #[tokio::test]
+    async fn own_dump_loads_back_through_from_redis_cookies() {
+        let client = TrackedClient::new().unwrap();
+        let login = Url::parse("http://shop.example/login").unwrap();
+        // тот же хук, которым reqwest сохраняет Set-Cookie из ответа
+        let headers = [
+            HeaderValue::from_static("session=s1; Path=/; HttpOnly"),
+            HeaderValue::from_static("cart=3; Path=/shop"),
+        ];
+        reqwest::cookie::CookieStore::set_cookies(&*client.cookie_store, &mut headers.iter(), &login);
+        let dump = client.dump_cookies().unwrap();
+        assert!(dump.starts_with('['));
+
+        let restored = TrackedClient::from_redis_cookies("http://127.0.0.1:9".to_string(), &dump)
+            .await
+            .unwrap();
+        for path in ["/", "/shop/item"] {
+            let url = login.join(path).unwrap();
+            let mut expected = client.cookies_for_url(&url).unwrap();
+            let mut actual = restored.cookies_for_url(&url).unwrap();
+            expected.sort();
+            actual.sort();
+            assert_eq!(actual, expected, "{}", path);
+        }
+        let shop = login.join("/shop").unwrap();
+        assert_eq!(restored.cookies_for_url(&shop).unwrap().len(), 2);
+        assert_eq!(restored.dump_cookies().unwrap().len(), dump.len());
+
+        // построчный вариант того же дампа по-прежнему загружается
+        let values: Vec<Value> = serde_json::from_str(&dump).unwrap();
+        let ndjson: Vec<String> = values.iter().map(Value::to_string).collect();
+        let from_lines = TrackedClient::from_cookie_json(&ndjson.join("\n")).unwrap();
+        assert_eq!(from_lines.cookies_for_url(&shop).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{bail, Result};
+use reqwest::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CsvColumn {
+    Key,
+    Method,
+    Host,
+    Path,
+    Status,
+    DurationMs,
+    ResponseBytes,
+    ErrorKind,
+    RequestTime,
+}
+
+impl CsvColumn {
+    // Порядок по умолчанию; export_csv_with выводит колонки в порядке CsvOptions::columns
+    pub const ALL: [CsvColumn; 9] = [
+        CsvColumn::Key,
+        CsvColumn::Method,
+        CsvColumn::Host,
+        CsvColumn::Path,
+        CsvColumn::Status,
+        CsvColumn::DurationMs,
+        CsvColumn::ResponseBytes,
+        CsvColumn::ErrorKind,
+        CsvColumn::RequestTime,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CsvColumn::Key => "key",
+            CsvColumn::Method => "method",
+            CsvColumn::Host => "host",
+            CsvColumn::Path => "path",
+            CsvColumn::Status => "status",
+            CsvColumn::DurationMs => "duration_ms",
+            CsvColumn::ResponseBytes => "response_bytes",
+            CsvColumn::ErrorKind => "error_kind",
+            CsvColumn::RequestTime => "request_time",
+        }
+    }
+
+    // Пусто, если ответа нет
+    fn cell(self, key: &str, entry: &RequestResponseData, url: Option<&Url>) -> String {
+        let req = &entry.request_data;
+        let resp = entry.response_data.as_ref();
+        match self {
+            CsvColumn::Key => key.to_string(),
+            CsvColumn::Method => req.method.clone(),
+            CsvColumn::Host => url.and_then(Url::host_str).unwrap_or_default().to_string(),
+            CsvColumn::Path => url.map(Url::path).unwrap_or_default().to_string(),
+            CsvColumn::Status => resp.map(|r| r.status.to_string()).unwrap_or_default(),
+            CsvColumn::DurationMs => resp.map(|r| r.duration_ms.to_string()).unwrap_or_default(),
+            CsvColumn::ResponseBytes => resp.map(|r| r.response_body_bytes.to_string()).unwrap_or_default(),
+            CsvColumn::ErrorKind => entry
+                .error_kind
+                .and_then(|kind| serde_json::to_value(kind).ok())
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            CsvColumn::RequestTime => req.request_time.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    pub columns: Vec<CsvColumn>,
+    // строка с именами колонок
+    pub header: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { columns: CsvColumn::ALL.to_vec(), header: true }
+    }
+}
+
+impl CsvOptions {
+    pub fn new() -> Self {
+        CsvOptions::default()
+    }
+
+    pub fn columns(mut self, columns: &[CsvColumn]) -> Self {
+        self.columns = columns.to_vec();
+        self
+    }
+
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+// RFC 4180: в кавычки, если есть запятая, кавычка или перевод строки; кавычки удваиваются
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn csv_row<I: IntoIterator<Item = String>>(cells: I) -> String {
+    let mut row = cells.into_iter().map(|c| csv_field(&c)).collect::<Vec<_>>().join(",");
+    row.push_str("\r\n");
+    row
+}
+
+impl TrackedClient {
+    pub async fn export_csv(&self) -> Result<String> {
+        self.export_csv_with(&CsvOptions::default()).await
+    }
+
+    // Строка на запись в порядке seq, включая записи без ответа
+    pub async fn export_csv_with(&self, opts: &CsvOptions) -> Result<String> {
+        if opts.columns.is_empty() {
+            bail!("CSV export needs at least one column");
+        }
+        let coll = self.collector.lock().await;
+        let mut entries: Vec<(&String, &RequestResponseData)> = coll.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.seq);
+
+        let mut out = String::new();
+        if opts.header {
+            out.push_str(&csv_row(opts.columns.iter().map(|c| c.name().to_string())));
+        }
+        for (key, entry) in entries {
+            let url = Url::parse(&entry.request_data.endpoint).ok();
+            out.push_str(&csv_row(opts.columns.iter().map(|c| c.cell(key, entry, url.as_ref()))));
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::redact::{redact_cookie_value, redact_header_value};
+use crate::{BodyEncoding, RequestData, TrackedClient};
+use anyhow::{anyhow, Result};
+
+// Их curl выставляет сам по URL и телу
+const CURL_MANAGED_HEADERS: [&str; 3] = ["host", "content-length", "transfer-encoding"];
+
+// Одинарные кавычки sh: внутри буквально всё, включая переводы строк, кроме самой '
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn curl_command(req: &RequestData, names: &[String]) -> String {
+    let mut args = vec!["curl".to_string()];
+    let mut prefix = Vec::new();
+
+    let has_body = req.body.is_some() || req.multipart.is_some() || req.upload.is_some();
+    match req.method.as_str() {
+        "HEAD" => args.push("--head".to_string()),
+        "GET" if !has_body => {}
+        "POST" if has_body => {}
+        method => args.push(format!("-X {}", shell_quote(method))),
+    }
+    args.push(shell_quote(&req.endpoint));
+
+    // content-type multipart с boundary curl -F подставит свой
+    let headers: Vec<(&String, &String)> = if req.headers_ordered.is_empty() {
+        req.headers.iter().collect()
+    } else {
+        req.headers_ordered.iter().map(|(k, v)| (k, v)).collect()
+    };
+    let mut explicit_cookie = false;
+    for (name, value) in headers {
+        let lower = name.to_ascii_lowercase();
+        if CURL_MANAGED_HEADERS.contains(&lower.as_str()) || (lower == "content-type" && req.multipart.is_some()) {
+            continue;
+        }
+        explicit_cookie |= lower == "cookie";
+        let header = format!("{}: {}", name, redact_header_value(names, name, value));
+        args.push(format!("-H {}", shell_quote(&header)));
+    }
+    // явный заголовок Cookie reqwest не дополняет из хранилища
+    if !explicit_cookie && !req.cookies.is_empty() {
+        let mut cookies: Vec<String> = req
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, redact_cookie_value(names, name, value)))
+            .collect();
+        cookies.sort();
+        args.push(format!("--cookie {}", shell_quote(&cookies.join("; "))));
+    }
+
+    if let Some(parts) = &req.multipart {
+        for part in parts {
+            let field = match (&part.text, &part.filename) {
+                (Some(text), None) => format!("{}={}", part.name, text),
+                // содержимое файлов не хранится: подставьте путь к своему файлу
+                (_, filename) => {
+                    let mut field = format!("{}=@{}", part.name, filename.as_deref().unwrap_or("FILE"));
+                    if let Some(mime) = &part.mime {
+                        field.push_str(&format!(";type={}", mime));
+                    }
+                    field
+                }
+            };
+            args.push(format!("-F {}", shell_quote(&field)));
+        }
+    } else if let Some(upload) = &req.upload {
+        args.push(format!("--data-binary {}", shell_quote(&format!("@{}", upload.path))));
+    } else if let Some(body) = &req.body {
+        if req.body_truncated {
+            prefix.push(format!(
+                "# body truncated: {} of {} bytes",
+                body.len(),
+                req.body_original_len.map(|n| n.to_string()).unwrap_or_else(|| "?".to_string())
+            ));
+        }
+        match req.body_encoding {
+            // бинарное тело в sh-строку не помещается: раскодируем в stdin
+            BodyEncoding::Base64 => {
+                args[0] = format!("printf '%s' {} | base64 -d | curl", shell_quote(body));
+                args.push("--data-binary @-".to_string());
+            }
+            _ => args.push(format!("--data-raw {}", shell_quote(body))),
+        }
+    } else if req.body_streaming {
+        prefix.push("# request body was streamed and not stored".to_string());
+    }
+
+    prefix.push(args.join(" \\\n  "));
+    prefix.join("\n")
+}
+
+impl TrackedClient {
+    // Запрос записи как команда curl для sh/bash; имена из set_redacted_names — "<redacted>"
+    pub async fn as_curl(&self, key: &str) -> Result<String> {
+        let names = self.redacted_names();
+        let coll = self.collector.lock().await;
+        let entry = coll
+            .get(key)
+            .ok_or_else(|| anyhow!("Collector key '{}' not found", key))?;
+        Ok(curl_command(&entry.request_data, &names))
+    }
+
+    // Все записи в порядке отправки
+    pub async fn export_all_curl(&self) -> Result<Vec<String>> {
+        let names = self.redacted_names();
+        let coll = self.collector.lock().await;
+        let mut entries: Vec<_> = coll.values().collect();
+        entries.sort_by_key(|entry| entry.seq);
+        Ok(entries.into_iter().map(|entry| curl_command(&entry.request_data, &names)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+
+    #[cfg(unix)]
+    #[test]
+    fn quoted_strings_survive_the_shell() {
+        for raw in ["it's", "line one\nline 'two'\n", "$HOME `id` \\n \"q\" !x", "''", ""] {
+            let out = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf '%s' {}", shell_quote(raw)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(out.stdout).unwrap(), raw);
+        }
+    }
+
+    #[tokio::test]
+    async fn recorded_request_renders_as_curl() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/login" {
+                return TestResponse::ok("").header("Set-Cookie", "sid=abc; Path=/");
+            }
+            TestResponse::ok("ok")
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.set_redacted_names(&["authorization"]);
+        client.tracked_send("login", client.inner.get(server.url("/login"))).await.unwrap();
+        let builder = client
+            .inner
+            .put(server.url("/notes"))
+            .header("Authorization", "Bearer secret")
+            .header("X-Note", "it's")
+            .body("first line\nit's 'quoted'");
+        client.tracked_send("put", builder).await.unwrap();
+
+        let curl = client.as_curl("put").await.unwrap();
+        let expected = format!(
+            "curl \\\n  -X 'PUT' \\\n  '{}' \\\n  -H 'authorization: <redacted>' \\\n  -H 'x-note: it'\\''s' \\\n  --cookie 'sid=abc' \\\n  --data-raw 'first line\nit'\\''s '\\''quoted'\\'''",
+            server.url("/notes")
+        );
+        assert_eq!(curl, expected);
+        assert!(!curl.contains("content-length") && !curl.contains("host:"));
+
+        let all = client.export_all_curl().await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0], format!("curl \\\n  '{}'", server.url("/login")));
+        assert_eq!(all[1], curl);
+        assert_eq!(client.as_curl("missing").await.unwrap_err().to_string(), "Collector key 'missing' not found");
+    }
+}
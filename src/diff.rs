@@ -0,0 +1,188 @@
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+// Значение с двух сторон; None — на этой стороне его нет
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValueChange {
+    pub name: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+// Поле JSON-тела по пути вида $.items[0].id
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub path: String,
+    pub a: Option<Value>,
+    pub b: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BodyDiff {
+    // оба тела — JSON
+    Json { fields: Vec<FieldChange> },
+    // иначе только факт изменения и длины; None — тела нет
+    Text { a_len: Option<usize>, b_len: Option<usize> },
+}
+
+// None — ответа нет (ошибка транспорта или запрос ещё в полёте)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusChange {
+    pub a: Option<u16>,
+    pub b: Option<u16>,
+}
+
+// Отличия b от a; у одинаковых записей все поля пустые
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct EntryDiff {
+    pub request_headers: Vec<ValueChange>,
+    pub cookies: Vec<ValueChange>,
+    pub request_body: Option<BodyDiff>,
+    pub status: Option<StatusChange>,
+    pub response_headers: Vec<ValueChange>,
+    pub response_body: Option<BodyDiff>,
+}
+
+impl EntryDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == EntryDiff::default()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize entry diff")
+    }
+}
+
+// Имена заголовков без учёта регистра, по алфавиту
+fn map_diff(a: &HashMap<String, String>, b: &HashMap<String, String>, fold_case: bool) -> Vec<ValueChange> {
+    let norm = |m: &HashMap<String, String>| -> BTreeMap<String, String> {
+        m.iter()
+            .map(|(k, v)| (if fold_case { k.to_ascii_lowercase() } else { k.clone() }, v.clone()))
+            .collect()
+    };
+    let (mut a, mut b) = (norm(a), norm(b));
+    let names: BTreeSet<String> = a.keys().chain(b.keys()).cloned().collect();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (va, vb) = (a.remove(&name), b.remove(&name));
+            (va != vb).then_some(ValueChange { name, a: va, b: vb })
+        })
+        .collect()
+}
+
+fn json_diff(path: String, a: Option<&Value>, b: Option<&Value>, out: &mut Vec<FieldChange>) {
+    match (a, b) {
+        (Some(Value::Object(ma)), Some(Value::Object(mb))) => {
+            for (key, va) in ma {
+                json_diff(format!("{}.{}", path, key), Some(va), mb.get(key), out);
+            }
+            for (key, vb) in mb.iter().filter(|(key, _)| !ma.contains_key(*key)) {
+                json_diff(format!("{}.{}", path, key), None, Some(vb), out);
+            }
+        }
+        (Some(Value::Array(xa)), Some(Value::Array(xb))) => {
+            for i in 0..xa.len().max(xb.len()) {
+                json_diff(format!("{}[{}]", path, i), xa.get(i), xb.get(i), out);
+            }
+        }
+        (a, b) if a != b => out.push(FieldChange { path, a: a.cloned(), b: b.cloned() }),
+        _ => {}
+    }
+}
+
+fn parse_json(body: Option<&str>) -> Option<Value> {
+    serde_json::from_str(body?).ok()
+}
+
+fn body_diff(a: Option<&str>, b: Option<&str>) -> Option<BodyDiff> {
+    let (a, b) = (a.filter(|s| !s.is_empty()), b.filter(|s| !s.is_empty()));
+    if a == b {
+        return None;
+    }
+    if let (Some(ja), Some(jb)) = (parse_json(a), parse_json(b)) {
+        let mut fields = Vec::new();
+        json_diff("$".to_string(), Some(&ja), Some(&jb), &mut fields);
+        // отличаются только пробелы и порядок ключей
+        return (!fields.is_empty()).then_some(BodyDiff::Json { fields });
+    }
+    Some(BodyDiff::Text { a_len: a.map(str::len), b_len: b.map(str::len) })
+}
+
+pub fn diff_entries(a: &RequestResponseData, b: &RequestResponseData) -> EntryDiff {
+    let (ra, rb) = (&a.request_data, &b.request_data);
+    let (pa, pb) = (a.response_data.as_ref(), b.response_data.as_ref());
+    let empty = HashMap::new();
+    let status = (pa.map(|r| r.status), pb.map(|r| r.status));
+    EntryDiff {
+        request_headers: map_diff(&ra.headers, &rb.headers, true),
+        cookies: map_diff(&ra.cookies, &rb.cookies, false),
+        request_body: body_diff(ra.body.as_deref(), rb.body.as_deref()),
+        status: (status.0 != status.1).then_some(StatusChange { a: status.0, b: status.1 }),
+        response_headers: map_diff(pa.map_or(&empty, |r| &r.headers), pb.map_or(&empty, |r| &r.headers), true),
+        response_body: body_diff(pa.map(|r| r.body.as_str()), pb.map(|r| r.body.as_str())),
+    }
+}
+
+fn shown(value: &Option<String>) -> String {
+    value.as_ref().map_or_else(|| "(absent)".to_string(), |v| format!("{:?}", v))
+}
+
+fn write_changes(f: &mut fmt::Formatter<'_>, title: &str, changes: &[ValueChange]) -> fmt::Result {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "{}:", title)?;
+    for c in changes {
+        writeln!(f, "  {}: {} -> {}", c.name, shown(&c.a), shown(&c.b))?;
+    }
+    Ok(())
+}
+
+fn write_body(f: &mut fmt::Formatter<'_>, title: &str, body: &Option<BodyDiff>) -> fmt::Result {
+    let json = |v: &Option<Value>| v.as_ref().map_or_else(|| "(absent)".to_string(), Value::to_string);
+    let len = |n: &Option<usize>| n.map_or_else(|| "none".to_string(), |n| format!("{} bytes", n));
+    match body {
+        None => Ok(()),
+        Some(BodyDiff::Json { fields }) => {
+            writeln!(f, "{}:", title)?;
+            for c in fields {
+                writeln!(f, "  {}: {} -> {}", c.path, json(&c.a), json(&c.b))?;
+            }
+            Ok(())
+        }
+        Some(BodyDiff::Text { a_len, b_len }) => writeln!(f, "{}: changed ({} -> {})", title, len(a_len), len(b_len)),
+    }
+}
+
+// Текстовый вид: разделы только с отличиями
+impl fmt::Display for EntryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        write_changes(f, "Request headers", &self.request_headers)?;
+        write_changes(f, "Cookies", &self.cookies)?;
+        write_body(f, "Request body", &self.request_body)?;
+        if let Some(StatusChange { a, b }) = self.status {
+            let status = |s: Option<u16>| s.map_or_else(|| "no response".to_string(), |s| s.to_string());
+            writeln!(f, "Status: {} -> {}", status(a), status(b))?;
+        }
+        write_changes(f, "Response headers", &self.response_headers)?;
+        write_body(f, "Response body", &self.response_body)
+    }
+}
+
+impl TrackedClient {
+    // diff_entries для двух записей коллектора
+    pub async fn diff_entries_by_key(&self, key_a: &str, key_b: &str) -> Result<EntryDiff> {
+        let coll = self.collector.lock().await;
+        let entry = |key: &str| coll.get(key).ok_or_else(|| anyhow!("No collector entry '{}'", key));
+        Ok(diff_entries(entry(key_a)?, entry(key_b)?))
+    }
+}
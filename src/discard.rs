@@ -0,0 +1,83 @@
+use crate::errors::{EntryError, ErrorKind};
+use crate::{FailedPhase, ResponseHead, timestamp, BodyEncoding, ResponseData, SendOptions, TrackedClient};
+use anyhow::{anyhow, Result};
+use reqwest::RequestBuilder;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct DiscardSummary {
+    pub key: String,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub bytes: u64,
+    // вместе с передачей тела
+    pub duration_ms: u64,
+    pub final_url: String,
+}
+
+impl TrackedClient {
+    // Тело вычитывается и выбрасывается: честная длительность без расхода памяти
+    pub async fn tracked_send_discard(&self, key: &str, builder: RequestBuilder) -> Result<DiscardSummary> {
+        let req = self.build_tracked(key, builder).await?;
+        let (mut resp, start, key, seq, wal_id) = self.start_tracked(key, req, &SendOptions::default()).await?;
+
+        let final_url = resp.url().to_string();
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        let headers_at = Instant::now();
+        let mut bytes = 0u64;
+        let outcome = loop {
+            match resp.chunk().await {
+                Ok(Some(chunk)) => bytes += chunk.len() as u64,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        let (response_time, response_time_ms) = timestamp()?;
+        let resp_data = ResponseData {
+            status,
+            headers: headers.clone(),
+            body: String::new(),
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(final_url.clone()),
+            response_time,
+            response_time_ms,
+            duration_ms,
+            body_encoding: BodyEncoding::Omitted,
+            response_body_bytes: bytes,
+            ttfb_ms: Some(headers_at.duration_since(start).as_millis() as u64),
+            body_ms: Some(headers_at.elapsed().as_millis() as u64),
+            ..Default::default()
+        };
+        match outcome {
+            Ok(()) => {
+                self.finish_entry(&key, seq, wal_id.as_deref(), resp_data).await?;
+                Ok(DiscardSummary { key, status, headers, bytes, duration_ms, final_url })
+            }
+            Err(e) => {
+                let message = format!("Failed to read response body: {} (after {} bytes)", e, bytes);
+                let error = EntryError::new(message.clone(), ErrorKind::classify(&e)).in_phase(FailedPhase::BodyRead);
+                self.finish_entry_with_error(&key, seq, wal_id.as_deref(), resp_data, error).await?;
+                Err(anyhow!(message))
+            }
+        }
+    }
+}
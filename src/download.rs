@@ -0,0 +1,363 @@
+use crate::errors::{EntryError, ErrorKind};
+use crate::{FailedPhase, ResponseHead, timestamp, BodyEncoding, ResponseData, SendOptions, TrackedClient};
+use anyhow::{anyhow, Context, Result};
+use reqwest::header::RANGE;
+use reqwest::{IntoUrl, Request, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    // оставить недокачанный файл на диске при обрыве (по умолчанию удаляется)
+    pub keep_partial: bool,
+}
+
+// Что пишется в запись коллектора вместо тела ответа
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DownloadInfo {
+    pub path: String,
+    // получено в этой попытке
+    pub bytes_written: u64,
+    pub sha256: Option<String>,
+    pub complete: bool,
+    pub partial_kept: bool,
+    // заголовок Range, если докачивали
+    #[serde(default)]
+    pub range_requested: Option<String>,
+    // с какого смещения дописывали файл; 0 — писали с начала
+    #[serde(default)]
+    pub resumed_from: u64,
+    // размер файла после попытки
+    #[serde(default)]
+    pub total_bytes: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadReport {
+    pub key: String,
+    pub path: PathBuf,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub bytes_written: u64,
+    pub resumed_from: u64,
+    pub total_bytes: u64,
+    // хэш всего файла, а не только этой попытки
+    pub sha256: String,
+    pub elapsed_ms: u64,
+    pub final_url: String,
+}
+
+// Разбирает "bytes start-end/total" и "bytes */total"
+fn parse_content_range(value: &str) -> Option<(Option<u64>, Option<u64>)> {
+    let rest = value.trim().strip_prefix("bytes")?.trim_start();
+    let (range, total) = rest.split_once('/')?;
+    let total = total.trim().parse().ok();
+    let start = if range.trim() == "*" {
+        None
+    } else {
+        Some(range.split_once('-')?.0.trim().parse().ok()?)
+    };
+    Some((start, total))
+}
+
+impl TrackedClient {
+    pub async fn tracked_download(&self, key: &str, builder: RequestBuilder, path: &Path) -> Result<DownloadReport> {
+        self.tracked_download_with(key, builder, path, &DownloadOptions::default()).await
+    }
+
+    // Тело пишется на диск по чанкам и в памяти не копится
+    pub async fn tracked_download_with(
+        &self,
+        key: &str,
+        builder: RequestBuilder,
+        path: &Path,
+        options: &DownloadOptions,
+    ) -> Result<DownloadReport> {
+        let req = self.build_tracked(key, builder).await?;
+        self.sealing(self.download(key, req, path, options.keep_partial, 0)).await
+    }
+
+    // Докачка: если файл уже есть, просим Range: bytes=N- и дописываем. Сервер,
+    // проигнорировавший Range (200), перезаписывает файл с начала. Недокачанное не удаляется.
+    pub async fn tracked_download_resumable<U: IntoUrl>(&self, key: &str, url: U, path: &Path) -> Result<DownloadReport> {
+        let existing = match tokio::fs::metadata(path).await {
+            Ok(meta) if meta.is_file() => meta.len(),
+            _ => 0,
+        };
+        let mut builder = self.inner.get(url);
+        if existing > 0 {
+            builder = builder.header(RANGE, format!("bytes={}-", existing));
+        }
+        let req = self.build_tracked(key, builder).await?;
+        self.sealing(self.download(key, req, path, true, existing)).await
+    }
+
+    async fn download(
+        &self,
+        key: &str,
+        req: Request,
+        path: &Path,
+        keep_partial: bool,
+        resume_from: u64,
+    ) -> Result<DownloadReport> {
+        let range_requested = (resume_from > 0).then(|| format!("bytes={}-", resume_from));
+        let (mut resp, start, key, seq, wal_id) = self.start_tracked(key, req, &SendOptions::default()).await?;
+        let (key, wal_id) = (key.as_str(), wal_id.as_deref());
+
+        let final_url = resp.url().to_string();
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        let headers_at = Instant::now();
+        let content_range = headers.get("content-range").and_then(|v| parse_content_range(v));
+
+        // 206 с нужным началом — дописываем; 416 на уже полный файл — качать нечего
+        let offset = match (status, content_range) {
+            (206, Some((Some(from), _))) if resume_from > 0 && from == resume_from => resume_from,
+            _ => 0,
+        };
+        let already_complete =
+            resume_from > 0 && status == 416 && matches!(content_range, Some((None, Some(total))) if total == resume_from);
+
+        let mut hasher = Sha256::new();
+        let mut bytes_written = 0u64;
+        let outcome: Result<()> = async {
+            if already_complete {
+                return hash_file(path, &mut hasher).await;
+            }
+            let mut file = open_target(path, offset > 0).await?;
+            if offset > 0 {
+                hash_file(path, &mut hasher).await?;
+            }
+            write_body(&mut resp, &mut file, path, &mut hasher, &mut bytes_written).await
+        }
+        .await;
+        drop(resp);
+
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        let sha256 = format!("{:x}", hasher.finalize());
+        let total_bytes = if already_complete { resume_from } else { offset + bytes_written };
+        let (response_time, response_time_ms) = timestamp()?;
+        let resp_data = ResponseData {
+            status,
+            headers: headers.clone(),
+            body: String::new(),
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(final_url.clone()),
+            response_time,
+            response_time_ms,
+            duration_ms: elapsed_ms,
+            ttfb_ms: Some(headers_at.duration_since(start).as_millis() as u64),
+            body_ms: Some(headers_at.elapsed().as_millis() as u64),
+            body_encoding: BodyEncoding::Omitted,
+            response_body_bytes: bytes_written,
+            response_body_sha256: outcome.is_ok().then(|| sha256.clone()),
+            ..Default::default()
+        };
+        let mut info = DownloadInfo {
+            path: path.display().to_string(),
+            bytes_written,
+            sha256: None,
+            complete: false,
+            partial_kept: false,
+            range_requested,
+            resumed_from: offset,
+            total_bytes,
+        };
+
+        match outcome {
+            Ok(()) => {
+                self.finish_entry(key, seq, wal_id, resp_data).await?;
+                info.sha256 = Some(sha256.clone());
+                info.complete = true;
+                self.update_entry(key, seq, |entry| entry.download = Some(info)).await;
+                Ok(DownloadReport {
+                    key: key.to_string(),
+                    path: path.to_path_buf(),
+                    status,
+                    headers,
+                    bytes_written,
+                    resumed_from: offset,
+                    total_bytes,
+                    sha256,
+                    elapsed_ms,
+                    final_url,
+                })
+            }
+            Err(e) => {
+                // недокачанный файл: удаляем, если не просили оставить
+                info.partial_kept = keep_partial || tokio::fs::remove_file(path).await.is_err();
+                let message = format!("{:#} (after {} bytes)", e, bytes_written);
+                let error = EntryError::new(message.clone(), ErrorKind::classify(e.as_ref())).in_phase(FailedPhase::BodyRead);
+                self.finish_entry_with_error(key, seq, wal_id, resp_data, error).await?;
+                self.update_entry(key, seq, |entry| entry.download = Some(info)).await;
+                Err(anyhow!(message))
+            }
+        }
+    }
+}
+
+async fn write_body(
+    resp: &mut Response,
+    file: &mut File,
+    path: &Path,
+    hasher: &mut Sha256,
+    bytes_written: &mut u64,
+) -> Result<()> {
+    while let Some(chunk) = resp.chunk().await.context("Failed to read response body")? {
+        file.write_all(&chunk)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        hasher.update(&chunk);
+        *bytes_written += chunk.len() as u64;
+    }
+    file.flush()
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// Хэш уже лежащей на диске части, чтобы итоговый sha256 был по всему файлу
+async fn hash_file(path: &Path, hasher: &mut Sha256) -> Result<()> {
+    let mut file = File::open(path)
+        .await
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+async fn open_target(path: &Path, append: bool) -> Result<File> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    if append {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))
+    } else {
+        File::create(path)
+            .await
+            .with_context(|| format!("Failed to create {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    fn payload() -> Vec<u8> {
+        (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("download-{}-{}.bin", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    // Отдаёт payload с поддержкой Range; первый полный ответ обрывается на середине
+    async fn range_server(honour_range: bool) -> TestServer {
+        let cut_first = Arc::new(AtomicBool::new(true));
+        TestServer::start(move |req| {
+            let cut_first = cut_first.clone();
+            async move {
+                let body = payload();
+                let start = req
+                    .header("range")
+                    .filter(|_| honour_range)
+                    .and_then(|r| r.strip_prefix("bytes="))
+                    .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+                match start {
+                    Some(start) => TestResponse::new(206)
+                        .header("Content-Range", &format!("bytes {}-{}/{}", start, body.len() - 1, body.len()))
+                        .body(body[start..].to_vec()),
+                    None if cut_first.swap(false, Ordering::SeqCst) => TestResponse::ok(body).cut_after(40_000),
+                    None => TestResponse::ok(body),
+                }
+            }
+        })
+        .await
+    }
+
+    fn sha256_of(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    #[tokio::test]
+    async fn killed_download_resumes_to_identical_file() {
+        let server = range_server(true).await;
+        let path = temp_path("resume");
+        let client = TrackedClient::new().unwrap();
+
+        assert!(client.tracked_download_resumable("first", server.url("/file"), &path).await.is_err());
+        let partial = std::fs::metadata(&path).unwrap().len();
+        assert!(partial > 0 && partial < 100_000);
+
+        let report = client.tracked_download_resumable("second", server.url("/file"), &path).await.unwrap();
+        assert_eq!(report.status, 206);
+        assert_eq!(report.resumed_from, partial);
+        assert_eq!(report.bytes_written, 100_000 - partial);
+        assert_eq!(report.total_bytes, 100_000);
+        let file = std::fs::read(&path).unwrap();
+        assert_eq!(file, payload());
+        assert_eq!(report.sha256, sha256_of(&file));
+
+        let coll = client.collector.lock().await;
+        let first = coll["first"].download.clone().unwrap();
+        assert!(!first.complete && first.partial_kept);
+        let second = coll["second"].download.clone().unwrap();
+        assert_eq!(second.range_requested, Some(format!("bytes={}-", partial)));
+        assert_eq!((second.resumed_from, second.bytes_written, second.total_bytes), (partial, 100_000 - partial, 100_000));
+        assert!(second.complete);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn ignored_range_restarts_from_zero() {
+        let server = range_server(false).await;
+        let path = temp_path("restart");
+        std::fs::write(&path, b"stale partial content").unwrap();
+        let client = TrackedClient::new().unwrap();
+        // первый полный ответ сервер обрывает, второй отдаёт целиком
+        let _ = client.tracked_download_resumable("cut", server.url("/file"), &path).await;
+        let report = client.tracked_download_resumable("full", server.url("/file"), &path).await.unwrap();
+        assert_eq!(report.status, 200);
+        assert_eq!(report.resumed_from, 0);
+        assert_eq!(std::fs::read(&path).unwrap(), payload());
+        let _ = std::fs::remove_file(&path);
+    }
+}
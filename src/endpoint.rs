@@ -0,0 +1,151 @@
+use crate::errors::{EntryError, ErrorKind};
+use crate::{LoggedText, SendOptions, TrackedClient};
+use anyhow::{anyhow, bail, Context, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::Method;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+// Всё, что нельзя оставлять как есть внутри сегмента пути
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ').add(b'"').add(b'#').add(b'<').add(b'>').add(b'`')
+    .add(b'?').add(b'{').add(b'}').add(b'/').add(b'%').add(b'&')
+    .add(b'+').add(b'=').add(b';').add(b'\\').add(b'^').add(b'|');
+
+// Описание API-метода: шаблон URL с {плейсхолдерами} и ожидаемые статусы.
+// Логическое имя попадает в запись и не зависит от конкретного URL.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub name: String,
+    pub method: Method,
+    pub path: String,
+    // пусто — подходит любой 2xx
+    pub expected_status: Vec<u16>,
+    pub body_schema: Option<&'static str>,
+}
+
+impl Endpoint {
+    pub fn new(name: &str, method: Method, path: &str) -> Self {
+        Endpoint {
+            name: name.to_string(),
+            method,
+            path: path.to_string(),
+            expected_status: Vec::new(),
+            body_schema: None,
+        }
+    }
+
+    pub fn expect(mut self, statuses: &[u16]) -> Self {
+        self.expected_status = statuses.to_vec();
+        self
+    }
+
+    pub fn body_schema(mut self, marker: &'static str) -> Self {
+        self.body_schema = Some(marker);
+        self
+    }
+
+    pub fn accepts(&self, status: u16) -> bool {
+        if self.expected_status.is_empty() {
+            (200..300).contains(&status)
+        } else {
+            self.expected_status.contains(&status)
+        }
+    }
+
+    fn placeholders(&self) -> Result<Vec<&str>> {
+        let mut names = Vec::new();
+        let mut rest = self.path.as_str();
+        while let Some(open) = rest.find('{') {
+            let close = rest[open..]
+                .find('}')
+                .ok_or_else(|| anyhow!("Endpoint '{}': unclosed placeholder in {}", self.name, self.path))?;
+            names.push(&rest[open + 1..open + close]);
+            rest = &rest[open + close + 1..];
+        }
+        Ok(names)
+    }
+
+    // Подставляет параметры в шаблон; лишние и недостающие параметры — ошибка
+    pub fn render<P: Serialize + ?Sized>(&self, params: &P) -> Result<String> {
+        let params = match serde_json::to_value(params).context("Failed to serialize endpoint params")? {
+            Value::Object(map) => map,
+            Value::Null => serde_json::Map::new(),
+            _ => bail!("Endpoint '{}': params must serialize to an object", self.name),
+        };
+        let mut values = BTreeMap::new();
+        for (name, value) in &params {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => bail!("Endpoint '{}': parameter '{}' must be a scalar", self.name, name),
+            };
+            values.insert(name.as_str(), value);
+        }
+
+        let placeholders = self.placeholders()?;
+        let missing: Vec<&str> = placeholders.iter().copied().filter(|p| !values.contains_key(p)).collect();
+        let extra: Vec<&str> = values.keys().copied().filter(|k| !placeholders.contains(k)).collect();
+        if !missing.is_empty() || !extra.is_empty() {
+            let mut problems = Vec::new();
+            if !missing.is_empty() {
+                problems.push(format!("missing template parameters: {}", missing.join(", ")));
+            }
+            if !extra.is_empty() {
+                problems.push(format!("unexpected parameters: {}", extra.join(", ")));
+            }
+            bail!("Endpoint '{}': {}", self.name, problems.join("; "));
+        }
+
+        let mut url = self.path.clone();
+        for (name, value) in &values {
+            let encoded = utf8_percent_encode(value, PATH_SEGMENT).to_string();
+            url = url.replace(&format!("{{{}}}", name), &encoded);
+        }
+        Ok(url)
+    }
+}
+
+impl TrackedClient {
+    // Вызов описанного метода API. Неожиданный статус не превращается в Err:
+    // ответ возвращается как есть, а в записи появляется логическая ошибка.
+    pub async fn tracked_call<P: Serialize + ?Sized, B: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        endpoint: &Endpoint,
+        params: &P,
+        body: Option<&B>,
+    ) -> Result<LoggedText> {
+        let url = endpoint.render(params)?;
+        let mut builder = self.inner.request(endpoint.method.clone(), &url);
+        if let Some(body) = body {
+            builder = builder.json(body);
+        }
+        let opts = SendOptions {
+            endpoint_name: Some(endpoint.name.clone()),
+            expected_status: endpoint.expected_status.clone(),
+            ..SendOptions::default()
+        };
+        self.sealing(async {
+            let logged = self.tracked_send_with(key, builder, &opts).await?;
+
+            if !endpoint.accepts(logged.status) {
+                let expected = if endpoint.expected_status.is_empty() {
+                    "2xx".to_string()
+                } else {
+                    format!("{:?}", endpoint.expected_status)
+                };
+                let message = format!(
+                    "Unexpected status {} for endpoint '{}' (expected {})",
+                    logged.status, endpoint.name, expected
+                );
+                let error = EntryError::new(message, ErrorKind::Other);
+                self.update_entry(&logged.key, logged.seq, |entry| error.apply(entry)).await;
+            }
+            Ok(logged)
+        })
+        .await
+    }
+}
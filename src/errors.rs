@@ -0,0 +1,127 @@
+use crate::{BodyReadError, RequestResponseData};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+// Класс ошибки записи, чтобы не разбирать текст reqwest регулярками
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Timeout,
+    Connect,
+    Dns,
+    TlsHandshake,
+    Proxy,
+    BodyRead,
+    TooManyRedirects,
+    Other,
+}
+
+impl ErrorKind {
+    // Есть ли смысл повторить тот же запрос
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Timeout | ErrorKind::Connect | ErrorKind::Dns | ErrorKind::Proxy | ErrorKind::BodyRead
+        )
+    }
+
+    // По флагам reqwest::Error и его цепочке source. DNS, TLS и прокси reqwest различает
+    // только текстом внутренних ошибок, поэтому для них смотрим на сообщения — но лишь
+    // на те, что ниже reqwest::Error: выше (и в нём самом) бывает URL.
+    pub fn classify(err: &(dyn Error + 'static)) -> ErrorKind {
+        let (mut timeout, mut connect, mut redirect, mut body) = (false, false, false, false);
+        let mut below_reqwest = false;
+        let mut text = String::new();
+        let mut cause = Some(err);
+        while let Some(e) = cause {
+            cause = e.source();
+            if let Some(e) = e.downcast_ref::<reqwest::Error>() {
+                timeout |= e.is_timeout();
+                connect |= e.is_connect();
+                redirect |= e.is_redirect();
+                body |= e.is_body() || e.is_decode();
+                below_reqwest = true;
+                continue;
+            }
+            match e.downcast_ref::<BodyReadError>() {
+                Some(BodyReadError::TimedOut(_)) => timeout = true,
+                Some(BodyReadError::Reqwest(_)) => body = true,
+                None => {}
+            }
+            if e.downcast_ref::<std::io::Error>().is_some_and(|io| io.kind() == std::io::ErrorKind::TimedOut) {
+                timeout = true;
+            }
+            if below_reqwest {
+                text.push_str(&e.to_string().to_ascii_lowercase());
+                text.push('\n');
+            }
+        }
+
+        let mentions = |words: &[&str]| words.iter().any(|w| text.contains(w));
+        if timeout {
+            ErrorKind::Timeout
+        } else if redirect {
+            ErrorKind::TooManyRedirects
+        } else if mentions(&["dns error", "failed to lookup address", "name or service not known"]) {
+            ErrorKind::Dns
+        } else if mentions(&["tunnel", "proxy"]) {
+            ErrorKind::Proxy
+        } else if mentions(&["tls", "ssl", "certificate", "handshake"]) {
+            ErrorKind::TlsHandshake
+        } else if connect {
+            ErrorKind::Connect
+        } else if body {
+            ErrorKind::BodyRead
+        } else {
+            ErrorKind::Other
+        }
+    }
+}
+
+// На каком шаге отправки запрос не удался
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum FailedPhase {
+    // RequestBuilder::build
+    Build,
+    // отправка и ожидание заголовков ответа
+    Execute,
+    // чтение тела после заголовков
+    BodyRead,
+}
+
+// Ошибка для записи в коллектор: текст, его класс и шаг (у логических ошибок шага нет)
+#[derive(Debug, Clone)]
+pub(crate) struct EntryError {
+    pub(crate) message: String,
+    pub(crate) kind: ErrorKind,
+    pub(crate) phase: Option<FailedPhase>,
+}
+
+impl EntryError {
+    pub(crate) fn new(message: String, kind: ErrorKind) -> Self {
+        EntryError { message, kind, phase: None }
+    }
+
+    pub(crate) fn in_phase(mut self, phase: FailedPhase) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    pub(crate) fn from_error(err: &(dyn Error + 'static)) -> Self {
+        EntryError::new(err.to_string(), ErrorKind::classify(err))
+    }
+
+    pub(crate) fn apply(self, entry: &mut RequestResponseData) {
+        entry.error = Some(self.message);
+        entry.error_kind = Some(self.kind);
+        entry.is_retryable = Some(self.kind.is_retryable());
+        if let Some(phase) = self.phase {
+            entry.failed_phase = Some(phase);
+            let elapsed = Utc::now().timestamp_millis() - entry.request_data.request_time_ms;
+            entry.duration_ms = Some(elapsed.max(0) as u64);
+        }
+        entry.refresh_outcome();
+    }
+}
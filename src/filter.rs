@@ -0,0 +1,182 @@
+use crate::{Outcome, RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use reqwest::Url;
+use std::ops::RangeInclusive;
+
+// Условия отбора записей; заданные условия объединяются через И, пустой фильтр пропускает всё
+#[derive(Debug, Clone, Default)]
+pub struct EntryFilter {
+    // запись без ответа под диапазон статусов не попадает
+    pub status: Option<RangeInclusive<u16>>,
+    // Some(true) — только с транспортной ошибкой, Some(false) — только без неё
+    pub transport_error: Option<bool>,
+    // outcome не Success и не Pending
+    pub failed: bool,
+    pub key_prefix: Option<String>,
+    pub url_contains: Option<String>,
+    pub host: Option<String>,
+    pub min_duration_ms: Option<u64>,
+}
+
+impl EntryFilter {
+    pub fn new() -> Self {
+        EntryFilter::default()
+    }
+
+    // HTTP- и транспортные ошибки, включая логические (GraphQL, Endpoint)
+    pub fn errors_only() -> Self {
+        EntryFilter { failed: true, ..Default::default() }
+    }
+
+    pub fn status(mut self, range: RangeInclusive<u16>) -> Self {
+        self.status = Some(range);
+        self
+    }
+
+    pub fn transport_error(mut self, has_error: bool) -> Self {
+        self.transport_error = Some(has_error);
+        self
+    }
+
+    pub fn key_prefix(mut self, prefix: &str) -> Self {
+        self.key_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn url_contains(mut self, needle: &str) -> Self {
+        self.url_contains = Some(needle.to_string());
+        self
+    }
+
+    // без учёта регистра, без порта
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    // время ответа, а если его нет — время до ошибки
+    pub fn min_duration_ms(mut self, ms: u64) -> Self {
+        self.min_duration_ms = Some(ms);
+        self
+    }
+
+    pub fn matches(&self, key: &str, entry: &RequestResponseData) -> bool {
+        let resp = entry.response_data.as_ref();
+        if let Some(range) = &self.status {
+            if !resp.is_some_and(|r| range.contains(&r.status)) {
+                return false;
+            }
+        }
+        if let Some(has_error) = self.transport_error {
+            if (entry.outcome == Outcome::TransportError) != has_error {
+                return false;
+            }
+        }
+        if self.failed && matches!(entry.outcome, Outcome::Success | Outcome::Pending) {
+            return false;
+        }
+        if let Some(prefix) = &self.key_prefix {
+            if !key.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        let endpoint = &entry.request_data.endpoint;
+        if let Some(needle) = &self.url_contains {
+            if !endpoint.contains(needle.as_str()) {
+                return false;
+            }
+        }
+        if let Some(host) = &self.host {
+            let url = Url::parse(endpoint).ok();
+            if !url.as_ref().and_then(Url::host_str).is_some_and(|h| h.eq_ignore_ascii_case(host)) {
+                return false;
+            }
+        }
+        if let Some(min) = self.min_duration_ms {
+            let duration = resp.map(|r| r.duration_ms).or(entry.duration_ms);
+            if duration.is_none_or(|d| d < min) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl TrackedClient {
+    // Формат get_collected_data, только подходящие записи
+    pub async fn get_collected_filtered(&self, filter: EntryFilter) -> Result<String> {
+        let coll = self.collector.lock().await;
+        let selected: IndexMap<&String, &RequestResponseData> =
+            coll.iter().filter(|(key, entry)| filter.matches(key, entry)).collect();
+        serde_json::to_string(&selected).context("Failed to serialize filtered data")
+    }
+
+    pub async fn get_collected_errors(&self) -> Result<String> {
+        self.get_collected_filtered(EntryFilter::errors_only()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use serde_json::Value;
+    use std::time::Duration;
+
+    fn keys(json: &str) -> Vec<String> {
+        let value: Value = serde_json::from_str(json).unwrap();
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    #[tokio::test]
+    async fn filters_mix_of_success_500_and_errors() {
+        let server = TestServer::start(|req| async move {
+            match req.path.as_str() {
+                "/boom" => TestResponse::new(500).body("boom"),
+                "/missing" => TestResponse::new(404),
+                "/slow" => {
+                    tokio::time::sleep(Duration::from_millis(120)).await;
+                    TestResponse::ok("slow")
+                }
+                _ => TestResponse::ok("ok"),
+            }
+        })
+        .await;
+        let closed = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.local_addr().unwrap()
+        };
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("api:ok", client.inner.get(server.url("/ok"))).await.unwrap();
+        client.tracked_send("api:boom", client.inner.get(server.url("/boom"))).await.unwrap();
+        client.tracked_send("web:missing", client.inner.get(server.url("/missing"))).await.unwrap();
+        client.tracked_send("web:slow", client.inner.get(server.url("/slow"))).await.unwrap();
+        let down = format!("http://{}/down", closed);
+        assert!(client.tracked_send("api:down", client.inner.get(down)).await.is_err());
+
+        let errors = client.get_collected_errors().await.unwrap();
+        assert_eq!(keys(&errors), ["api:boom", "web:missing", "api:down"]);
+        // формат get_collected_data
+        let parsed: IndexMap<String, RequestResponseData> = serde_json::from_str(&errors).unwrap();
+        assert_eq!(parsed["api:boom"].response_data.as_ref().unwrap().status, 500);
+
+        let filtered = |filter: EntryFilter| {
+            let client = client.clone();
+            async move { keys(&client.get_collected_filtered(filter).await.unwrap()) }
+        };
+        assert_eq!(filtered(EntryFilter::new().status(500..=599)).await, ["api:boom"]);
+        assert_eq!(filtered(EntryFilter::new().status(400..=599)).await, ["api:boom", "web:missing"]);
+        assert_eq!(filtered(EntryFilter::new().transport_error(true)).await, ["api:down"]);
+        assert_eq!(
+            filtered(EntryFilter::new().key_prefix("api:").transport_error(false)).await,
+            ["api:ok", "api:boom"]
+        );
+        assert_eq!(filtered(EntryFilter::new().url_contains("/slow")).await, ["web:slow"]);
+        assert_eq!(filtered(EntryFilter::new().min_duration_ms(100)).await, ["web:slow"]);
+        assert_eq!(filtered(EntryFilter::new().host("127.0.0.1")).await.len(), 5);
+        assert!(filtered(EntryFilter::new().host("example.com")).await.is_empty());
+        assert!(filtered(EntryFilter::errors_only().key_prefix("web:").status(500..=599)).await.is_empty());
+        assert_eq!(filtered(EntryFilter::new()).await.len(), 5);
+    }
+}
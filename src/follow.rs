@@ -0,0 +1,122 @@
+use crate::{LoggedText, SendOptions, TrackedClient};
+use anyhow::Result;
+use reqwest::header::{HeaderMap, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, LOCATION, PROXY_AUTHORIZATION, TRANSFER_ENCODING};
+use reqwest::{Method, Request, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+// Один промежуточный ответ цепочки редиректов
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RedirectHop {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub location: Option<String>,
+    pub set_cookies: Vec<String>,
+    pub duration_ms: u64,
+}
+
+// Заголовки, которые нельзя уносить на другой хост
+const CREDENTIAL_HEADERS: [reqwest::header::HeaderName; 3] = [AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION];
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme() && a.host_str() == b.host_str() && a.port_or_known_default() == b.port_or_known_default()
+}
+
+// Запрос следующего шага по правилам браузеров: 303 (и 301/302 не для GET/HEAD) превращаются
+// в GET без тела, 307/308 повторяют метод и тело. None — идти дальше нельзя.
+fn next_request(template: Option<Request>, status: u16, location: Url) -> Option<Request> {
+    // template — копия отправленного запроса; тело-поток скопировать нельзя, и тогда её нет
+    let mut prev = template?;
+    let to_get = match status {
+        303 => *prev.method() != Method::HEAD,
+        301 | 302 => *prev.method() != Method::GET && *prev.method() != Method::HEAD,
+        307 | 308 => false,
+        _ => return None,
+    };
+
+    let mut headers: HeaderMap = prev.headers().clone();
+    if !same_origin(prev.url(), &location) {
+        for name in &CREDENTIAL_HEADERS {
+            headers.remove(name);
+        }
+    }
+    let mut req = if to_get {
+        for name in [CONTENT_TYPE, CONTENT_LENGTH, TRANSFER_ENCODING] {
+            headers.remove(name);
+        }
+        Request::new(Method::GET, location)
+    } else {
+        let mut req = Request::new(prev.method().clone(), location);
+        *req.body_mut() = prev.body_mut().take();
+        req
+    };
+    *req.headers_mut() = headers;
+    *req.timeout_mut() = prev.timeout().copied();
+    *req.version_mut() = prev.version();
+    Some(req)
+}
+
+impl TrackedClient {
+    // Редиректы проходятся вручную, не больше max_hops: каждый промежуточный ответ
+    // попадает в redirect_chain записи, а тело и итоговый ответ — как в tracked_send_text.
+    // Куки промежуточных ответов ложатся в общее хранилище и уходят со следующим шагом.
+    // Если лимит шагов исчерпан, возвращается последний 3xx как есть.
+    pub async fn tracked_send_follow(&self, key: &str, builder: RequestBuilder, max_hops: usize) -> Result<LoggedText> {
+        let req = self.build_tracked(key, builder).await?;
+        let orig_url = req.url().to_string();
+        let opts = SendOptions::default();
+        let (mut req, key, seq, wal_id) = self.begin_tracked(key, req, &opts).await?;
+
+        let start = Instant::now();
+        let mut hops = 0;
+        let resp = loop {
+            let hop_start = Instant::now();
+            let method = req.method().to_string();
+            let url = req.url().clone();
+            let template = req.try_clone();
+            let resp = self.execute_tracked(&self.no_redirect, &key, seq, wal_id.as_deref(), req).await?;
+            if !resp.status().is_redirection() || hops >= max_hops {
+                break resp;
+            }
+
+            let location = resp
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let next = location
+                .as_deref()
+                .and_then(|loc| url.join(loc).ok())
+                .and_then(|target| next_request(template, resp.status().as_u16(), target));
+            let Some(next) = next else {
+                break resp;
+            };
+
+            let hop = RedirectHop {
+                method,
+                url: url.to_string(),
+                status: resp.status().as_u16(),
+                location,
+                set_cookies: Self::response_head(&resp).set_cookies,
+                duration_ms: hop_start.elapsed().as_millis() as u64,
+            };
+            self.update_entry(&key, seq, |entry| entry.redirect_chain.push(hop)).await;
+            hops += 1;
+            req = next;
+        };
+
+        let (resp_data, body, final_url) = self.complete_text(&key, seq, wal_id.as_deref(), resp, start, &opts).await?;
+        Ok(LoggedText {
+            key,
+            seq,
+            truncated: resp_data.body_truncated,
+            http_version: resp_data.http_version,
+            status: resp_data.status,
+            headers: resp_data.headers,
+            body,
+            redirected: final_url != orig_url,
+            final_url,
+        })
+    }
+}
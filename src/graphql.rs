@@ -0,0 +1,83 @@
+use crate::errors::{EntryError, ErrorKind};
+use crate::{truncate, LoggedText, SendOptions, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::IntoUrl;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub(crate) const DEFAULT_GRAPHQL_QUERY_MAX_LEN: usize = 2000;
+
+// Операция GraphQL отдельно от сырого тела: по ней и различаются записи одного /graphql
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GraphqlInfo {
+    pub operation_name: Option<String>,
+    pub query: String,
+    pub variables: Value,
+    // массив errors из ответа, если он непустой
+    #[serde(default)]
+    pub errors: Option<Value>,
+}
+
+fn error_summary(errors: &[Value]) -> String {
+    let messages: Vec<&str> = errors
+        .iter()
+        .map(|e| e.get("message").and_then(Value::as_str).unwrap_or("unknown error"))
+        .collect();
+    format!("GraphQL errors: {}", messages.join("; "))
+}
+
+impl TrackedClient {
+    pub fn set_graphql_query_max_len(&self, max_len: usize) {
+        self.settings_mut().graphql_query_max_len = max_len;
+    }
+
+    // POST {query, variables, operationName}. Ошибки из конверта {data, errors}
+    // попадают в error записи даже при HTTP 200; сам вызов при этом успешен.
+    pub async fn tracked_send_graphql<U: IntoUrl, V: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        url: U,
+        query: &str,
+        variables: &V,
+        operation_name: Option<&str>,
+    ) -> Result<LoggedText> {
+        let variables = serde_json::to_value(variables).context("Failed to serialize GraphQL variables")?;
+        let mut body = json!({ "query": query, "variables": variables });
+        if let Some(name) = operation_name {
+            body["operationName"] = Value::String(name.to_string());
+        }
+
+        let max_len = self.settings().graphql_query_max_len;
+        let opts = SendOptions {
+            graphql: Some(GraphqlInfo {
+                operation_name: operation_name.map(str::to_string),
+                query: truncate(query, max_len),
+                variables,
+                errors: None,
+            }),
+            ..SendOptions::default()
+        };
+        self.sealing(async {
+            let logged = self.tracked_send_with(key, self.inner.post(url).json(&body), &opts).await?;
+
+            let errors = serde_json::from_str::<Value>(&logged.body)
+                .ok()
+                .and_then(|v| v.get("errors").cloned())
+                .filter(|e| e.as_array().is_some_and(|a| !a.is_empty()));
+            if let Some(errors) = errors {
+                let message = error_summary(errors.as_array().map(Vec::as_slice).unwrap_or_default());
+                self.update_entry(&logged.key, logged.seq, |entry| {
+                    if entry.error.is_none() {
+                        EntryError::new(message, ErrorKind::Other).apply(entry);
+                    }
+                    if let Some(info) = entry.graphql.as_mut() {
+                        info.errors = Some(errors);
+                    }
+                })
+                .await;
+            }
+            Ok(logged)
+        })
+        .await
+    }
+}
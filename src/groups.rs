@@ -0,0 +1,55 @@
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+
+// Группа на время жизни значения: end_group при Drop
+pub struct GroupGuard {
+    client: TrackedClient,
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        self.client.end_group();
+    }
+}
+
+impl TrackedClient {
+    // Все записи до end_group получат group; вложенные группы склеиваются через "/".
+    // Стек общий для клона клиента: параллельным задачам группу лучше передавать в SendOptions.
+    pub fn begin_group(&self, name: &str) {
+        self.settings_mut().group_stack.push(name.to_string());
+    }
+
+    // Закрывает самую вложенную группу и возвращает её имя
+    pub fn end_group(&self) -> Option<String> {
+        self.settings_mut().group_stack.pop()
+    }
+
+    pub fn group_scope(&self, name: &str) -> GroupGuard {
+        self.begin_group(name);
+        GroupGuard { client: self.clone() }
+    }
+
+    pub fn current_group(&self) -> Option<String> {
+        let settings = self.settings();
+        (!settings.group_stack.is_empty()).then(|| settings.group_stack.join("/"))
+    }
+
+    // {группа: {ключ: запись}}; записи без группы — под ""
+    pub async fn get_collected_data_grouped(&self) -> Result<String> {
+        let coll = self.collector.lock().await;
+        let mut grouped: IndexMap<&str, IndexMap<&String, &RequestResponseData>> = IndexMap::new();
+        for (key, entry) in coll.iter() {
+            grouped
+                .entry(entry.group.as_deref().unwrap_or(""))
+                .or_default()
+                .insert(key, entry);
+        }
+        serde_json::to_string(&grouped).context("Failed to serialize grouped data")
+    }
+
+    pub async fn get_pretty_truncated_data_grouped(&self) -> Result<String> {
+        let raw = self.get_collected_data_grouped().await?;
+        self.pretty_truncated(&raw)
+    }
+}
@@ -0,0 +1,113 @@
+use crate::auto_flush::{finalized_entries, remove_taken};
+use crate::{MergeMode, TrackedClient};
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+// Больше не распаковываем: защита от gzip-бомбы
+pub const MAX_DECOMPRESSED_BYTES: u64 = 1 << 30;
+
+pub(crate) fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).context("Failed to compress data")?;
+    encoder.finish().context("Failed to compress data")
+}
+
+// Не больше limit байт распакованного; за пределом — ошибка, а не обрезанный результат
+pub(crate) fn gunzip(data: &[u8], limit: u64) -> Result<Vec<u8>> {
+    if !data.starts_with(&GZIP_MAGIC) {
+        if matches!(data.iter().find(|b| !b.is_ascii_whitespace()), Some(b'{' | b'[')) {
+            bail!("Data is plain JSON, not gzip; use load_collected_data for uncompressed dumps");
+        }
+        bail!("Data is not gzip-compressed (expected magic bytes 1f 8b)");
+    }
+    let mut out = Vec::new();
+    GzDecoder::new(data)
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut out)
+        .context("Failed to decompress gzip data")?;
+    if out.len() as u64 > limit {
+        bail!("Decompressed data exceeds {} bytes", limit);
+    }
+    Ok(out)
+}
+
+async fn gzip_blocking(json: String) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || gzip(json.as_bytes()))
+        .await
+        .context("Failed to compress collected data")?
+}
+
+impl TrackedClient {
+    // get_collected_data в gzip; сжатие идёт вне потока рантайма
+    pub async fn export_compressed(&self) -> Result<Vec<u8>> {
+        gzip_blocking(self.get_collected_data().await?).await
+    }
+
+    // Как take_collected_data, но записи убираются только после успешного сжатия
+    pub async fn take_compressed(&self) -> Result<Vec<u8>> {
+        let taken = finalized_entries(&*self.collector.lock().await);
+        let json = serde_json::to_string(&taken).context("Failed to serialize collected data")?;
+        let packed = gzip_blocking(json).await?;
+        remove_taken(&mut *self.collector.lock().await, &taken);
+        Ok(packed)
+    }
+
+    // Обратное к export_compressed: заменяет коллектор записями из дампа (MergeMode::Replace);
+    // распакованный дамп больше MAX_DECOMPRESSED_BYTES не принимается
+    pub async fn load_collected_compressed(&self, bytes: &[u8]) -> Result<usize> {
+        let bytes = bytes.to_vec();
+        let json = tokio::task::spawn_blocking(move || gunzip(&bytes, MAX_DECOMPRESSED_BYTES))
+            .await
+            .context("Failed to decompress collected data")??;
+        let json = String::from_utf8(json).context("Decompressed collected data is not UTF-8")?;
+        self.load_collected_data(&json, MergeMode::Replace).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+
+    #[tokio::test]
+    async fn export_and_load_round_trip() {
+        let server = TestServer::start(|req| async move { TestResponse::ok(req.path.repeat(200)) }).await;
+        let client = TrackedClient::new().unwrap();
+        for key in ["a", "b", "c"] {
+            client.tracked_send(key, client.inner.get(server.url(&format!("/{}", key)))).await.unwrap();
+        }
+        let json = client.get_collected_data().await.unwrap();
+        let packed = client.export_compressed().await.unwrap();
+        assert!(packed.starts_with(&GZIP_MAGIC));
+        assert!(packed.len() < json.len());
+
+        let fresh = TrackedClient::new().unwrap();
+        assert_eq!(fresh.load_collected_compressed(&packed).await.unwrap(), 3);
+        // заголовки — HashMap, поэтому сравнение без учёта порядка ключей
+        let as_value = |json: &[u8]| serde_json::from_slice::<serde_json::Value>(json).unwrap();
+        assert_eq!(as_value(fresh.get_collected_data().await.unwrap().as_bytes()), as_value(json.as_bytes()));
+
+        let taken = client.take_compressed().await.unwrap();
+        assert_eq!(as_value(&gunzip(&taken, MAX_DECOMPRESSED_BYTES).unwrap()), as_value(json.as_bytes()));
+        assert!(client.collector.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_plain_json_garbage_and_bombs() {
+        let client = TrackedClient::new().unwrap();
+        let err = client.load_collected_compressed(b"  {\"a\":{}}").await.unwrap_err();
+        assert!(err.to_string().contains("plain JSON"), "{}", err);
+        let err = client.load_collected_compressed(b"PK\x03\x04").await.unwrap_err();
+        assert!(err.to_string().contains("magic bytes"), "{}", err);
+
+        let packed = gzip(&[0u8; 4096]).unwrap();
+        assert_eq!(gunzip(&packed, 4096).unwrap().len(), 4096);
+        let err = gunzip(&packed, 4095).unwrap_err();
+        assert!(err.to_string().contains("exceeds 4095 bytes"), "{}", err);
+        assert!(gunzip(&packed[..packed.len() / 2], 4096).is_err());
+    }
+}
@@ -0,0 +1,387 @@
+use crate::redact::{redact_cookie_value, redact_header_value};
+use crate::{BodyEncoding, RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub(crate) const BASE64_BODY_COMMENT: &str = "body is base64-encoded";
+
+// HAR 1.2 (http://www.softwareishard.com/blog/har-12-spec/); -1 — величина неизвестна
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Har {
+    pub(crate) log: HarLog,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct HarLog {
+    pub(crate) version: String,
+    pub(crate) creator: HarCreator,
+    pub(crate) entries: Vec<HarEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct HarCreator {
+    pub(crate) name: String,
+    pub(crate) version: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HarEntry {
+    pub(crate) started_date_time: String,
+    pub(crate) time: i64,
+    pub(crate) request: HarRequest,
+    pub(crate) response: HarResponse,
+    pub(crate) cache: HashMap<String, String>,
+    pub(crate) timings: HarTimings,
+    #[serde(rename = "serverIPAddress", default, skip_serializing_if = "Option::is_none")]
+    pub(crate) server_ip_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) connection: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) comment: Option<String>,
+    // так отмечают неудавшиеся запросы DevTools
+    #[serde(rename = "_error", default, skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HarRequest {
+    pub(crate) method: String,
+    pub(crate) url: String,
+    pub(crate) http_version: String,
+    pub(crate) cookies: Vec<HarCookie>,
+    pub(crate) headers: Vec<HarNameValue>,
+    pub(crate) query_string: Vec<HarNameValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) post_data: Option<HarPostData>,
+    pub(crate) headers_size: i64,
+    pub(crate) body_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HarResponse {
+    pub(crate) status: u16,
+    pub(crate) status_text: String,
+    pub(crate) http_version: String,
+    pub(crate) cookies: Vec<HarCookie>,
+    pub(crate) headers: Vec<HarNameValue>,
+    pub(crate) content: HarContent,
+    #[serde(rename = "redirectURL")]
+    pub(crate) redirect_url: String,
+    pub(crate) headers_size: i64,
+    pub(crate) body_size: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HarNameValue {
+    pub(crate) name: String,
+    pub(crate) value: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HarCookie {
+    pub(crate) name: String,
+    pub(crate) value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) expires: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) http_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) secure: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HarPostData {
+    pub(crate) mime_type: String,
+    pub(crate) text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) comment: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HarContent {
+    pub(crate) size: i64,
+    pub(crate) mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) comment: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct HarTimings {
+    pub(crate) blocked: i64,
+    pub(crate) dns: i64,
+    pub(crate) connect: i64,
+    pub(crate) ssl: i64,
+    pub(crate) send: i64,
+    pub(crate) wait: i64,
+    pub(crate) receive: i64,
+}
+
+// Заголовки по порядку отправки, если он записан
+fn header_list(ordered: &[(String, String)], map: &HashMap<String, String>, names: &[String]) -> Vec<HarNameValue> {
+    let pairs: Vec<(&String, &String)> = if ordered.is_empty() {
+        map.iter().collect()
+    } else {
+        ordered.iter().map(|(k, v)| (k, v)).collect()
+    };
+    pairs
+        .into_iter()
+        .map(|(name, value)| HarNameValue { name: name.clone(), value: redact_header_value(names, name, value) })
+        .collect()
+}
+
+fn har_entry(entry: &RequestResponseData, names: &[String]) -> HarEntry {
+    let req = &entry.request_data;
+    let resp = entry.response_data.as_ref();
+
+    let mut cookies: Vec<HarCookie> = req
+        .cookies
+        .iter()
+        .map(|(name, value)| HarCookie { name: name.clone(), value: redact_cookie_value(names, name, value), ..Default::default() })
+        .collect();
+    cookies.sort_by(|a, b| a.name.cmp(&b.name));
+    let post_data = req.body.as_ref().map(|text| HarPostData {
+        mime_type: req.content_type().unwrap_or_default().to_string(),
+        text: text.clone(),
+        comment: match (req.body_encoding, req.body_truncated) {
+            (BodyEncoding::Base64, _) => Some(BASE64_BODY_COMMENT.to_string()),
+            (_, true) => Some("body truncated".to_string()),
+            _ => None,
+        },
+    });
+    let http_version = resp
+        .map(|r| r.http_version.clone())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "HTTP/1.1".to_string());
+    let request = HarRequest {
+        method: req.method.clone(),
+        url: req.endpoint.clone(),
+        http_version: http_version.clone(),
+        cookies,
+        headers: header_list(&req.headers_ordered, &req.headers, names),
+        query_string: req
+            .query_params
+            .iter()
+            .map(|(name, value)| HarNameValue { name: name.clone(), value: value.clone() })
+            .collect(),
+        post_data,
+        headers_size: -1,
+        body_size: req
+            .request_body_bytes
+            .map(|n| n as i64)
+            .unwrap_or(if req.body.is_some() || req.body_streaming { -1 } else { 0 }),
+    };
+
+    let time = resp
+        .map(|r| r.duration_ms)
+        .or(entry.duration_ms)
+        .unwrap_or_default() as i64;
+    let response = match resp {
+        Some(r) => HarResponse {
+            status: r.status,
+            status_text: StatusCode::from_u16(r.status)
+                .ok()
+                .and_then(|s| s.canonical_reason())
+                .unwrap_or_default()
+                .to_string(),
+            http_version,
+            cookies: r
+                .set_cookies_parsed
+                .iter()
+                .filter(|c| c.parse_error.is_none())
+                .map(|c| HarCookie {
+                    name: c.name.clone(),
+                    value: redact_cookie_value(names, &c.name, &c.value),
+                    path: c.path.clone(),
+                    domain: c.domain.clone(),
+                    expires: c.expires.clone(),
+                    http_only: Some(c.http_only),
+                    secure: Some(c.secure),
+                })
+                .collect(),
+            headers: header_list(&r.headers_ordered, &r.headers, names),
+            content: HarContent {
+                size: r.response_body_bytes as i64,
+                mime_type: r.header("content-type").unwrap_or("x-unknown").to_string(),
+                text: (r.body_encoding != BodyEncoding::Omitted).then(|| r.body.clone()),
+                encoding: (r.body_encoding == BodyEncoding::Base64).then(|| "base64".to_string()),
+                comment: match r.body_encoding {
+                    BodyEncoding::Omitted => Some("body not stored".to_string()),
+                    _ if r.body_truncated => Some("body truncated".to_string()),
+                    _ => None,
+                },
+            },
+            redirect_url: r.location().unwrap_or_default().to_string(),
+            headers_size: -1,
+            body_size: r.response_body_bytes as i64,
+        },
+        // ответа нет: статус 0, причина — в comment и _error
+        None => HarResponse {
+            status: 0,
+            status_text: String::new(),
+            http_version: String::new(),
+            cookies: Vec::new(),
+            headers: Vec::new(),
+            content: HarContent { size: 0, mime_type: "x-unknown".to_string(), text: None, encoding: None, comment: None },
+            redirect_url: String::new(),
+            headers_size: -1,
+            body_size: -1,
+        },
+    };
+
+    // всё до заголовков — wait, остальное — receive; send и сетевые фазы не измеряются
+    let wait = resp.and_then(|r| r.ttfb_ms).map(|t| t as i64).unwrap_or(time).min(time);
+    let remote_addr = resp.and_then(|r| r.remote_addr.as_deref());
+    HarEntry {
+        started_date_time: req.request_time.clone(),
+        time,
+        request,
+        response,
+        cache: HashMap::new(),
+        timings: HarTimings { blocked: -1, dns: -1, connect: -1, ssl: -1, send: 0, wait, receive: time - wait },
+        server_ip_address: remote_addr
+            .and_then(|addr| addr.parse::<std::net::SocketAddr>().ok())
+            .map(|addr| addr.ip().to_string()),
+        // локальный порт различает TCP-соединения
+        connection: resp
+            .and_then(|r| r.local_addr.as_deref())
+            .and_then(|addr| addr.rsplit(':').next())
+            .map(str::to_string),
+        comment: entry.error.clone(),
+        error: entry.response_data.is_none().then(|| entry.error.clone()).flatten(),
+    }
+}
+
+// Записи по времени начала в HAR-документ
+pub(crate) fn har_document(coll: &IndexMap<String, RequestResponseData>, names: &[String]) -> Har {
+    let mut entries: Vec<&RequestResponseData> = coll.values().collect();
+    entries.sort_by_key(|entry| (entry.request_data.request_time_ms, entry.seq));
+    Har {
+        log: HarLog {
+            version: "1.2".to_string(),
+            creator: HarCreator {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            entries: entries.into_iter().map(|entry| har_entry(entry, names)).collect(),
+        },
+    }
+}
+
+impl TrackedClient {
+    // HAR 1.2 для DevTools/Fiddler: записи по времени начала, заголовки по порядку отправки,
+    // имена из set_redacted_names скрыты
+    pub async fn export_har(&self) -> Result<String> {
+        let names = self.redacted_names();
+        let coll = self.collector.lock().await;
+        serde_json::to_string_pretty(&har_document(&coll, &names)).context("Failed to serialize HAR")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use serde_json::Value;
+
+    async fn session() -> (TrackedClient, TestServer) {
+        let server = TestServer::start(|req| async move {
+            match req.path.as_str() {
+                "/login" => TestResponse::ok("hi").header("Set-Cookie", "sid=abc; Path=/; HttpOnly"),
+                _ => TestResponse::ok(r#"{"ok":true}"#).header("Content-Type", "application/json"),
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("login", client.inner.get(server.url("/login"))).await.unwrap();
+        let api = client
+            .inner
+            .post(server.url("/api?x=1&y=two"))
+            .header("Authorization", "Bearer secret")
+            .header("Content-Type", "application/json")
+            .body(r#"{"q":1}"#);
+        client.tracked_send("api", api).await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let down = format!("http://{}/down", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(client.tracked_send("down", client.inner.get(down)).await.is_err());
+        (client, server)
+    }
+
+    fn require(value: &Value, fields: &[&str]) {
+        for field in fields {
+            assert!(value.get(field).is_some(), "missing {} in {}", field, value);
+        }
+    }
+
+    #[tokio::test]
+    async fn export_follows_har_1_2_structure() {
+        let (client, _server) = session().await;
+        client.set_redacted_names(&["authorization"]);
+        let har: Value = serde_json::from_str(&client.export_har().await.unwrap()).unwrap();
+
+        let log = &har["log"];
+        assert_eq!(log["version"], "1.2");
+        require(&log["creator"], &["name", "version"]);
+        let entries = log["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 3);
+        for entry in entries {
+            require(entry, &["startedDateTime", "time", "request", "response", "cache", "timings"]);
+            require(&entry["request"], &["method", "url", "httpVersion", "cookies", "headers", "queryString", "headersSize", "bodySize"]);
+            require(&entry["response"], &["status", "statusText", "httpVersion", "cookies", "headers", "content", "redirectURL", "headersSize", "bodySize"]);
+            require(&entry["response"]["content"], &["size", "mimeType"]);
+            require(&entry["timings"], &["send", "wait", "receive"]);
+        }
+        let started: Vec<&str> = entries.iter().map(|e| e["startedDateTime"].as_str().unwrap()).collect();
+        assert!(started.windows(2).all(|w| w[0] <= w[1]));
+
+        let api = &entries[1]["request"];
+        assert_eq!(api["method"], "POST");
+        assert_eq!(api["queryString"], serde_json::json!([{"name": "x", "value": "1"}, {"name": "y", "value": "two"}]));
+        assert_eq!(api["postData"]["text"], r#"{"q":1}"#);
+        assert_eq!(api["postData"]["mimeType"], "application/json");
+        assert_eq!(api["cookies"], serde_json::json!([{"name": "sid", "value": "abc"}]));
+        let auth = api["headers"].as_array().unwrap().iter().find(|h| h["name"] == "authorization").unwrap();
+        assert_eq!(auth["value"], "<redacted>");
+        assert_eq!(entries[0]["response"]["cookies"][0]["name"], "sid");
+        assert_eq!(entries[0]["response"]["cookies"][0]["httpOnly"], true);
+
+        let failed = &entries[2];
+        assert_eq!(failed["response"]["status"], 0);
+        assert!(failed["comment"].as_str().is_some_and(|c| !c.is_empty()));
+        assert_eq!(failed["_error"], failed["comment"]);
+    }
+
+    #[tokio::test]
+    async fn har_entry_round_trips() {
+        let (client, _server) = session().await;
+        let text = client.export_har().await.unwrap();
+        let har: Har = serde_json::from_str(&text).unwrap();
+        let coll = client.collector.lock().await;
+        let api = har.log.entries.iter().find(|e| e.request.method == "POST").unwrap();
+        let source = &coll["api"];
+        assert_eq!(api.request.url, source.request_data.endpoint);
+        assert_eq!(api.started_date_time, source.request_data.request_time);
+        let resp = source.response_data.as_ref().unwrap();
+        assert_eq!(api.response.status, resp.status);
+        assert_eq!(api.response.content.text.as_deref(), Some(resp.body.as_str()));
+        assert_eq!(api.time, resp.duration_ms as i64);
+        assert_eq!(serde_json::to_string_pretty(&har).unwrap(), text);
+    }
+}
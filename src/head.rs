@@ -0,0 +1,77 @@
+use crate::{ResponseHead, timestamp, ResponseData, SendOptions, TrackedClient};
+use anyhow::Result;
+use reqwest::IntoUrl;
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct LoggedHead {
+    pub key: String,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub content_length: Option<u64>,
+    pub final_url: String,
+    pub redirected: bool,
+}
+
+impl TrackedClient {
+    // Свой таймаут для tracked_head; None — как у обычных запросов
+    pub fn set_head_timeout(&self, timeout: Option<Duration>) {
+        self.settings_mut().head_timeout = timeout;
+    }
+
+    // HEAD-проба: тело не читается вовсе, в записи оно пустое
+    pub async fn tracked_head<U: IntoUrl>(&self, key: &str, url: U) -> Result<LoggedHead> {
+        let req = self.build_tracked(key, self.inner.head(url)).await?;
+        let orig_url = req.url().to_string();
+        let opts = SendOptions {
+            timeout: self.settings().head_timeout,
+            ..SendOptions::default()
+        };
+        let (resp, start, key, seq, wal_id) = self.start_tracked(key, req, &opts).await?;
+
+        let final_url = resp.url().to_string();
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        drop(resp);
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        let (response_time, response_time_ms) = timestamp()?;
+        let resp_data = ResponseData {
+            status,
+            headers: headers.clone(),
+            body: String::new(),
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(final_url.clone()),
+            response_time,
+            response_time_ms,
+            duration_ms,
+            ttfb_ms: Some(duration_ms),
+            ..Default::default()
+        };
+        self.finish_entry(&key, seq, wal_id.as_deref(), resp_data).await?;
+        Ok(LoggedHead {
+            key,
+            status,
+            headers,
+            content_length: content_length_header,
+            redirected: final_url != orig_url,
+            final_url,
+        })
+    }
+}
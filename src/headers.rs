@@ -0,0 +1,165 @@
+use crate::{RequestData, ResponseData};
+use chrono::{DateTime, Utc};
+use reqwest::header::{CONTENT_LENGTH, COOKIE, HOST, TRANSFER_ENCODING};
+use reqwest::Request;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// Имена с send path приходят в нижнем регистре; перебор — для данных, загруженных извне
+pub(crate) fn lookup<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .get(&name.to_ascii_lowercase())
+        .or_else(|| {
+            headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v)
+        })
+        .map(String::as_str)
+}
+
+// Все заголовки с данным префиксом имени (без учёта регистра), отсортированные по имени
+fn matching<'a>(headers: &'a HashMap<String, String>, prefix: &str) -> Vec<(&'a str, &'a str)> {
+    let mut found: Vec<(&str, &str)> = headers
+        .iter()
+        .filter(|(k, _)| {
+            k.len() >= prefix.len()
+                && k.is_char_boundary(prefix.len())
+                && k[..prefix.len()].eq_ignore_ascii_case(prefix)
+        })
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    found.sort_unstable();
+    found
+}
+
+// Заголовки, которых ещё нет в собранном Request и которые допишут при отправке:
+// умолчания клиента (reqwest), Cookie из хранилища, затем Host и длина тела (hyper).
+// В HTTP/1.1 они уходят после заданных вызывающим, в этом же порядке; в HTTP/2 Host
+// становится псевдозаголовком :authority.
+pub(crate) fn implicit_headers(req: &Request, client_defaults: &[String], has_cookies: bool) -> Vec<String> {
+    let headers = req.headers();
+    let mut implicit: Vec<String> = client_defaults
+        .iter()
+        .filter(|name| !headers.contains_key(name.as_str()))
+        .cloned()
+        .collect();
+    if has_cookies && !headers.contains_key(COOKIE) {
+        implicit.push(COOKIE.to_string());
+    }
+    if !headers.contains_key(HOST) {
+        implicit.push(HOST.to_string());
+    }
+    let framed = headers.contains_key(CONTENT_LENGTH) || headers.contains_key(TRANSFER_ENCODING);
+    match req.body() {
+        _ if framed => {}
+        Some(body) if body.as_bytes().is_some() => implicit.push(CONTENT_LENGTH.to_string()),
+        Some(_) => implicit.push(TRANSFER_ENCODING.to_string()),
+        None => {}
+    }
+    implicit
+}
+
+// Retry-After в секундах или как HTTP-дата; прошедшая дата даёт ноль
+pub(crate) fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?.with_timezone(&Utc);
+    Some((at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+impl RequestData {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        lookup(&self.headers, name)
+    }
+
+    pub fn headers_matching(&self, prefix: &str) -> Vec<(&str, &str)> {
+        matching(&self.headers, prefix)
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+}
+
+impl ResponseData {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        lookup(&self.headers, name)
+    }
+
+    pub fn headers_matching(&self, prefix: &str) -> Vec<(&str, &str)> {
+        matching(&self.headers, prefix)
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.header("location")
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        parse_retry_after(self.header("retry-after")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use crate::TrackedClient;
+
+    #[tokio::test]
+    async fn accessors_ignore_case_on_captured_entries() {
+        let server = TestServer::start(|_| async {
+            TestResponse::ok("{}")
+                .header("Content-Type", "application/json; charset=utf-8")
+                .header("Location", "/next")
+                .header("Retry-After", "120")
+                .header("X-RateLimit-Remaining", "7")
+                .header("X-RateLimit-Limit", "10")
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let resp = client
+            .tracked_send("k", client.inner.post(server.url("/")).header("X-Trace-Id", "abc").body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(resp.header("CONTENT-TYPE"), Some("application/json; charset=utf-8"));
+        assert_eq!(resp.content_type(), Some("application/json; charset=utf-8"));
+        assert_eq!(resp.location(), Some("/next"));
+        assert_eq!(resp.retry_after(), Some(Duration::from_secs(120)));
+        assert_eq!(
+            resp.headers_matching("x-ratelimit-"),
+            vec![("x-ratelimit-limit", "10"), ("x-ratelimit-remaining", "7")]
+        );
+        assert_eq!(resp.header("missing"), None);
+
+        let entry = client.collector.lock().await.get("k").cloned().unwrap();
+        assert_eq!(entry.request_data.header("x-trace-id"), Some("abc"));
+        assert_eq!(entry.request_data.header("X-TRACE-ID"), Some("abc"));
+        assert_eq!(entry.request_data.headers_matching("X-Trace"), vec![("x-trace-id", "abc")]);
+    }
+
+    #[test]
+    fn lookup_falls_back_for_mixed_case_keys() {
+        let loaded = ResponseData {
+            headers: HashMap::from([("Content-Type".to_string(), "text/html".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(loaded.content_type(), Some("text/html"));
+        assert_eq!(loaded.headers_matching("content-"), vec![("Content-Type", "text/html")]);
+    }
+
+    #[test]
+    fn retry_after_accepts_http_dates() {
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let later = (Utc::now() + chrono::Duration::seconds(3600)).to_rfc2822();
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(delay > Duration::from_secs(3500) && delay <= Duration::from_secs(3600));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+}
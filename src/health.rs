@@ -0,0 +1,410 @@
+use crate::TrackedClient;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Failing,
+}
+
+// Пороги вердикта; окно — последние `window` времени, но не больше `max_samples` запросов
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HealthThresholds {
+    pub window: Duration,
+    pub max_samples: usize,
+    // ниже этого числа запросов в окне доля ошибок и p95 не учитываются
+    pub min_samples: usize,
+    pub degraded_error_rate: f64,
+    pub failing_error_rate: f64,
+    pub degraded_p95_ms: u64,
+    pub failing_p95_ms: u64,
+    pub degraded_consecutive_failures: u32,
+    pub failing_consecutive_failures: u32,
+    // брейкер хоста открыт, пока столько его последних запросов в окне подряд неудачны
+    pub breaker_consecutive_failures: u32,
+    // один открытый брейкер даёт Degraded, столько — Failing
+    pub failing_open_breakers: usize,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            window: Duration::from_secs(5 * 60),
+            max_samples: 500,
+            min_samples: 5,
+            degraded_error_rate: 0.1,
+            failing_error_rate: 0.5,
+            degraded_p95_ms: 5_000,
+            failing_p95_ms: 15_000,
+            degraded_consecutive_failures: 3,
+            failing_consecutive_failures: 10,
+            breaker_consecutive_failures: 5,
+            failing_open_breakers: 2,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Health {
+    pub status: HealthStatus,
+    pub requests: usize,
+    pub failures: usize,
+    pub error_rate: f64,
+    pub p95_ms: Option<u64>,
+    pub consecutive_failures: u32,
+    pub open_breakers: Vec<String>,
+    pub top_failing_host: Option<String>,
+    pub last_error: Option<String>,
+}
+
+struct Sample {
+    at: Instant,
+    host: String,
+    duration_ms: Option<u64>,
+    error: Option<String>,
+}
+
+// Счётчики хоста по образцам в окне
+#[derive(Default)]
+struct HostCounters {
+    requests: usize,
+    failures: usize,
+    // хвост неудач подряд среди его запросов в окне
+    streak: usize,
+}
+
+// Скользящее окно исходов, пополняется при каждой записи ответа или ошибки;
+// счётчики обновляются при добавлении и вытеснении образца, summary окно не сортирует
+#[derive(Default)]
+pub(crate) struct HealthMonitor {
+    thresholds: HealthThresholds,
+    samples: VecDeque<Sample>,
+    failures: usize,
+    // хвост неудач подряд в окне: при вытеснении не может превысить число образцов
+    streak: usize,
+    durations: BTreeMap<u64, usize>,
+    timed: usize,
+    hosts: HashMap<String, HostCounters>,
+}
+
+impl HealthMonitor {
+    fn pop_front(&mut self) {
+        let Some(s) = self.samples.pop_front() else {
+            return;
+        };
+        if s.error.is_some() {
+            self.failures -= 1;
+        }
+        self.streak = self.streak.min(self.samples.len());
+        if let Some(ms) = s.duration_ms {
+            self.timed -= 1;
+            if let Some(n) = self.durations.get_mut(&ms) {
+                *n -= 1;
+                if *n == 0 {
+                    self.durations.remove(&ms);
+                }
+            }
+        }
+        if let Some(host) = self.hosts.get_mut(&s.host) {
+            host.requests -= 1;
+            if s.error.is_some() {
+                host.failures -= 1;
+            }
+            host.streak = host.streak.min(host.requests);
+            if host.requests == 0 {
+                self.hosts.remove(&s.host);
+            }
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self.samples.len() > self.thresholds.max_samples {
+            self.pop_front();
+        }
+        while let Some(front) = self.samples.front() {
+            if now.duration_since(front.at) > self.thresholds.window {
+                self.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    // error = None — успешный ответ; 5xx считаем отказом наравне с ошибкой транспорта
+    pub(crate) fn observe(&mut self, endpoint: &str, duration_ms: Option<u64>, error: Option<String>) {
+        self.observe_at(Instant::now(), endpoint, duration_ms, error);
+    }
+
+    fn observe_at(&mut self, now: Instant, endpoint: &str, duration_ms: Option<u64>, error: Option<String>) {
+        let host = reqwest::Url::parse(endpoint)
+            .ok()
+            .and_then(|u| u.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let failed = error.is_some();
+        let counters = self.hosts.entry(host.clone()).or_default();
+        counters.requests += 1;
+        if failed {
+            counters.failures += 1;
+            counters.streak += 1;
+            self.failures += 1;
+            self.streak += 1;
+        } else {
+            counters.streak = 0;
+            self.streak = 0;
+        }
+        if let Some(ms) = duration_ms {
+            *self.durations.entry(ms).or_default() += 1;
+            self.timed += 1;
+        }
+        self.samples.push_back(Sample { at: now, host, duration_ms, error });
+        self.prune(now);
+    }
+
+    fn p95_ms(&self) -> Option<u64> {
+        if self.timed == 0 {
+            return None;
+        }
+        let rank = (((self.timed as f64) * 0.95).ceil() as usize).clamp(1, self.timed);
+        let mut seen = 0;
+        for (&ms, &n) in &self.durations {
+            seen += n;
+            if seen >= rank {
+                return Some(ms);
+            }
+        }
+        None
+    }
+
+    fn summary(&mut self) -> Health {
+        self.summary_at(Instant::now())
+    }
+
+    fn summary_at(&mut self, now: Instant) -> Health {
+        self.prune(now);
+        let t = &self.thresholds;
+
+        let requests = self.samples.len();
+        let failures = self.failures;
+        let error_rate = if requests == 0 { 0.0 } else { failures as f64 / requests as f64 };
+        let p95_ms = self.p95_ms();
+        let consecutive_failures = self.streak as u32;
+
+        let mut open_breakers: Vec<String> = self
+            .hosts
+            .iter()
+            .filter(|(_, c)| t.breaker_consecutive_failures > 0 && c.streak >= t.breaker_consecutive_failures as usize)
+            .map(|(host, _)| host.clone())
+            .collect();
+        open_breakers.sort();
+        let top_failing_host = self
+            .hosts
+            .iter()
+            .filter(|(_, c)| c.failures > 0)
+            .max_by(|a, b| a.1.failures.cmp(&b.1.failures).then_with(|| b.0.cmp(a.0)))
+            .map(|(host, _)| host.clone());
+        let last_error = self.samples.iter().rev().find_map(|s| s.error.clone());
+
+        let enough = requests >= t.min_samples;
+        let p95 = p95_ms.unwrap_or(0);
+        let status = if consecutive_failures >= t.failing_consecutive_failures
+            || (t.failing_open_breakers > 0 && open_breakers.len() >= t.failing_open_breakers)
+            || (enough && (error_rate >= t.failing_error_rate || p95 >= t.failing_p95_ms))
+        {
+            HealthStatus::Failing
+        } else if consecutive_failures >= t.degraded_consecutive_failures
+            || !open_breakers.is_empty()
+            || (enough && (error_rate >= t.degraded_error_rate || p95 >= t.degraded_p95_ms))
+        {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Ok
+        };
+
+        Health {
+            status,
+            requests,
+            failures,
+            error_rate,
+            p95_ms,
+            consecutive_failures,
+            open_breakers,
+            top_failing_host,
+            last_error,
+        }
+    }
+}
+
+impl TrackedClient {
+    // Краткий вердикт для супервизора, считается по окну без обхода коллектора
+    pub fn health(&self) -> Health {
+        self.health_monitor().summary()
+    }
+
+    pub fn health_thresholds(&self) -> HealthThresholds {
+        self.health_monitor().thresholds.clone()
+    }
+
+    pub fn set_health_thresholds(&self, thresholds: HealthThresholds) {
+        self.health_monitor().thresholds = thresholds;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor() -> HealthMonitor {
+        HealthMonitor {
+            thresholds: HealthThresholds {
+                window: Duration::from_secs(60),
+                min_samples: 4,
+                degraded_consecutive_failures: 2,
+                failing_consecutive_failures: 4,
+                ..HealthThresholds::default()
+            },
+            ..HealthMonitor::default()
+        }
+    }
+
+    fn ok(m: &mut HealthMonitor, at: Instant) {
+        m.observe_at(at, "https://api.example.com/ok", Some(10), None);
+    }
+
+    fn fail(m: &mut HealthMonitor, at: Instant, host: &str) {
+        m.observe_at(at, &format!("https://{}/x", host), Some(10), Some(format!("{} down", host)));
+    }
+
+    #[test]
+    fn status_recovers_as_failures_age_out() {
+        let start = Instant::now();
+        let mut m = monitor();
+        for i in 0..10 {
+            ok(&mut m, start + Duration::from_secs(i));
+        }
+        assert_eq!(m.summary_at(start + Duration::from_secs(10)).status, HealthStatus::Ok);
+
+        // 2 ошибки из 12: доля выше 10%, и хвост из двух подряд
+        let t = start + Duration::from_secs(20);
+        fail(&mut m, t, "api.example.com");
+        fail(&mut m, t, "api.example.com");
+        let h = m.summary_at(t);
+        assert_eq!(h.status, HealthStatus::Degraded);
+        assert_eq!(h.consecutive_failures, 2);
+
+        let t = start + Duration::from_secs(30);
+        fail(&mut m, t, "api.example.com");
+        fail(&mut m, t, "api.example.com");
+        let h = m.summary_at(t);
+        assert_eq!(h.status, HealthStatus::Failing);
+        assert_eq!(h.consecutive_failures, 4);
+        assert_eq!(h.top_failing_host.as_deref(), Some("api.example.com"));
+        assert_eq!(h.last_error.as_deref(), Some("api.example.com down"));
+
+        // успешные запросы ранних секунд ушли из окна, остались только ошибки
+        let h = m.summary_at(start + Duration::from_secs(75));
+        assert_eq!(h.requests, 4);
+        assert_eq!(h.status, HealthStatus::Failing);
+
+        // первые две ошибки уходят: хвост укорачивается вместе с окном
+        let h = m.summary_at(start + Duration::from_secs(85));
+        assert_eq!(h.requests, 2);
+        assert_eq!(h.consecutive_failures, 2);
+        assert_eq!(h.status, HealthStatus::Degraded);
+
+        // окно опустело — счётчик не залипает
+        let h = m.summary_at(start + Duration::from_secs(95));
+        assert_eq!(h.requests, 0);
+        assert_eq!(h.consecutive_failures, 0);
+        assert_eq!(h.status, HealthStatus::Ok);
+        assert_eq!(h.top_failing_host, None);
+        assert_eq!(h.last_error, None);
+    }
+
+    #[test]
+    fn open_breakers_degrade_then_fail() {
+        let start = Instant::now();
+        let mut m = monitor();
+        m.thresholds.breaker_consecutive_failures = 2;
+        m.thresholds.degraded_consecutive_failures = 100;
+        m.thresholds.failing_consecutive_failures = 100;
+        m.thresholds.min_samples = 1000;
+
+        fail(&mut m, start, "a.example.com");
+        fail(&mut m, start, "a.example.com");
+        ok(&mut m, start);
+        let h = m.summary_at(start);
+        assert_eq!(h.open_breakers, vec!["a.example.com".to_string()]);
+        assert_eq!(h.status, HealthStatus::Degraded);
+
+        let t = start + Duration::from_secs(30);
+        fail(&mut m, t, "b.example.com");
+        fail(&mut m, t, "b.example.com");
+        let h = m.summary_at(t);
+        assert_eq!(h.open_breakers, vec!["a.example.com".to_string(), "b.example.com".to_string()]);
+        assert_eq!(h.status, HealthStatus::Failing);
+
+        // брейкер a закрывается, когда его ошибки выходят из окна
+        let h = m.summary_at(start + Duration::from_secs(65));
+        assert_eq!(h.open_breakers, vec!["b.example.com".to_string()]);
+        assert_eq!(h.status, HealthStatus::Degraded);
+
+        ok(&mut m, start + Duration::from_secs(66));
+        m.observe_at(start + Duration::from_secs(66), "https://b.example.com/x", Some(10), None);
+        let h = m.summary_at(start + Duration::from_secs(66));
+        assert!(h.open_breakers.is_empty());
+        assert_eq!(h.status, HealthStatus::Ok);
+    }
+
+    #[test]
+    fn p95_follows_the_window() {
+        let start = Instant::now();
+        let mut m = monitor();
+        m.thresholds.degraded_p95_ms = 500;
+        m.thresholds.failing_p95_ms = 1_000;
+        for i in 0..19 {
+            m.observe_at(start, "https://api.example.com/", Some(i * 10), None);
+        }
+        m.observe_at(start, "https://api.example.com/", Some(2_000), None);
+        let h = m.summary_at(start);
+        assert_eq!(h.p95_ms, Some(180));
+        assert_eq!(h.status, HealthStatus::Ok);
+
+        let t = start + Duration::from_secs(30);
+        for _ in 0..20 {
+            m.observe_at(t, "https://api.example.com/", Some(1_500), None);
+        }
+        assert_eq!(m.summary_at(t).status, HealthStatus::Failing);
+
+        for _ in 0..2 {
+            m.observe_at(start + Duration::from_secs(70), "https://api.example.com/", Some(20), None);
+        }
+        let h = m.summary_at(start + Duration::from_secs(70));
+        assert_eq!(h.requests, 22);
+        assert_eq!(h.p95_ms, Some(1_500));
+
+        let h = m.summary_at(start + Duration::from_secs(95));
+        assert_eq!(h.p95_ms, Some(20));
+        assert_eq!(h.status, HealthStatus::Ok);
+    }
+
+    #[test]
+    fn max_samples_evicts_counters_too() {
+        let start = Instant::now();
+        let mut m = monitor();
+        m.thresholds.max_samples = 4;
+        for _ in 0..4 {
+            fail(&mut m, start, "api.example.com");
+        }
+        assert_eq!(m.summary_at(start).status, HealthStatus::Failing);
+        for _ in 0..4 {
+            ok(&mut m, start);
+        }
+        let h = m.summary_at(start);
+        assert_eq!((h.requests, h.failures, h.consecutive_failures), (4, 0, 0));
+        assert_eq!(h.status, HealthStatus::Ok);
+    }
+}
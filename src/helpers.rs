@@ -0,0 +1,121 @@
+use crate::{LoggedText, SendOptions, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::header::HeaderMap;
+use reqwest::{IntoUrl, RequestBuilder};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+fn with_headers(builder: RequestBuilder, headers: Option<HeaderMap>) -> RequestBuilder {
+    match headers {
+        Some(headers) => builder.headers(headers),
+        None => builder,
+    }
+}
+
+// Разбирает urlencoded-пары в объект; повторяющиеся имена собираются в массив
+fn form_to_value(body: &[u8]) -> Value {
+    let mut map = Map::new();
+    for (name, value) in url::form_urlencoded::parse(body) {
+        let value = Value::String(value.into_owned());
+        match map.get_mut(name.as_ref()) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                map.insert(name.into_owned(), value);
+            }
+        }
+    }
+    Value::Object(map)
+}
+
+// Короткие обёртки над tracked_send_text, чтобы на местах вызова не трогать inner
+impl TrackedClient {
+    pub async fn tracked_get<U: IntoUrl>(
+        &self,
+        key: &str,
+        url: U,
+        headers: Option<HeaderMap>,
+    ) -> Result<LoggedText> {
+        let builder = with_headers(self.inner.get(url), headers);
+        self.tracked_send_text(key, builder).await
+    }
+
+    pub async fn tracked_post_json<U: IntoUrl, T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        url: U,
+        body: &T,
+        headers: Option<HeaderMap>,
+    ) -> Result<LoggedText> {
+        let builder = with_headers(self.inner.post(url).json(body), headers);
+        let opts = SendOptions {
+            body_parsed: Some(serde_json::to_value(body).context("Failed to serialize JSON body")?),
+            ..SendOptions::default()
+        };
+        self.tracked_send_with(key, builder, &opts).await
+    }
+
+    pub async fn tracked_post_form<U: IntoUrl, T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        url: U,
+        form: &T,
+        headers: Option<HeaderMap>,
+    ) -> Result<LoggedText> {
+        let encoded = serde_urlencoded::to_string(form).context("Failed to serialize form body")?;
+        let builder = with_headers(self.inner.post(url).form(form), headers);
+        let opts = SendOptions {
+            body_parsed: Some(form_to_value(encoded.as_bytes())),
+            ..SendOptions::default()
+        };
+        self.tracked_send_with(key, builder, &opts).await
+    }
+
+    // POST формы; в RequestData.body_parsed попадает разобранный объект
+    pub async fn tracked_send_form<U: IntoUrl, T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        url: U,
+        form: &T,
+    ) -> Result<LoggedText> {
+        self.tracked_post_form(key, url, form, None).await
+    }
+
+    // POST JSON; в RequestData.body_parsed попадает само значение
+    pub async fn tracked_send_json_body<U: IntoUrl, T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        url: U,
+        body: &T,
+    ) -> Result<LoggedText> {
+        self.tracked_post_json(key, url, body, None).await
+    }
+
+    pub async fn tracked_put_json<U: IntoUrl, T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        url: U,
+        body: &T,
+        headers: Option<HeaderMap>,
+    ) -> Result<LoggedText> {
+        let builder = with_headers(self.inner.put(url).json(body), headers);
+        let opts = SendOptions {
+            body_parsed: Some(serde_json::to_value(body).context("Failed to serialize JSON body")?),
+            ..SendOptions::default()
+        };
+        self.tracked_send_with(key, builder, &opts).await
+    }
+
+    pub async fn tracked_delete<U: IntoUrl>(
+        &self,
+        key: &str,
+        url: U,
+        headers: Option<HeaderMap>,
+    ) -> Result<LoggedText> {
+        let builder = with_headers(self.inner.delete(url), headers);
+        self.tracked_send_text(key, builder).await
+    }
+}
@@ -1,27 +1,480 @@
-use reqwest::{Client, RequestBuilder, Proxy};
+use reqwest::{Client, ClientBuilder, Request, RequestBuilder, Response};
 use reqwest_cookie_store::CookieStoreMutex;
 use cookie_store::CookieStore;
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::collections::HashMap;
-use std::time::Instant;
-use chrono::{Utc, FixedOffset};
-use std::io::Cursor;
+use indexmap::IndexMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc, FixedOffset};
 use anyhow::{anyhow, Context, Result};
 use tokio::sync::Mutex;
+use bytes::Bytes;
+use base64::Engine;
+use sha2::{Digest, Sha256};
 
-// Функция для усечения строки до max символов
+mod auto_flush;
+mod auto_key;
+mod batch;
+mod capture;
+mod conditional;
+mod connection;
+mod cookie_file;
+mod cookie_jar;
+mod cookie_watch;
+mod cookies;
+mod csv;
+mod curl;
+mod diff;
+mod discard;
+mod download;
+mod endpoint;
+mod errors;
+mod filter;
+mod follow;
+mod headers;
+mod graphql;
+mod groups;
+mod gzip;
+mod har;
+mod head;
+mod health;
+mod helpers;
+mod limits;
+mod markdown;
+mod merge;
+mod mitm;
+mod multipart;
+mod ndjson;
+mod netscape;
+mod notes;
+mod outcome;
+mod page;
+mod postman;
+mod proxy;
+mod redact;
+#[cfg(feature = "redis-sink")]
+mod redis_sink;
+mod report;
+mod retry;
+mod save;
+mod sink;
+mod schema;
+mod sort;
+mod sse;
+mod streamed;
+mod subscribe;
+mod summary;
+#[cfg(test)]
+mod test_support;
+mod upload;
+mod wal;
+
+use auto_flush::{AutoFlush, Seal};
+use conditional::ValidatorCache;
+use cookie_file::CookieFile;
+use cookie_watch::CookieWatch;
+use cookies::{cookie_snapshot, CookieSnapshot};
+use connection::SeenConnections;
+use errors::EntryError;
+use proxy::ClientFactory;
+use graphql::DEFAULT_GRAPHQL_QUERY_MAX_LEN;
+use health::HealthMonitor;
+use limits::{decode_text, looks_binary, parse_content_type, read_capped, CappedBody};
+use subscribe::Subscribers;
+use wal::WriteAheadLog;
+
+pub use auto_flush::DEFAULT_AUTO_FLUSH_THRESHOLD;
+pub use batch::{BatchHandle, BatchProgress, BatchResults};
+pub use cookie_file::{CookieFileOptions, DEFAULT_COOKIE_FILE_DEBOUNCE};
+pub use cookie_jar::{CookieOpts, CookieRecord};
+pub use cookie_watch::CookieChangeCallback;
+pub use cookies::{CookieChange, CookieChanges, SetCookieInfo, COOKIE_ENVELOPE_FORMAT, COOKIE_ENVELOPE_VERSION};
+pub use csv::{CsvColumn, CsvOptions};
+pub use diff::{diff_entries, BodyDiff, EntryDiff, FieldChange, StatusChange, ValueChange};
+pub use discard::DiscardSummary;
+pub use download::{DownloadInfo, DownloadOptions, DownloadReport};
+pub use endpoint::Endpoint;
+pub use errors::{ErrorKind, FailedPhase};
+pub use filter::EntryFilter;
+pub use follow::RedirectHop;
+pub use graphql::GraphqlInfo;
+pub use groups::GroupGuard;
+pub use gzip::MAX_DECOMPRESSED_BYTES;
+pub use head::LoggedHead;
+pub use health::{Health, HealthStatus, HealthThresholds};
+pub use limits::{OversizePolicy, ResponseTooLarge};
+pub use multipart::{MultipartPartInfo, MultipartSpec};
+pub use ndjson::from_ndjson;
+pub use outcome::Outcome;
+#[cfg(feature = "redis-sink")]
+pub use redis_sink::{RedisSink, RedisSinkMode, RedisSinkStats, DEFAULT_REDIS_FALLBACK_LIMIT};
+pub use report::ReportOptions;
+pub use retry::{AttemptInfo, RetriedText, RetryInfo, RetryPolicy};
+pub use schema::{from_collected_json, MergeMode, COLLECTED_SCHEMA_VERSION};
+pub use sink::{CollectorSink, FileSink, MemorySink, SinkFuture, SinkPhases};
+pub use sort::SortBy;
+pub use sse::{SseEvent, SseEventRecord, SseLog, SseStream};
+pub use streamed::TrackedResponse;
+pub use subscribe::{Backpressure, SubscriberStats, Subscription, DEFAULT_SUBSCRIPTION_CAPACITY};
+pub use summary::{EndpointStats, EndpointSummary};
+pub use upload::UploadInfo;
+pub use wal::{recover_incomplete, IncompleteRecord, WalSink, WalStats};
+
+// Ошибка чтения тела; остаётся в цепочке anyhow, чтобы её можно было классифицировать
+#[derive(Debug)]
+pub(crate) enum BodyReadError {
+    Reqwest(reqwest::Error),
+    TimedOut(Duration),
+}
+
+impl std::fmt::Display for BodyReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyReadError::Reqwest(e) => write!(f, "{}", e),
+            BodyReadError::TimedOut(limit) => write!(f, "body read timed out after {} ms", limit.as_millis()),
+        }
+    }
+}
+
+impl std::error::Error for BodyReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BodyReadError::Reqwest(e) => Some(e),
+            BodyReadError::TimedOut(_) => None,
+        }
+    }
+}
+
+// Чтение тела с необязательным собственным пределом времени
+async fn read_body<T>(
+    limit: Option<Duration>,
+    read: impl Future<Output = reqwest::Result<T>>,
+) -> std::result::Result<T, BodyReadError> {
+    match limit {
+        Some(limit) => match tokio::time::timeout(limit, read).await {
+            Ok(result) => result.map_err(BodyReadError::Reqwest),
+            Err(_) => Err(BodyReadError::TimedOut(limit)),
+        },
+        None => read.await.map_err(BodyReadError::Reqwest),
+    }
+}
+
+// Тело прочитано целиком, но его размер расходится с Content-Length. Не проверяем
+// HEAD/204/304, намеренно обрезанные тела и пути, где тело не читалось вовсе.
+fn length_mismatch(method: &str, resp: &ResponseData) -> bool {
+    let Some(declared) = resp.content_length_header else {
+        return false;
+    };
+    let no_body = method.eq_ignore_ascii_case("HEAD")
+        || resp.status < 200
+        || resp.status == 204
+        || resp.status == 304;
+    let not_read = resp.body_encoding == BodyEncoding::Omitted && resp.response_body_bytes == 0;
+    !no_body && !not_read && !resp.body_truncated && declared != resp.response_body_bytes
+}
+
+// Все заголовки в порядке HeaderMap, повторы сохраняются
+fn ordered_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
+        .collect()
+}
+
+pub(crate) struct ResponseHead {
+    status: u16,
+    headers: HashMap<String, String>,
+    headers_ordered: Vec<(String, String)>,
+    set_cookies: Vec<String>,
+    http_version: String,
+    remote_addr: Option<String>,
+    local_addr: Option<String>,
+    connection_reused: Option<bool>,
+    content_length_header: Option<u64>,
+}
+
+impl ResponseHead {
+    // Ответ без тела: когда тело прочитать не удалось, а статус и заголовки уже есть
+    fn without_body(self, final_url: String, start: Instant, ttfb_ms: u64) -> Result<ResponseData> {
+        let (response_time, response_time_ms) = timestamp()?;
+        Ok(ResponseData {
+            status: self.status,
+            headers: self.headers,
+            headers_ordered: self.headers_ordered,
+            set_cookies: self.set_cookies,
+            http_version: self.http_version,
+            remote_addr: self.remote_addr,
+            local_addr: self.local_addr,
+            connection_reused: self.connection_reused,
+            content_length_header: self.content_length_header,
+            final_url: Some(final_url),
+            response_time,
+            response_time_ms,
+            duration_ms: start.elapsed().as_millis() as u64,
+            ttfb_ms: Some(ttfb_ms),
+            body_encoding: BodyEncoding::Omitted,
+            ..Default::default()
+        })
+    }
+}
+
+// application/json и производные вроде application/problem+json
+fn is_json_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+// Ближайшая граница символа не правее i
+fn floor_char_boundary(s: &str, i: usize) -> usize {
+    let mut i = i.min(s.len());
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+// Ближайшая граница символа не левее i
+fn ceil_char_boundary(s: &str, i: usize) -> usize {
+    let mut i = i.min(s.len());
+    while !s.is_char_boundary(i) {
+        i += 1;
+    }
+    i
+}
+
+// Функция для усечения строки до max символов (режем по границе UTF-8)
 fn truncate(s: &str, max: usize) -> String {
     if s.len() > max {
-        format!("{}...", &s[0..max-3])
+        let cut = floor_char_boundary(s, max.saturating_sub(3));
+        format!("{}...", &s[..cut])
     } else {
         s.to_string()
     }
 }
 
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn msk_offset() -> Result<FixedOffset> {
+    FixedOffset::east_opt(3 * 3600).context("Failed to create MSK timezone offset")
+}
+
+fn now_msk() -> Result<DateTime<FixedOffset>> {
+    Ok(Utc::now().with_timezone(&msk_offset()?))
+}
+
+// Один и тот же момент строкой RFC3339 (МСК) и в миллисекундах с эпохи
+fn timestamp() -> Result<(String, i64)> {
+    let now = now_msk()?;
+    Ok((now.to_rfc3339(), now.timestamp_millis()))
+}
+
+// Простейший glob: '*' совпадает с любой подстрокой
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let mut rest = text;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            match rest.strip_prefix(part) {
+                Some(r) => rest = r,
+                None => return false,
+            }
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            match rest.find(part) {
+                Some(pos) => rest = &rest[pos + part.len()..],
+                None => return false,
+            }
+        }
+    }
+    true
+}
+
+// Политика сохранения тела ответа в коллекторе (вызывающему всегда отдаётся полное тело)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub enum BodyCapture {
+    #[default]
+    Full,
+    Excerpt { head_bytes: usize, tail_bytes: usize },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BodyExcerpt {
+    pub head_len: usize,
+    pub tail_len: usize,
+    pub skipped: usize,
+}
+
+impl BodyCapture {
+    // Возвращает сохраняемое тело и описание выборки, если тело было сокращено
+    fn apply(&self, body: &str) -> (String, Option<BodyExcerpt>) {
+        match *self {
+            BodyCapture::Full => (body.to_string(), None),
+            BodyCapture::Excerpt { head_bytes, tail_bytes } => {
+                if body.len() <= head_bytes.saturating_add(tail_bytes) {
+                    return (body.to_string(), None);
+                }
+                let head_end = floor_char_boundary(body, head_bytes);
+                let tail_start = ceil_char_boundary(body, body.len() - tail_bytes).max(head_end);
+                let excerpt = BodyExcerpt {
+                    head_len: head_end,
+                    tail_len: body.len() - tail_start,
+                    skipped: tail_start - head_end,
+                };
+                let stored = format!(
+                    "{}…<skipped {} bytes>…{}",
+                    &body[..head_end],
+                    excerpt.skipped,
+                    &body[tail_start..]
+                );
+                (stored, Some(excerpt))
+            }
+        }
+    }
+}
+
+// Как тело ответа лежит в коллекторе
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyEncoding {
+    #[default]
+    Utf8,
+    Base64,
+    // тело не сохранено, есть только длина и sha256
+    Omitted,
+}
+
+// Бинарные тела больше этого размера не кладём в коллектор целиком
+const DEFAULT_BINARY_INLINE_LIMIT: usize = 256 * 1024;
+// Сколько байт потокового ответа копируем в коллектор
+const DEFAULT_STREAM_CAPTURE_LIMIT: usize = 64 * 1024;
+// До какого размера capture_body буферизует тело целиком
+const DEFAULT_CAPTURE_BODY_MAX_BYTES: usize = 1024 * 1024;
+// Заголовки, которые reqwest подставляет в каждый запрос сам
+const REQWEST_DEFAULT_HEADERS: [&str; 1] = ["accept"];
+// Сколько байт текстового тела запроса или ответа хранится в записи
+const DEFAULT_MAX_LOGGED_BODY_BYTES: usize = 64 * 1024;
+
+// Настройки клиента, меняются на лету через set_*-методы
+#[derive(Debug, Clone)]
+struct Settings {
+    body_capture: BodyCapture,
+    // (шаблон URL, политика); побеждает первое совпадение
+    body_capture_rules: Vec<(String, BodyCapture)>,
+    binary_inline_limit: usize,
+    stream_capture_limit: usize,
+    capture_body: bool,
+    capture_body_max_bytes: usize,
+    max_logged_body_bytes: usize,
+    // умолчания, с которыми собран inner (user_agent и т.п.), в нижнем регистре
+    client_default_headers: Vec<String>,
+    // таймаут, с которым собран inner (если известен)
+    client_timeout: Option<Duration>,
+    global_tags: HashMap<String, String>,
+    key_policy: KeyCollisionPolicy,
+    head_timeout: Option<Duration>,
+    max_response_bytes: Option<usize>,
+    oversize_policy: OversizePolicy,
+    parse_json_responses: bool,
+    graphql_query_max_len: usize,
+    hash_bodies: bool,
+    store_cookie_jar: bool,
+    prune_expired_cookies: bool,
+    // открытые begin_group, от внешней к вложенной
+    group_stack: Vec<String>,
+    // в нижнем регистре
+    redacted_names: Vec<String>,
+    // приёмники записей помимо коллектора
+    sinks: Vec<Arc<dyn CollectorSink>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            body_capture: BodyCapture::default(),
+            body_capture_rules: Vec::new(),
+            binary_inline_limit: DEFAULT_BINARY_INLINE_LIMIT,
+            stream_capture_limit: DEFAULT_STREAM_CAPTURE_LIMIT,
+            capture_body: false,
+            capture_body_max_bytes: DEFAULT_CAPTURE_BODY_MAX_BYTES,
+            max_logged_body_bytes: DEFAULT_MAX_LOGGED_BODY_BYTES,
+            client_default_headers: REQWEST_DEFAULT_HEADERS.iter().map(|h| h.to_string()).collect(),
+            client_timeout: None,
+            global_tags: HashMap::new(),
+            key_policy: KeyCollisionPolicy::default(),
+            head_timeout: None,
+            max_response_bytes: None,
+            oversize_policy: OversizePolicy::default(),
+            parse_json_responses: false,
+            graphql_query_max_len: DEFAULT_GRAPHQL_QUERY_MAX_LEN,
+            hash_bodies: true,
+            store_cookie_jar: false,
+            prune_expired_cookies: false,
+            group_stack: Vec::new(),
+            redacted_names: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    fn body_capture_for(&self, url: &str) -> &BodyCapture {
+        self.body_capture_rules
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, url))
+            .map(|(_, policy)| policy)
+            .unwrap_or(&self.body_capture)
+    }
+}
+
+// Что делать, если запись с таким ключом уже есть в коллекторе
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyCollisionPolicy {
+    // старая запись заменяется; ответ запроса, чью запись заменили, в новую не попадает
+    #[default]
+    Overwrite,
+    // запрос не отправляется, вызов возвращает ошибку
+    Error,
+    // новая запись ложится под key#2, key#3, ...
+    Suffix,
+}
+
+// Разобранный URL запроса для группировки по хосту и пути; логин и пароль не хранятся
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct UrlParts {
+    pub scheme: String,
+    pub host: Option<String>,
+    // явный порт или порт схемы по умолчанию
+    pub port: Option<u16>,
+    pub path: String,
+    // RequestBuilder сам переносит логин и пароль из URL в Authorization,
+    // так что true бывает только у Request, собранного вручную
+    pub had_userinfo: bool,
+}
+
+impl UrlParts {
+    fn from_url(url: &reqwest::Url) -> Self {
+        UrlParts {
+            scheme: url.scheme().to_string(),
+            host: url.host_str().map(str::to_string),
+            port: url.port_or_known_default(),
+            path: url.path().to_string(),
+            had_userinfo: !url.username().is_empty() || url.password().is_some(),
+        }
+    }
+}
+
 // Структуры для данных (с добавлением времен)
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct RequestData {
     pub method: String,
     pub endpoint: String,
@@ -29,16 +482,157 @@ pub struct RequestData {
     pub body: Option<String>,
     pub cookies: HashMap<String, String>,
     pub request_time: String,
+    // тот же момент, что request_time, в мс с эпохи
+    #[serde(default)]
+    pub request_time_ms: i64,
+    // бизнес-контекст вызывающего (order_id и т.п.), по сети не передаётся
+    #[serde(default)]
+    pub context: HashMap<String, String>,
+    // структурированная форма тела (для form/json-хелперов)
+    #[serde(default)]
+    pub body_parsed: Option<Value>,
+    // метаданные частей multipart-формы (тело потоковое, само не сохраняется)
+    #[serde(default)]
+    pub multipart: Option<Vec<MultipartPartInfo>>,
+    // файл, отправленный потоком вместо тела
+    #[serde(default)]
+    pub upload: Option<UploadInfo>,
+    // действовавшие таймауты: весь запрос и отдельно чтение тела
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub body_timeout_ms: Option<u64>,
+    // тело есть, но потоковое: в body его нет
+    #[serde(default)]
+    pub body_streaming: bool,
+    // None — тело потоковое или его нет
+    #[serde(default)]
+    pub request_body_bytes: Option<u64>,
+    // тело с Content-Type application/json, если оно разбирается
+    #[serde(default)]
+    pub body_json: Option<Value>,
+    // как ушли в запрос: по порядку и с повторами (в headers повтор затирает значение)
+    #[serde(default)]
+    pub headers_ordered: Vec<(String, String)>,
+    // имена из headers_ordered: порядок, в котором их отправит HTTP/1.1
+    #[serde(default)]
+    pub header_order: Vec<String>,
+    // что добавится при отправке поверх header_order (см. headers::implicit_headers)
+    #[serde(default)]
+    pub implicit_headers: Vec<String>,
+    // разобранная строка запроса: по порядку, с повторами, уже декодированная
+    #[serde(default)]
+    pub query_params: Vec<(String, String)>,
+    #[serde(default)]
+    pub url_parts: UrlParts,
+    // бинарное тело лежит в body как base64
+    #[serde(default)]
+    pub body_encoding: BodyEncoding,
+    // в body не всё тело; исходная длина в байтах, если известна
+    #[serde(default)]
+    pub body_truncated: bool,
+    #[serde(default)]
+    pub body_original_len: Option<u64>,
+    // None — тела нет, оно потоковое или хэширование выключено
+    #[serde(default)]
+    pub request_body_sha256: Option<String>,
+    // через какой прокси ушёл запрос (без логина и пароля); None — напрямую
+    #[serde(default)]
+    pub proxy: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+// Почему сохранённое тело неполное
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyTruncation {
+    // упёрлись в лимит захвата
+    CaptureLimit,
+    // вызывающий не дочитал поток
+    Caller,
+    // тело больше max_response_bytes
+    SizeLimit,
+    // BodyCapture::Excerpt оставил начало и конец
+    Excerpt,
+    // тело длиннее max_logged_body_bytes
+    LogLimit,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ResponseData {
     pub status: u16,
     pub headers: HashMap<String, String>,
     pub body: String,
     pub set_cookies: Vec<String>,
+    // set_cookies в разобранном виде, в том же порядке
+    #[serde(default)]
+    pub set_cookies_parsed: Vec<SetCookieInfo>,
     pub response_time: String,
+    #[serde(default)]
+    pub response_time_ms: i64,
     pub duration_ms: u64,
+    #[serde(default)]
+    pub body_excerpt: Option<BodyExcerpt>,
+    #[serde(default)]
+    pub body_encoding: BodyEncoding,
+    #[serde(default)]
+    pub response_body_bytes: u64,
+    #[serde(default)]
+    pub response_body_sha256: Option<String>,
+    // до заголовков ответа; duration_ms — весь вызов, body_ms — чтение тела после заголовков
+    #[serde(default)]
+    pub ttfb_ms: Option<u64>,
+    #[serde(default)]
+    pub body_ms: Option<u64>,
+    #[serde(default)]
+    pub body_truncated: bool,
+    #[serde(default)]
+    pub truncated_by: Option<BodyTruncation>,
+    // полный размер обрезанного тела в байтах, если он известен
+    #[serde(default)]
+    pub body_original_len: Option<u64>,
+    // значение Content-Length из заголовков ответа
+    #[serde(default, alias = "content_length")]
+    pub content_length_header: Option<u64>,
+    // Content-Length не совпал с числом прочитанных байт (оборванная передача)
+    #[serde(default)]
+    pub length_mismatch: bool,
+    // 304 на условный запрос: вызывающему отдано тело из кэша валидаторов
+    #[serde(default)]
+    pub served_from_cache: bool,
+    // на каком байте остановили чтение по max_response_bytes
+    #[serde(default)]
+    pub body_truncated_at: Option<usize>,
+    // только с set_parse_json_responses(true)
+    #[serde(default)]
+    pub body_json: Option<Value>,
+    #[serde(default)]
+    pub headers_ordered: Vec<(String, String)>,
+    // "HTTP/1.1", "HTTP/2.0", ...
+    #[serde(default)]
+    pub http_version: String,
+    // адрес, с которого пришёл ответ; через прокси — адрес прокси
+    #[serde(default)]
+    pub remote_addr: Option<String>,
+    #[serde(default)]
+    pub local_addr: Option<String>,
+    // пришёл ли ответ по уже использованному соединению (keep-alive); оценка,
+    // см. TrackedClient::mark_connection. None — определить не удалось
+    #[serde(default)]
+    pub connection_reused: Option<bool>,
+    // из Content-Type: тип без параметров и объявленная кодировка, по ней декодируется тело
+    #[serde(default)]
+    pub content_type: Option<String>,
+    #[serde(default)]
+    pub charset: Option<String>,
+    // трейлеры после тела (grpc-status и т.п.); пусто, если их нет или тело не дочитано
+    #[serde(default)]
+    pub trailers: HashMap<String, String>,
+    // URL, с которого пришёл ответ, после всех редиректов
+    #[serde(default)]
+    pub final_url: Option<String>,
+    // final_url отличается от endpoint запроса
+    #[serde(default)]
+    pub redirected: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -46,209 +640,1429 @@ pub struct RequestResponseData {
     pub request_data: RequestData,
     pub response_data: Option<ResponseData>,
     pub error: Option<String>,
+    // полный дамп хранилища после ответа, только с set_store_cookie_jar(true)
     pub cookies: Option<String>,
+    // что этот ответ поменял в куках
+    #[serde(default)]
+    pub cookie_changes: Option<CookieChanges>,
+    // куки на момент начала запроса, для cookie_changes
+    #[serde(skip)]
+    pub(crate) cookies_before: Option<CookieSnapshot>,
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    // логическое имя Endpoint для группировки статистики
+    #[serde(default)]
+    pub endpoint_name: Option<String>,
+    #[serde(default)]
+    pub download: Option<DownloadInfo>,
+    #[serde(default)]
+    pub retry: Option<RetryInfo>,
+    // метки вызова поверх глобальных (set_global_tags)
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    // заметки, добавленные после ответа (annotate)
+    #[serde(default)]
+    pub notes: Vec<String>,
+    #[serde(default)]
+    pub sse: Option<SseLog>,
+    #[serde(default)]
+    pub graphql: Option<GraphqlInfo>,
+    // промежуточные ответы tracked_send_follow, по порядку
+    #[serde(default)]
+    pub redirect_chain: Vec<RedirectHop>,
+    // попытки отправки; у одиночного вызова — ровно одна
+    #[serde(default)]
+    pub attempts: Vec<AttemptInfo>,
+    // номер записи в порядке создания, уникален в пределах клиента; 0 — запись старого формата
+    #[serde(default)]
+    pub seq: u64,
+    // класс ошибки из error и можно ли повторить запрос
+    #[serde(default)]
+    pub error_kind: Option<ErrorKind>,
+    #[serde(default)]
+    pub is_retryable: Option<bool>,
+    // при ошибке отправки: на каком шаге и сколько прошло от начала запроса до неё
+    #[serde(default)]
+    pub failed_phase: Option<FailedPhase>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    // статусы, считающиеся успехом (пусто — любой 2xx), и итог по ним
+    #[serde(default)]
+    pub expected_status: Vec<u16>,
+    #[serde(default)]
+    pub outcome: Outcome,
+    // шаг сценария: begin_group или SendOptions::group
+    #[serde(default)]
+    pub group: Option<String>,
+    // можно ли уже забрать запись автосбросом; загруженные записи запечатаны
+    #[serde(skip)]
+    pub(crate) seal: Seal,
+    // seq, выданный begin_entry; перенумерация его не трогает. По нему завершение находит
+    // свою запись, даже если ключ уже занят более новым запросом; у загруженных записей 0
+    #[serde(skip)]
+    pub(crate) issued_seq: u64,
+}
+
+// Результат tracked_send_text: полное тело плюс итоговый URL после редиректов
+#[derive(Debug, Clone)]
+pub struct LoggedText {
+    // ключ записи в коллекторе
+    pub key: String,
+    // её seq (RequestResponseData::seq на момент создания)
+    pub seq: u64,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    pub final_url: String,
+    pub redirected: bool,
+    // тело обрезано по max_response_bytes (OversizePolicy::Truncate)
+    pub truncated: bool,
+    pub http_version: String,
+}
+
+// Результат tracked_send_bytes: тело без каких-либо преобразований
+#[derive(Debug, Clone)]
+pub struct LoggedBytes {
+    pub key: String,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: Bytes,
+    pub final_url: String,
+    pub redirected: bool,
+    pub truncated: bool,
+}
+
+// Параметры отдельного вызова tracked_send_with
+#[derive(Debug, Clone, Default)]
+pub struct SendOptions {
+    pub context: HashMap<String, String>,
+    pub tags: HashMap<String, String>,
+    // вместо таймаута клиента; действует до конца чтения тела
+    pub timeout: Option<Duration>,
+    // отдельный предел на чтение тела после получения заголовков
+    pub body_timeout: Option<Duration>,
+    // вместо max_response_bytes / политики клиента
+    pub max_response_bytes: Option<usize>,
+    pub oversize_policy: Option<OversizePolicy>,
+    // какие статусы считать успехом (RequestResponseData::outcome); пусто — любой 2xx
+    pub expected_status: Vec<u16>,
+    // вместо текущей begin_group; полный путь, например "checkout/payment"
+    pub group: Option<String>,
+    pub(crate) body_parsed: Option<Value>,
+    pub(crate) batch_id: Option<String>,
+    pub(crate) endpoint_name: Option<String>,
+    pub(crate) multipart: Option<Vec<MultipartPartInfo>>,
+    pub(crate) upload: Option<UploadInfo>,
+    pub(crate) graphql: Option<GraphqlInfo>,
+}
+
+impl SendOptions {
+    pub fn new() -> Self {
+        SendOptions::default()
+    }
+
+    pub fn context(mut self, context: HashMap<String, String>) -> Self {
+        self.context.extend(context);
+        self
+    }
+
+    pub fn context_value(mut self, key: &str, value: &str) -> Self {
+        self.context.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn tags(mut self, tags: HashMap<String, String>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn body_timeout(mut self, timeout: Duration) -> Self {
+        self.body_timeout = Some(timeout);
+        self
+    }
+
+    pub fn max_response_bytes(mut self, limit: usize, policy: OversizePolicy) -> Self {
+        self.max_response_bytes = Some(limit);
+        self.oversize_policy = Some(policy);
+        self
+    }
+
+    pub fn expected_status(mut self, statuses: &[u16]) -> Self {
+        self.expected_status = statuses.to_vec();
+        self
+    }
+
+    pub fn group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_string());
+        self
+    }
 }
 
+// Коллектор, сериализуемый как объект с записями в порядке seq
+struct BySeq<'a>(Vec<(&'a String, &'a RequestResponseData)>);
+
+impl<'a> BySeq<'a> {
+    fn new(coll: &'a IndexMap<String, RequestResponseData>) -> Self {
+        let mut entries: Vec<_> = coll.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.seq);
+        BySeq(entries)
+    }
+}
+
+impl Serialize for BySeq<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().copied())
+    }
+}
+
+#[derive(Clone)]
 pub struct TrackedClient {
     pub inner: Client,
-    pub collector: Arc<Mutex<HashMap<String, RequestResponseData>>>,
+    // записи в порядке создания
+    pub collector: Arc<Mutex<IndexMap<String, RequestResponseData>>>,
     pub cookie_store: Arc<CookieStoreMutex>,
+    settings: Arc<RwLock<Settings>>,
+    health: Arc<std::sync::Mutex<HealthMonitor>>,
+    wal: Arc<std::sync::Mutex<Option<WriteAheadLog>>>,
+    subscribers: Arc<std::sync::Mutex<Subscribers>>,
+    auto_flush: Arc<std::sync::Mutex<AutoFlush>>,
+    cookie_watch: Arc<std::sync::Mutex<CookieWatch>>,
+    cookie_file: Arc<std::sync::Mutex<Option<CookieFile>>>,
+    // сквозной номер запроса для автоключей
+    seq: Arc<AtomicU64>,
+    // порядковый номер записи (RequestResponseData::seq)
+    entry_seq: Arc<AtomicU64>,
+    validators: Arc<std::sync::Mutex<ValidatorCache>>,
+    // тот же клиент, но без автоматических редиректов (tracked_send_follow)
+    no_redirect: Client,
+    // из чего собраны inner и no_redirect, без прокси
+    factory: ClientFactory,
+    // прокси, с которым собраны inner и no_redirect, без логина и пароля
+    proxy: Option<String>,
+    connections: Arc<std::sync::Mutex<SeenConnections>>,
 }
 
-impl TrackedClient {
-    pub fn new() -> Result<Self> {
-        let store = Arc::new(CookieStoreMutex::new(CookieStore::new(None)));
-        let client = Client::builder()
-            .cookie_provider(store.clone())
-            .build()
-            .context("Failed to build HTTP client")?;
+fn dump_cookie_store(cookie_store: &CookieStoreMutex) -> Result<String> {
+    let store = cookie_store
+        .lock()
+        .map_err(|e| anyhow!("Cookie store lock error: {}", e))?;
+
+    let cookies: Vec<&cookie_store::Cookie<'static>> = store.iter_any().collect();
+    serde_json::to_string(&cookies)
+        .context("Failed to serialize cookies array to string")
+}
+
+fn read_settings(settings: &RwLock<Settings>) -> RwLockReadGuard<'_, Settings> {
+    settings.read().unwrap_or_else(|e| e.into_inner())
+}
+
+// Всё, что нужно для записи в коллектор без ссылки на клиент (например, из Drop)
+#[derive(Clone)]
+pub(crate) struct Recorder {
+    collector: Arc<Mutex<IndexMap<String, RequestResponseData>>>,
+    cookie_store: Arc<CookieStoreMutex>,
+    settings: Arc<RwLock<Settings>>,
+    health: Arc<std::sync::Mutex<HealthMonitor>>,
+    wal: Arc<std::sync::Mutex<Option<WriteAheadLog>>>,
+    subscribers: Arc<std::sync::Mutex<Subscribers>>,
+    auto_flush: Arc<std::sync::Mutex<AutoFlush>>,
+    cookie_watch: Arc<std::sync::Mutex<CookieWatch>>,
+    cookie_file: Arc<std::sync::Mutex<Option<CookieFile>>>,
+}
+
+// Запись под key, если это та самая, что заведена с seq, а не заменившая её по
+// KeyCollisionPolicy::Overwrite
+fn entry_mut<'a>(
+    coll: &'a mut IndexMap<String, RequestResponseData>,
+    key: &str,
+    seq: u64,
+) -> Option<&'a mut RequestResponseData> {
+    coll.get_mut(key).filter(|entry| entry.issued_seq == seq)
+}
+
+impl Recorder {
+    fn health(&self) -> std::sync::MutexGuard<'_, HealthMonitor> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // wal_id — из WriteAheadLog::begin; None, если журнал был выключен
+    fn wal_end(&self, wal_id: Option<&str>, status: Option<u16>, error: Option<&str>) {
+        let Some(id) = wal_id else {
+            return;
+        };
+        if let Some(wal) = self.wal.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            wal.end(id, status, error);
+        }
+    }
+
+    // Куки после ответа: снимок для cookie_changes и, если включён, полный дамп
+    fn cookie_state(&self) -> Result<(CookieSnapshot, Option<String>)> {
+        if read_settings(&self.settings).prune_expired_cookies {
+            cookie_jar::prune_expired(&self.cookie_store)?;
+        }
+        let dump = if read_settings(&self.settings).store_cookie_jar {
+            Some(dump_cookie_store(&self.cookie_store)?)
+        } else {
+            None
+        };
+        let snapshot = cookie_snapshot(&self.cookie_store)?;
+        cookie_watch::notify_cookie_changes(&self.cookie_watch, &self.cookie_store)?;
+        cookie_file::note_cookies(&self.cookie_file, &self.cookie_store);
+        Ok((snapshot, dump))
+    }
+
+    // Кладёт ответ в запись, применяя политику сохранения тела
+    fn apply_response(
+        &self,
+        wal_id: Option<&str>,
+        entry: &mut RequestResponseData,
+        mut resp_data: ResponseData,
+        (cookies_after, cookies): (CookieSnapshot, Option<String>),
+        error: Option<EntryError>,
+    ) {
+        if let Some(ct) = resp_data.headers.get("content-type") {
+            (resp_data.content_type, resp_data.charset) = parse_content_type(ct);
+        }
+        let mut cut_to_limit = false;
+        if resp_data.body_encoding == BodyEncoding::Utf8 {
+            let settings = read_settings(&self.settings);
+            if settings.parse_json_responses
+                && !resp_data.body_truncated
+                && resp_data.content_type.as_deref().is_some_and(is_json_content_type)
+            {
+                resp_data.body_json = serde_json::from_str(&resp_data.body).ok();
+            }
+            let (body, excerpt) = settings
+                .body_capture_for(&entry.request_data.endpoint)
+                .apply(&resp_data.body);
+            resp_data.body = body;
+            resp_data.body_excerpt = excerpt;
+            if resp_data.body.len() > settings.max_logged_body_bytes {
+                let end = floor_char_boundary(&resp_data.body, settings.max_logged_body_bytes);
+                resp_data.body.truncate(end);
+                cut_to_limit = true;
+            }
+        }
+        resp_data.set_cookies_parsed = resp_data.set_cookies.iter().map(|raw| SetCookieInfo::parse(raw)).collect();
+        resp_data.length_mismatch = length_mismatch(&entry.request_data.method, &resp_data);
+        resp_data.redirected = resp_data.final_url.as_ref().map(|url| *url != entry.request_data.endpoint);
+        // выборка — тоже обрезка, но на расхождение с Content-Length не влияет
+        if resp_data.body_excerpt.is_some() && !resp_data.body_truncated {
+            resp_data.body_truncated = true;
+            resp_data.truncated_by = Some(BodyTruncation::Excerpt);
+        }
+        if cut_to_limit && !resp_data.body_truncated {
+            resp_data.body_truncated = true;
+            resp_data.truncated_by = Some(BodyTruncation::LogLimit);
+        }
+        if resp_data.body_truncated && resp_data.body_original_len.is_none() {
+            // при обрезке чтения известна только заявленная длина; иначе тело прочитано целиком
+            resp_data.body_original_len = match resp_data.truncated_by {
+                Some(BodyTruncation::SizeLimit | BodyTruncation::Caller) => resp_data.content_length_header,
+                _ => Some(resp_data.response_body_bytes),
+            };
+        }
+        let message = error.as_ref().map(|e| e.message.clone());
+        let failure = message
+            .clone()
+            .or_else(|| (resp_data.status >= 500).then(|| format!("HTTP {}", resp_data.status)));
+        self.health()
+            .observe(&entry.request_data.endpoint, Some(resp_data.duration_ms), failure);
+        self.wal_end(wal_id, Some(resp_data.status), message.as_deref());
+        if entry.attempts.is_empty() {
+            entry.attempts.push(AttemptInfo {
+                attempt: 1,
+                started_at: entry.request_data.request_time.clone(),
+                duration_ms: resp_data.duration_ms,
+                status: Some(resp_data.status),
+                error: message,
+            });
+        }
+        entry.response_data = Some(resp_data);
+        entry.cookies = cookies;
+        if let Some(before) = entry.cookies_before.take() {
+            entry.cookie_changes = Some(CookieChanges::between(&before, &cookies_after));
+        }
+        if let Some(error) = error {
+            error.apply(entry);
+        }
+        entry.refresh_outcome();
+    }
+
+    // error — ответ получен, но дочитать/обработать его не удалось
+    async fn finish(&self, key: &str, seq: u64, wal_id: Option<&str>, resp_data: ResponseData, error: Option<EntryError>) -> Result<()> {
+        let cookies = self.cookie_state()?;
+        let finished = {
+            let mut coll = self.collector.lock().await;
+            match entry_mut(&mut coll, key, seq) {
+                Some(entry) => {
+                    self.apply_response(wal_id, entry, resp_data, cookies, error);
+                    self.finished_copy(entry)
+                }
+                None => {
+                    self.superseded(wal_id, &resp_data, error.as_ref());
+                    None
+                }
+            }
+        };
+        if let Some(entry) = finished {
+            self.deliver(key, entry).await;
+        }
+        Ok(())
+    }
+
+    // Записи уже нет или под ключом лежит более новый запрос: ответ в неё не пишется,
+    // но строка WAL завершается
+    fn superseded(&self, wal_id: Option<&str>, resp_data: &ResponseData, error: Option<&EntryError>) {
+        self.wal_end(wal_id, Some(resp_data.status), error.map(|e| e.message.as_str()));
+    }
+
+    fn subscribed(&self) -> bool {
+        !subscribe::lock_subscribers(&self.subscribers).is_empty()
+    }
+
+    // Запись завершена (вызывается один раз на запись): печать, если её не держит обёртка,
+    // и копия для приёмников и подписчиков; None — раздавать некому
+    fn finished_copy(&self, entry: &mut RequestResponseData) -> Option<RequestResponseData> {
+        auto_flush::seal_finished(&self.auto_flush, entry);
+        (self.subscribed() || !read_settings(&self.settings).sinks.is_empty()).then(|| entry.clone())
+    }
+
+    // Вызывается без блокировки коллектора: приёмник или подписчик с Block может ждать
+    async fn deliver(&self, key: &str, entry: RequestResponseData) {
+        let sinks = read_settings(&self.settings).sinks.clone();
+        for sink in &sinks {
+            sink.record(key, &entry).await;
+        }
+        if self.subscribed() {
+            subscribe::publish(&self.subscribers, key, entry).await;
+        }
+    }
 
+    // Для Drop: в рантайме — фоновой задачей, вне его — на временном рантайме
+    fn deliver_detached(&self, key: String, entry: RequestResponseData) {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let recorder = self.clone();
+                handle.spawn(async move { recorder.deliver(&key, entry).await });
+            }
+            Err(_) => {
+                if let Ok(rt) = tokio::runtime::Builder::new_current_thread().enable_all().build() {
+                    rt.block_on(self.deliver(&key, entry));
+                }
+            }
+        }
+    }
+
+    // Запись только что заведена: её получают приёмники с SinkPhases::Both
+    pub(crate) async fn record_started(&self, key: &str, entry: RequestResponseData) {
+        let sinks = read_settings(&self.settings).sinks.clone();
+        for sink in sinks.iter().filter(|sink| sink.phases() == SinkPhases::Both) {
+            sink.record(key, &entry).await;
+        }
+    }
+
+    // Дополнение уже завершённой записи рассылается приёмникам заново, подписчикам — нет:
+    // они получают запись один раз
+    pub(crate) async fn update(&self, key: &str, seq: u64, f: impl FnOnce(&mut RequestResponseData)) {
+        let updated = {
+            let mut coll = self.collector.lock().await;
+            entry_mut(&mut coll, key, seq).and_then(|entry| {
+                f(entry);
+                let has_sinks = !read_settings(&self.settings).sinks.is_empty();
+                (has_sinks && entry.outcome != Outcome::Pending).then(|| entry.clone())
+            })
+        };
+        if let Some(entry) = updated {
+            let sinks = read_settings(&self.settings).sinks.clone();
+            for sink in &sinks {
+                sink.record(key, &entry).await;
+            }
+        }
+    }
+
+    async fn fail(&self, key: &str, seq: u64, wal_id: Option<&str>, error: EntryError) {
+        self.wal_end(wal_id, None, Some(&error.message));
+        let mut coll = self.collector.lock().await;
+        let finished = self.fail_locked(&mut coll, key, seq, error);
+        drop(coll);
+        if let Some(entry) = finished {
+            self.deliver(key, entry).await;
+        }
+    }
+
+    // Some — копия записи для приёмников и подписчиков
+    fn fail_locked(
+        &self,
+        coll: &mut IndexMap<String, RequestResponseData>,
+        key: &str,
+        seq: u64,
+        error: EntryError,
+    ) -> Option<RequestResponseData> {
+        let entry = entry_mut(coll, key, seq)?;
+        self.health()
+            .observe(&entry.request_data.endpoint, None, Some(error.message.clone()));
+        match entry.attempts.last_mut() {
+            // ответ уже записан, не удалось его дочитать
+            Some(last) => last.error = Some(error.message.clone()),
+            None => {
+                let started_at = entry.request_data.request_time.clone();
+                let duration_ms = DateTime::parse_from_rfc3339(&started_at)
+                    .ok()
+                    .zip(now_msk().ok())
+                    .map(|(start, now)| (now - start).num_milliseconds().max(0) as u64)
+                    .unwrap_or_default();
+                entry.attempts.push(AttemptInfo {
+                    attempt: 1,
+                    started_at,
+                    duration_ms,
+                    status: None,
+                    error: Some(error.message.clone()),
+                });
+            }
+        }
+        error.apply(entry);
+        self.finished_copy(entry)
+    }
+
+    // Синхронная запись для Drop: если коллектор занят, дописываем в фоновой задаче
+    pub(crate) fn finish_detached(&self, key: String, seq: u64, wal_id: Option<String>, resp_data: ResponseData, error: Option<EntryError>) {
+        let cookies = self.cookie_state().unwrap_or_default();
+        let write = move |recorder: &Recorder, coll: &mut IndexMap<String, RequestResponseData>| {
+            match entry_mut(coll, &key, seq) {
+                Some(entry) => {
+                    recorder.apply_response(wal_id.as_deref(), entry, resp_data, cookies, error);
+                    if let Some(entry) = recorder.finished_copy(entry) {
+                        recorder.deliver_detached(key, entry);
+                    }
+                }
+                None => recorder.superseded(wal_id.as_deref(), &resp_data, error.as_ref()),
+            }
+        };
+        if let Ok(mut coll) = self.collector.try_lock() {
+            write(self, &mut coll);
+            return;
+        }
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                let recorder = self.clone();
+                handle.spawn(async move {
+                    let mut coll = recorder.collector.lock().await;
+                    write(&recorder, &mut coll);
+                });
+            }
+            Err(_) => {
+                let mut coll = self.collector.blocking_lock();
+                write(self, &mut coll);
+            }
+        }
+    }
+}
+
+impl TrackedClient {
+    fn assemble(
+        builder: impl Fn() -> ClientBuilder + Send + Sync + 'static,
+        cookie_store: Arc<CookieStoreMutex>,
+        proxy: Option<&str>,
+    ) -> Result<Self> {
+        let factory: ClientFactory = Arc::new(builder);
+        let (inner, no_redirect) = proxy::build_clients(&factory, proxy)?;
         Ok(TrackedClient {
-            inner: client,
-            collector: Arc::new(Mutex::new(HashMap::new())),
-            cookie_store: store,
+            inner,
+            no_redirect,
+            factory,
+            proxy: proxy.map(proxy::strip_credentials),
+            collector: Arc::new(Mutex::new(IndexMap::new())),
+            cookie_store,
+            settings: Arc::new(RwLock::new(Settings::default())),
+            health: Arc::new(std::sync::Mutex::new(HealthMonitor::default())),
+            wal: Arc::new(std::sync::Mutex::new(None)),
+            subscribers: Arc::new(std::sync::Mutex::new(Subscribers::default())),
+            auto_flush: Arc::new(std::sync::Mutex::new(AutoFlush::default())),
+            cookie_watch: Arc::new(std::sync::Mutex::new(CookieWatch::default())),
+            cookie_file: Arc::new(std::sync::Mutex::new(None)),
+            seq: Arc::new(AtomicU64::new(1)),
+            entry_seq: Arc::new(AtomicU64::new(1)),
+            validators: Arc::new(std::sync::Mutex::new(ValidatorCache::default())),
+            connections: Arc::new(std::sync::Mutex::new(SeenConnections::new())),
         })
     }
 
+    pub fn new() -> Result<Self> {
+        let store = Arc::new(CookieStoreMutex::new(CookieStore::new(None)));
+        let jar = store.clone();
+        TrackedClient::assemble(move || Client::builder().cookie_provider(jar.clone()), store, None)
+    }
+
     pub async fn from_redis_cookies(
         proxy: String,
         cookie_json: &str,
     ) -> Result<Self> {
-        let reader = Cursor::new(cookie_json);
-        let store_inner = CookieStore::load_json_all(reader)
-            .map_err(|e| anyhow!("Failed to load cookies JSON: {}", e))?;
+        let store_inner = cookies::load_cookie_store(cookie_json)?;
         let jar = Arc::new(CookieStoreMutex::new(store_inner));
 
-        let proxy_http = Proxy::http(&proxy)
-            .context("Invalid HTTP proxy URL")?;
-        let proxy_https = Proxy::https(&proxy)
-            .context("Invalid HTTPS proxy URL")?;
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(15))
-            .cookie_provider(jar.clone())
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")
-            .proxy(proxy_http)
-            .proxy(proxy_https)
-            .build()
-            .context("Failed to build HTTP client with proxy")?;
+        let provider = jar.clone();
+        let builder = move || {
+            Client::builder()
+                .timeout(Duration::from_secs(15))
+                .cookie_provider(provider.clone())
+                .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")
+        };
 
-        Ok(TrackedClient {
-            inner: client,
-            collector: Arc::new(Mutex::new(HashMap::new())),
-            cookie_store: jar,
-        })
+        let client = TrackedClient::assemble(builder, jar, Some(&proxy)).context("Failed to build HTTP client with proxy")?;
+        client.settings_mut().client_timeout = Some(Duration::from_secs(15));
+        client.settings_mut().client_default_headers.push("user-agent".to_string());
+        Ok(client)
     }
 
     pub async fn new_basic(
         proxy: String,
         jar: Arc<CookieStoreMutex>,
     ) -> Result<Self> {
-        let proxy_http = Proxy::http(&proxy)
-            .context("Invalid HTTP proxy URL")?;
-        let proxy_https = Proxy::https(&proxy)
-            .context("Invalid HTTPS proxy URL")?;
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .cookie_provider(jar.clone())
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")
-            .proxy(proxy_http)
-            .proxy(proxy_https)
-            .build()
-            .context("Failed to build basic HTTP client with proxy")?;
+        let provider = jar.clone();
+        let builder = move || {
+            Client::builder()
+                .timeout(Duration::from_secs(10))
+                .cookie_provider(provider.clone())
+                .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")
+        };
 
-        Ok(TrackedClient {
-            inner: client,
-            collector: Arc::new(Mutex::new(HashMap::new())),
-            cookie_store: jar,
-        })
+        let client = TrackedClient::assemble(builder, jar, Some(&proxy)).context("Failed to build basic HTTP client with proxy")?;
+        client.settings_mut().client_timeout = Some(Duration::from_secs(10));
+        client.settings_mut().client_default_headers.push("user-agent".to_string());
+        Ok(client)
     }
 
-    pub fn dump_cookies(&self) -> Result<String> {
-        let store = self.cookie_store
-            .lock()
-            .map_err(|e| anyhow!("Cookie store lock error: {}", e))?;
+    fn settings(&self) -> RwLockReadGuard<'_, Settings> {
+        read_settings(&self.settings)
+    }
 
-        let mut buf: Vec<u8> = Vec::new();
-        store
-            .save_incl_expired_and_nonpersistent_json(&mut buf)
-            .map_err(|e| anyhow!("Failed to save cookies to JSON buffer: {}", e))?;
+    fn health_monitor(&self) -> std::sync::MutexGuard<'_, HealthMonitor> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
 
-        let raw = String::from_utf8(buf)
-            .context("Failed to convert cookie buffer to UTF-8 string")?;
+    fn settings_mut(&self) -> RwLockWriteGuard<'_, Settings> {
+        self.settings.write().unwrap_or_else(|e| e.into_inner())
+    }
 
-        let mut arr: Vec<Value> = Vec::new();
-        for line in raw.lines() {
-            if line.trim().is_empty() {
-                continue;
-            }
-            let v: Value = serde_json::from_str(line)
-                .context("Invalid cookie JSON line format")?;
-            arr.push(v);
-        }
-        serde_json::to_string(&arr)
-            .context("Failed to serialize cookies array to string")
+    // Политика по умолчанию для тел ответов в коллекторе
+    pub fn set_body_capture(&self, policy: BodyCapture) {
+        self.settings_mut().body_capture = policy;
     }
 
-    // теперь возвращает ResponseData для дальнейшего использования
-    pub async fn tracked_send(&self, key: &str, builder: RequestBuilder) -> Result<ResponseData> {
-        let mut req = builder
-            .build()
-            .context("Failed to build request")?;
+    // Переопределение политики для URL по шаблону ('*' — любая подстрока)
+    pub fn add_body_capture_rule(&self, url_pattern: &str, policy: BodyCapture) {
+        self.settings_mut()
+            .body_capture_rules
+            .push((url_pattern.to_string(), policy));
+    }
 
+    pub fn clear_body_capture_rules(&self) {
+        self.settings_mut().body_capture_rules.clear();
+    }
 
-        let msk = FixedOffset::east_opt(3 * 3600)
-            .context("Failed to create MSK timezone offset")?;
-        let request_time = Utc::now().with_timezone(&msk).to_rfc3339();
+    // Порог, после которого бинарное тело хранится только как длина + sha256
+    pub fn set_binary_inline_limit(&self, bytes: usize) {
+        self.settings_mut().binary_inline_limit = bytes;
+    }
+
+    // sha256 тел запроса и ответа (по всем полученным байтам, даже если сохранены не все).
+    // tracked_send_bytes считает хэш ответа всегда: без тела это единственный след.
+    pub fn set_hash_bodies(&self, enabled: bool) {
+        self.settings_mut().hash_bodies = enabled;
+    }
+
+    // Сколько байт текстового тела (запроса и ответа) сохраняется в записи;
+    // вызывающий всё равно получает тело целиком
+    pub fn set_max_logged_body_bytes(&self, bytes: usize) {
+        self.settings_mut().max_logged_body_bytes = bytes;
+    }
+
+    // Сколько байт из tracked_send_streamed попадёт в коллектор
+    pub fn set_stream_capture_limit(&self, bytes: usize) {
+        self.settings_mut().stream_capture_limit = bytes;
+    }
+
+    // tracked_execute и tracked_send_response будут заранее читать тело (до max_bytes) и сразу
+    // его логировать; вызывающий получает Response с теми же байтами
+    pub fn set_capture_body(&self, enabled: bool, max_bytes: usize) {
+        let mut settings = self.settings_mut();
+        settings.capture_body = enabled;
+        settings.capture_body_max_bytes = max_bytes;
+    }
+
+    pub fn set_key_collision_policy(&self, policy: KeyCollisionPolicy) {
+        self.settings_mut().key_policy = policy;
+    }
+
+    // Метки для всех последующих записей; метки вызова их перекрывают
+    pub fn set_global_tags(&self, tags: HashMap<String, String>) {
+        self.settings_mut().global_tags = tags;
+    }
+
+    // Разбирать JSON-ответы в ResponseData.body_json
+    pub fn set_parse_json_responses(&self, enabled: bool) {
+        self.settings_mut().parse_json_responses = enabled;
+    }
+
+    // Класть в каждую запись полный дамп кук (cookies) в дополнение к cookie_changes
+    pub fn set_store_cookie_jar(&self, enabled: bool) {
+        self.settings_mut().store_cookie_jar = enabled;
+    }
+
+    // Удалять истёкшие куки из хранилища после каждого ответа
+    pub fn set_prune_expired_cookies(&self, enabled: bool) {
+        self.settings_mut().prune_expired_cookies = enabled;
+    }
+
+    pub fn dump_cookies(&self) -> Result<String> {
+        dump_cookie_store(&self.cookie_store)
+    }
+
+    fn capture_request(&self, req: &Request, opts: &SendOptions) -> Result<RequestData> {
+        let (request_time, request_time_ms) = timestamp()?;
 
         let method = req.method().as_str().to_string();
         let endpoint = req.url().to_string();
-        let headers = req
-            .headers()
-            .iter()
-            .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
+        let query_params = req
+            .url()
+            .query_pairs()
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
             .collect();
-        let body = req
+        let headers_ordered = ordered_headers(req.headers());
+        let headers = headers_ordered.iter().cloned().collect();
+        let header_order = headers_ordered.iter().map(|(name, _)| name.clone()).collect();
+        let content_type = req
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok());
+        let bytes = req.body().and_then(|b| b.as_bytes());
+        let body_encoding = match bytes {
+            Some(b) if looks_binary(b, content_type) => BodyEncoding::Base64,
+            _ => BodyEncoding::Utf8,
+        };
+        let request_body_sha256 = bytes
+            .filter(|b| !b.is_empty() && self.settings().hash_bodies)
+            .map(sha256_hex);
+        let mut body = bytes.map(|b| match body_encoding {
+            BodyEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(b),
+            _ => String::from_utf8_lossy(b).to_string(),
+        });
+        let mut body_original_len = None;
+        let limit = self.settings().max_logged_body_bytes;
+        if let Some(text) = body.as_mut().filter(|t| body_encoding == BodyEncoding::Utf8 && t.len() > limit) {
+            body_original_len = bytes.map(|b| b.len() as u64);
+            text.truncate(floor_char_boundary(text, limit));
+        }
+        let body_streaming = req.body().is_some_and(|b| b.as_bytes().is_none());
+        let request_body_bytes = req
             .body()
             .and_then(|b| b.as_bytes())
-            .map(|b| String::from_utf8_lossy(b).to_string());
-
-        let url = req.url().clone();
-        let cookies_sent = {
-            let store = self.cookie_store
-                .lock()
-                .map_err(|e| anyhow!("Cookie store lock error: {}", e))?;
-            store
-                .get_request_cookies(&url)
-                .map(|c| (c.name().to_string(), c.value().to_string()))
-                .collect()
+            .map(|b| b.len() as u64)
+            .or_else(|| opts.upload.as_ref().map(|u| u.size));
+        let body_json = content_type
+            .filter(|ct| is_json_content_type(ct))
+            .and_then(|_| req.body()?.as_bytes())
+            .and_then(|b| serde_json::from_slice(b).ok());
+
+        let cookies: HashMap<String, String> = self.cookies_for_url(req.url())?.into_iter().collect();
+        let implicit_headers = headers::implicit_headers(req, &self.settings().client_default_headers, !cookies.is_empty());
+
+        Ok(RequestData {
+            method,
+            endpoint,
+            headers,
+            body,
+            cookies,
+            request_time,
+            request_time_ms,
+            context: opts.context.clone(),
+            body_parsed: opts.body_parsed.clone(),
+            multipart: opts.multipart.clone(),
+            upload: opts.upload.clone(),
+            timeout_ms: opts
+                .timeout
+                .or(self.settings().client_timeout)
+                .map(|t| t.as_millis() as u64),
+            body_timeout_ms: opts.body_timeout.map(|t| t.as_millis() as u64),
+            body_streaming,
+            request_body_bytes,
+            body_json,
+            headers_ordered,
+            header_order,
+            implicit_headers,
+            query_params,
+            url_parts: UrlParts::from_url(req.url()),
+            body_encoding,
+            body_truncated: body_original_len.is_some(),
+            body_original_len,
+            request_body_sha256,
+            proxy: self.proxy.clone(),
+        })
+    }
+
+    // Заводит запись по политике коллизий и возвращает ключ, под которым она легла, и её seq:
+    // finish_entry, fail_entry и update_entry дописывают только запись с этим seq
+    async fn begin_entry(&self, key: &str, req_data: RequestData, opts: &SendOptions) -> Result<(String, u64)> {
+        let (policy, mut tags) = {
+            let settings = self.settings();
+            (settings.key_policy, settings.global_tags.clone())
+        };
+        // явная группа вызова надёжнее общего стека, который видят все задачи
+        let group = opts.group.clone().or_else(|| self.current_group());
+        tags.extend(opts.tags.clone());
+        let cookies_before = cookie_snapshot(&self.cookie_store)?;
+        let mut coll = self.collector.lock().await;
+        let key = match policy {
+            KeyCollisionPolicy::Overwrite => key.to_string(),
+            KeyCollisionPolicy::Error if coll.contains_key(key) => {
+                return Err(anyhow!("Collector key '{}' already exists", key));
+            }
+            KeyCollisionPolicy::Error => key.to_string(),
+            KeyCollisionPolicy::Suffix => {
+                let mut candidate = key.to_string();
+                let mut n = 2;
+                while coll.contains_key(&candidate) {
+                    candidate = format!("{}#{}", key, n);
+                    n += 1;
+                }
+                candidate
+            }
         };
+        // номер берётся под блокировкой коллектора, поэтому растёт в порядке вставки
+        let seq = self.entry_seq.fetch_add(1, Ordering::Relaxed);
+        // перезаписанная запись уходит в конец, как новый запрос
+        coll.shift_remove(&key);
+        coll.insert(
+            key.clone(),
+            RequestResponseData {
+                request_data: req_data,
+                response_data: None,
+                error: None,
+                cookies: None,
+                cookie_changes: None,
+                cookies_before: Some(cookies_before),
+                batch_id: opts.batch_id.clone(),
+                endpoint_name: opts.endpoint_name.clone(),
+                download: None,
+                retry: None,
+                tags,
+                notes: Vec::new(),
+                sse: None,
+                graphql: opts.graphql.clone(),
+                redirect_chain: Vec::new(),
+                attempts: Vec::new(),
+                seq,
+                error_kind: None,
+                is_retryable: None,
+                failed_phase: None,
+                duration_ms: None,
+                expected_status: opts.expected_status.clone(),
+                outcome: Outcome::Pending,
+                group,
+                seal: auto_flush::initial_seal(&key, seq),
+                issued_seq: seq,
+            },
+        );
+        let started = read_settings(&self.settings)
+            .sinks
+            .iter()
+            .any(|sink| sink.phases() == SinkPhases::Both)
+            .then(|| coll.get(&key).cloned())
+            .flatten();
+        drop(coll);
+        if let Some(entry) = started {
+            self.recorder().record_started(&key, entry).await;
+        }
+        Ok((key, seq))
+    }
 
-        let req_data = RequestData { method, endpoint, headers, body, cookies: cookies_sent, request_time };
-        {
-            let mut coll = self.collector.lock().await;
-            coll.insert(
-                key.to_string(),
-                RequestResponseData { request_data: req_data.clone(), response_data: None, error: None, cookies: None },
-            );
+    async fn fail_entry(&self, key: &str, seq: u64, wal_id: Option<&str>, error: EntryError) {
+        self.recorder().fail(key, seq, wal_id, error).await
+    }
+
+    fn write_ahead(&self) -> std::sync::MutexGuard<'_, Option<WriteAheadLog>> {
+        self.wal.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn recorder(&self) -> Recorder {
+        Recorder {
+            collector: self.collector.clone(),
+            cookie_store: self.cookie_store.clone(),
+            settings: self.settings.clone(),
+            health: self.health.clone(),
+            wal: self.wal.clone(),
+            subscribers: self.subscribers.clone(),
+            auto_flush: self.auto_flush.clone(),
+            cookie_watch: self.cookie_watch.clone(),
+            cookie_file: self.cookie_file.clone(),
+        }
+    }
+
+    async fn finish_entry(&self, key: &str, seq: u64, wal_id: Option<&str>, resp_data: ResponseData) -> Result<()> {
+        self.recorder().finish(key, seq, wal_id, resp_data, None).await
+    }
+
+    async fn finish_entry_with_error(
+        &self,
+        key: &str,
+        seq: u64,
+        wal_id: Option<&str>,
+        resp_data: ResponseData,
+        error: EntryError,
+    ) -> Result<()> {
+        self.recorder().finish(key, seq, wal_id, resp_data, Some(error)).await
+    }
+
+    // Точечное дополнение уже созданной записи
+    async fn update_entry(&self, key: &str, seq: u64, f: impl FnOnce(&mut RequestResponseData)) {
+        self.recorder().update(key, seq, f).await
+    }
+
+    fn response_head(resp: &Response) -> ResponseHead {
+        let status = resp.status().as_u16();
+        let headers_ordered = ordered_headers(resp.headers());
+        let headers = headers_ordered.iter().cloned().collect();
+        let set_cookies = resp
+            .headers()
+            .get_all("set-cookie")
+            .iter()
+            .map(|v| v.to_str().unwrap_or("").to_string())
+            .collect();
+        let http_version = format!("{:?}", resp.version());
+        let remote_addr = resp.remote_addr().map(|addr| addr.to_string());
+        let local_addr = connection::local_addr(resp);
+        let connection_reused = connection::connection_reused(resp);
+        let content_length_header = resp
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
         }
+    }
 
+    // Записывает запрос в коллектор и выполняет его; ошибка транспорта фиксируется в записи.
+    // Возвращает фактический ключ записи (см. KeyCollisionPolicy), её seq и id строки WAL,
+    // которые передаются в finish_entry/fail_entry.
+    async fn start_tracked(
+        &self,
+        key: &str,
+        req: Request,
+        opts: &SendOptions,
+    ) -> Result<(Response, Instant, String, u64, Option<String>)> {
+        let (req, key, seq, wal_id) = self.begin_tracked(key, req, opts).await?;
         let start = Instant::now();
-        let response = self.inner.execute(req).await;
-        let duration_ms = start.elapsed().as_millis() as u64;
-        let response_time = Utc::now().with_timezone(&msk).to_rfc3339();
+        let resp = self.execute_tracked(&self.inner, &key, seq, wal_id.as_deref(), req).await?;
+        Ok((resp, start, key, seq, wal_id))
+    }
+
+    // builder.build(), но и неудачная сборка попадает в коллектор: запись с URL, если он
+    // разобрался, и failed_phase = build. Если завести запись нельзя (KeyCollisionPolicy::Error),
+    // возвращается сама ошибка сборки.
+    async fn build_tracked(&self, key: &str, builder: RequestBuilder) -> Result<Request> {
+        let err = match builder.build() {
+            Ok(req) => return Ok(req),
+            Err(e) => e,
+        };
+        let (request_time, request_time_ms) = timestamp()?;
+        let req_data = RequestData {
+            endpoint: err.url().map(|url| url.to_string()).unwrap_or_default(),
+            request_time,
+            request_time_ms,
+            proxy: self.proxy.clone(),
+            ..Default::default()
+        };
+        if let Ok((key, seq)) = self.begin_entry(key, req_data, &SendOptions::default()).await {
+            self.fail_entry(&key, seq, None, EntryError::from_error(&err).in_phase(FailedPhase::Build)).await;
+        }
+        Err(anyhow::Error::new(err).context("Failed to build request"))
+    }
 
-        let resp_data = match response {
+    // Первая половина start_tracked: запись в коллекторе и WAL, без отправки
+    async fn begin_tracked(
+        &self,
+        key: &str,
+        mut req: Request,
+        opts: &SendOptions,
+    ) -> Result<(Request, String, u64, Option<String>)> {
+        if let Some(timeout) = opts.timeout {
+            *req.timeout_mut() = Some(timeout);
+        }
+        let req_data = self.capture_request(&req, opts)?;
+        let (method, endpoint) = (req_data.method.clone(), req_data.endpoint.clone());
+        let (key, seq) = self.begin_entry(key, req_data, opts).await?;
+        let wal_id = self
+            .write_ahead()
+            .as_mut()
+            .map(|wal| wal.begin(&key, &method, &endpoint, self.proxy.clone()));
+        Ok((req, key, seq, wal_id))
+    }
+
+    async fn execute_tracked(&self, client: &Client, key: &str, seq: u64, wal_id: Option<&str>, req: Request) -> Result<Response> {
+        match client.execute(req).await {
             Ok(mut resp) => {
-                let status = resp.status().as_u16();
-                let headers = resp
-                    .headers()
-                    .iter()
-                    .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
-                    .collect();
-                let set_cookies = resp
-                    .headers()
-                    .get_all("set-cookie")
-                    .iter()
-                    .map(|v| v.to_str().unwrap_or("").to_string())
-                    .collect();
-                let body = resp.text().await.context("Failed to read response body")?;
-
-                ResponseData { status, headers, body, set_cookies, response_time, duration_ms }
+                self.mark_connection(&mut resp);
+                Ok(resp)
             }
             Err(e) => {
-                let mut coll = self.collector.lock().await;
-                if let Some(entry) = coll.get_mut(key) {
-                    entry.error = Some(e.to_string());
-                }
-                return Err(anyhow!("Request execution failed: {}", e));
+                self.fail_entry(key, seq, wal_id, EntryError::from_error(&e).in_phase(FailedPhase::Execute)).await;
+                let message = format!("Request execution failed: {}", e);
+                Err(anyhow::Error::new(e).context(message))
             }
+        }
+    }
+
+    // Общий путь: выполнить запрос, прочитать тело как текст, записать всё в коллектор.
+    // Возвращает полные данные ответа и итоговый URL.
+    async fn send_text(
+        &self,
+        key: &str,
+        req: Request,
+        opts: &SendOptions,
+    ) -> Result<(String, u64, ResponseData, String, String)> {
+        let (resp, start, key, seq, wal_id) = self.start_tracked(key, req, opts).await?;
+        let (resp_data, text, final_url) = self.complete_text(&key, seq, wal_id.as_deref(), resp, start, opts).await?;
+        Ok((key, seq, resp_data, text, final_url))
+    }
+
+    // Чтение тела уже полученного ответа и запись его в коллектор. Бинарное тело
+    // сохраняется как в send_bytes, а вызывающему всё равно отдаётся текст.
+    async fn complete_text(
+        &self,
+        key: &str,
+        seq: u64,
+        wal_id: Option<&str>,
+        resp: Response,
+        start: Instant,
+        opts: &SendOptions,
+    ) -> Result<(ResponseData, String, String)> {
+        let final_url = resp.url().to_string();
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        let ttfb_ms = start.elapsed().as_millis() as u64;
+        let headers_at = Instant::now();
+        let (CappedBody { bytes: raw, truncated_at, trailers }, oversize) = self.read_limited(key, seq, wal_id, resp, start, opts).await?;
+        let content_type = headers.get("content-type").map(String::as_str);
+        let text = decode_text(&raw, content_type);
+        let (body, body_encoding) = if looks_binary(&raw, content_type) {
+            self.store_binary(&raw)
+        } else {
+            (text.clone(), BodyEncoding::Utf8)
         };
+        let response_body_sha256 = (!raw.is_empty() && self.settings().hash_bodies).then(|| sha256_hex(&raw));
+        let body_ms = headers_at.elapsed().as_millis() as u64;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let (response_time, response_time_ms) = timestamp()?;
 
-        // Обновляем хранилище и возвращаем данные
-        {
-            let mut coll = self.collector.lock().await;
-            if let Some(entry) = coll.get_mut(key) {
-                entry.response_data = Some(resp_data.clone());
-                entry.cookies = Some(self.dump_cookies()?);
+        let resp_data = ResponseData {
+            status,
+            headers,
+            response_body_bytes: raw.len() as u64,
+            body,
+            body_encoding,
+            response_body_sha256,
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(final_url.clone()),
+            response_time,
+            response_time_ms,
+            duration_ms,
+            ttfb_ms: Some(ttfb_ms),
+            body_ms: Some(body_ms),
+            body_truncated: truncated_at.is_some(),
+            truncated_by: truncated_at.map(|_| BodyTruncation::SizeLimit),
+            body_truncated_at: truncated_at,
+            trailers,
+            ..Default::default()
+        };
+        self.finish_limited(key, seq, wal_id, resp_data.clone(), oversize).await?;
+        Ok((resp_data, text, final_url))
+    }
+
+    // base64 или, если тело больше binary_inline_limit, ничего
+    fn store_binary(&self, body: &[u8]) -> (String, BodyEncoding) {
+        if body.len() > self.settings().binary_inline_limit {
+            (String::new(), BodyEncoding::Omitted)
+        } else {
+            (base64::engine::general_purpose::STANDARD.encode(body), BodyEncoding::Base64)
+        }
+    }
+
+    // То же, что send_text, но тело читается как есть; в коллектор идёт base64 или хэш
+    async fn send_bytes(
+        &self,
+        key: &str,
+        req: Request,
+        opts: &SendOptions,
+    ) -> Result<(String, u64, ResponseData, Bytes, String)> {
+        let (resp, start, key, seq, wal_id) = self.start_tracked(key, req, opts).await?;
+        let (key, wal_id) = (key.as_str(), wal_id.as_deref());
+
+        let final_url = resp.url().to_string();
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        let ttfb_ms = start.elapsed().as_millis() as u64;
+        let headers_at = Instant::now();
+        let (CappedBody { bytes: body, truncated_at, trailers }, oversize) = self.read_limited(key, seq, wal_id, resp, start, opts).await?;
+        let body_ms = headers_at.elapsed().as_millis() as u64;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let (response_time, response_time_ms) = timestamp()?;
+
+        let (stored, body_encoding) = self.store_binary(&body);
+        let resp_data = ResponseData {
+            status,
+            headers,
+            body: stored,
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(final_url.clone()),
+            response_time,
+            response_time_ms,
+            duration_ms,
+            ttfb_ms: Some(ttfb_ms),
+            body_ms: Some(body_ms),
+            body_encoding,
+            response_body_bytes: body.len() as u64,
+            response_body_sha256: Some(sha256_hex(&body)),
+            body_truncated: truncated_at.is_some(),
+            truncated_by: truncated_at.map(|_| BodyTruncation::SizeLimit),
+            body_truncated_at: truncated_at,
+            trailers,
+            ..Default::default()
+        };
+        self.finish_limited(key, seq, wal_id, resp_data.clone(), oversize).await?;
+        Ok((key.to_string(), seq, resp_data, body, final_url))
+    }
+
+    // Тело с учётом max_response_bytes и body_timeout. Если Content-Length уже больше
+    // предела, тело не читается вовсе. Второе значение — ошибка для OversizePolicy::Error.
+    async fn read_limited(
+        &self,
+        key: &str,
+        seq: u64,
+        wal_id: Option<&str>,
+        resp: Response,
+        start: Instant,
+        opts: &SendOptions,
+    ) -> Result<(CappedBody, Option<ResponseTooLarge>)> {
+        let ttfb_ms = start.elapsed().as_millis() as u64;
+        let final_url = resp.url().to_string();
+        let head = Self::response_head(&resp);
+        let (limit, policy) = {
+            let settings = self.settings();
+            (
+                opts.max_response_bytes.or(settings.max_response_bytes),
+                opts.oversize_policy.unwrap_or(settings.oversize_policy),
+            )
+        };
+        let declared = resp.content_length();
+        if let (Some(limit), Some(len)) = (limit, declared) {
+            if len > limit as u64 {
+                let oversize = (policy == OversizePolicy::Error).then_some(ResponseTooLarge { limit, declared });
+                let empty = CappedBody { bytes: Bytes::new(), truncated_at: Some(0), trailers: HashMap::new() };
+                return Ok((empty, oversize));
+            }
+        }
+        match read_body(opts.body_timeout, read_capped(resp, limit)).await {
+            Ok(body) => {
+                let oversize = body
+                    .truncated_at
+                    .filter(|_| policy == OversizePolicy::Error)
+                    .map(|limit| ResponseTooLarge { limit, declared: None });
+                Ok((body, oversize))
+            }
+            Err(e) => {
+                // статус и заголовки уже пришли, их не теряем
+                let partial = head.without_body(final_url, start, ttfb_ms)?;
+                let error = EntryError::from_error(&e).in_phase(FailedPhase::BodyRead);
+                self.finish_entry_with_error(key, seq, wal_id, partial, error).await?;
+                let message = format!("Failed to read response body: {}", e);
+                Err(anyhow::Error::new(e).context(message))
             }
         }
+    }
+
+    async fn finish_limited(
+        &self,
+        key: &str,
+        seq: u64,
+        wal_id: Option<&str>,
+        resp_data: ResponseData,
+        oversize: Option<ResponseTooLarge>,
+    ) -> Result<()> {
+        match oversize {
+            Some(e) => {
+                self.finish_entry_with_error(key, seq, wal_id, resp_data, EntryError::from_error(&e)).await?;
+                Err(anyhow::Error::new(e))
+            }
+            None => self.finish_entry(key, seq, wal_id, resp_data).await,
+        }
+    }
+
+    // теперь возвращает ResponseData для дальнейшего использования
+    pub async fn tracked_send(&self, key: &str, builder: RequestBuilder) -> Result<ResponseData> {
+        let req = self.build_tracked(key, builder).await?;
+        let (_, _, resp_data, _, _) = self.send_text(key, req, &SendOptions::default()).await?;
         Ok(resp_data)
     }
 
+    // Готовый Request без билдера. Тело ответа читает вызывающий, поэтому в запись
+    // попадают только статус и заголовки (для логирования тела — tracked_send_streamed
+    // или set_capture_body).
+    pub async fn tracked_execute(&self, key: &str, req: Request) -> Result<Response> {
+        let (resp, start, key, seq, wal_id) = self.start_tracked(key, req, &SendOptions::default()).await?;
+        let (capture_body, capture_max) = {
+            let settings = self.settings();
+            (settings.capture_body, settings.capture_body_max_bytes)
+        };
+        if capture_body {
+            return self.capture_response(&key, seq, wal_id.as_deref(), resp, start, capture_max).await;
+        }
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let (response_time, response_time_ms) = timestamp()?;
+        let resp_data = ResponseData {
+            status,
+            headers,
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            final_url: Some(resp.url().to_string()),
+            response_time,
+            response_time_ms,
+            duration_ms,
+            // тело не читается, весь вызов — ожидание заголовков
+            ttfb_ms: Some(duration_ms),
+            body_encoding: BodyEncoding::Omitted,
+            ..Default::default()
+        };
+        self.finish_entry(&key, seq, wal_id.as_deref(), resp_data).await?;
+        Ok(resp)
+    }
+
+    // То же с чтением тела, как в tracked_send_text
+    pub async fn tracked_execute_text(&self, key: &str, req: Request) -> Result<LoggedText> {
+        self.send_logged(key, req, &SendOptions::default()).await
+    }
+
+    pub async fn tracked_send_text(&self, key: &str, builder: RequestBuilder) -> Result<LoggedText> {
+        self.tracked_send_with(key, builder, &SendOptions::default()).await
+    }
+
+    pub async fn tracked_send_with(
+        &self,
+        key: &str,
+        builder: RequestBuilder,
+        opts: &SendOptions,
+    ) -> Result<LoggedText> {
+        let req = self.build_tracked(key, builder).await?;
+        self.send_logged(key, req, opts).await
+    }
+
+    async fn send_logged(&self, key: &str, req: Request, opts: &SendOptions) -> Result<LoggedText> {
+        let orig_url = req.url().to_string();
+        let (key, seq, resp_data, body, final_url) = self.send_text(key, req, opts).await?;
+        Ok(LoggedText {
+            key,
+            seq,
+            truncated: resp_data.body_truncated,
+            http_version: resp_data.http_version,
+            status: resp_data.status,
+            headers: resp_data.headers,
+            body,
+            redirected: final_url != orig_url,
+            final_url,
+        })
+    }
+
+    // Вместе с ответом — копия записи в окончательном виде (ответ и куки уже записаны)
+    pub async fn tracked_send_with_entry(
+        &self,
+        key: &str,
+        builder: RequestBuilder,
+        opts: &SendOptions,
+    ) -> Result<(LoggedText, RequestResponseData)> {
+        let logged = self.tracked_send_with(key, builder, opts).await?;
+        let entry = self
+            .collector
+            .lock()
+            .await
+            .get(&logged.key)
+            .filter(|entry| entry.issued_seq == logged.seq)
+            .cloned()
+            .ok_or_else(|| anyhow!("Collector entry '{}' was removed before snapshot", logged.key))?;
+        Ok((logged, entry))
+    }
+
+    pub async fn tracked_send_with_tags(
+        &self,
+        key: &str,
+        builder: RequestBuilder,
+        tags: HashMap<String, String>,
+    ) -> Result<LoggedText> {
+        self.tracked_send_with(key, builder, &SendOptions::new().tags(tags)).await
+    }
+
+    pub async fn tracked_send_bytes(&self, key: &str, builder: RequestBuilder) -> Result<LoggedBytes> {
+        let req = self.build_tracked(key, builder).await?;
+        let orig_url = req.url().to_string();
+        let (key, _, resp_data, body, final_url) = self.send_bytes(key, req, &SendOptions::default()).await?;
+        Ok(LoggedBytes {
+            key,
+            truncated: resp_data.body_truncated,
+            status: resp_data.status,
+            headers: resp_data.headers,
+            body,
+            redirected: final_url != orig_url,
+            final_url,
+        })
+    }
+
     pub async fn get_collected_data(&self) -> Result<String> {
         let coll = self.collector.lock().await;
         serde_json::to_string(&*coll).context("Failed to serialize collected data")
     }
 
+    // Копия коллектора без сериализации, в порядке коллектора (порядок отправки — collected_vec)
+    pub async fn collected(&self) -> IndexMap<String, RequestResponseData> {
+        self.collector.lock().await.clone()
+    }
+
+    // Забирает только завершённые записи; запросы в пути остаются и завершатся в коллекторе
+    pub async fn take_collected(&self) -> IndexMap<String, RequestResponseData> {
+        auto_flush::take_finalized(&mut *self.collector.lock().await)
+    }
+
+    // Записи в порядке seq
+    pub async fn collected_vec(&self) -> Vec<(String, RequestResponseData)> {
+        let coll = self.collector.lock().await;
+        BySeq::new(&coll).0.into_iter().map(|(key, entry)| (key.clone(), entry.clone())).collect()
+    }
+
+    // То же, но записи идут по seq, то есть в порядке отправки
+    pub async fn get_collected_data_sorted(&self) -> Result<String> {
+        let coll = self.collector.lock().await;
+        serde_json::to_string(&BySeq::new(&coll)).context("Failed to serialize collected data")
+    }
+
     pub async fn get_pretty_truncated_data(&self) -> Result<String> {
         let raw = self.get_collected_data().await?;
-        let mut data: Value = serde_json::from_str(&raw).context("Failed to parse collected JSON")?;
+        self.pretty_truncated(&raw)
+    }
+
+    pub async fn get_pretty_truncated_data_sorted(&self) -> Result<String> {
+        let raw = self.get_collected_data_sorted().await?;
+        self.pretty_truncated(&raw)
+    }
+
+    fn pretty_truncated(&self, raw: &str) -> Result<String> {
+        let mut data: Value = serde_json::from_str(raw).context("Failed to parse collected JSON")?;
+        redact::redact_value(&mut data, &self.redacted_names());
+
+        fn truncate_leaves(value: &mut Value) {
+            match value {
+                Value::String(s) => *s = truncate(s, 200),
+                Value::Array(arr) => arr.iter_mut().for_each(truncate_leaves),
+                Value::Object(map) => map.values_mut().for_each(truncate_leaves),
+                _ => {}
+            }
+        }
 
         fn truncate_fields(value: &mut Value) {
             match value {
                 Value::Object(map) => {
-                    for v in map.values_mut() {
-                        truncate_fields(v);
+                    // метки короткие и выводятся как есть; в разобранные тела не заходим,
+                    // их поля с именами headers/cookies к записи не относятся
+                    for (k, v) in map.iter_mut() {
+                        if !matches!(k.as_str(), "tags" | "body_json" | "body_parsed") {
+                            truncate_fields(v);
+                        }
+                    }
+                    // структурированное тело читается лучше сырого
+                    if map.get("body_parsed").is_some_and(|v| !v.is_null()) {
+                        map.remove("body");
+                    }
+                    if let Some(json) = map.get_mut("body_json").filter(|v| !v.is_null()) {
+                        truncate_leaves(json);
+                        map.remove("body");
+                    }
+                    // из base64 читать нечего: начало и исходный размер
+                    if map.get("body_encoding").and_then(Value::as_str) == Some("base64") {
+                        let mut shortened = None;
+                        if let Some(Value::String(s)) = map.get_mut("body").filter(|v| v.as_str().is_some_and(|s| s.len() > 64)) {
+                            let padding = s.bytes().rev().take_while(|&b| b == b'=').count();
+                            let decoded = (s.len() / 4 * 3).saturating_sub(padding);
+                            *s = format!("{} ({} bytes)", truncate(s, 64), decoded);
+                            shortened = Some(decoded);
+                        }
+                        if let Some(decoded) = shortened {
+                            map.insert("body_truncated".to_string(), Value::Bool(true));
+                            if map.get("body_original_len").is_none_or(Value::is_null) {
+                                map.insert("body_original_len".to_string(), Value::from(decoded));
+                            }
+                        }
                     }
                     if let Some(Value::Object(hdrs)) = map.get_mut("headers") {
                         for inner in hdrs.values_mut() {
@@ -257,9 +2071,40 @@ impl TrackedClient {
                             }
                         }
                     }
+                    for field in ["headers_ordered", "query_params"] {
+                        if let Some(Value::Array(pairs)) = map.get_mut(field) {
+                            for pair in pairs {
+                                if let Some(Value::String(s)) = pair.get_mut(1) {
+                                    *s = truncate(s, 50);
+                                }
+                            }
+                        }
+                    }
+                    // полный URL есть в endpoint, здесь достаточно хоста и пути
+                    if let Some(Value::Object(parts)) = map.get("url_parts") {
+                        let host = parts.get("host").and_then(Value::as_str).unwrap_or_default();
+                        let path = parts.get("path").and_then(Value::as_str).unwrap_or_default();
+                        let compact = format!("{}{}", host, path);
+                        map.insert("url_parts".to_string(), Value::String(compact));
+                    }
+                    // разбивка времени ответа одной строкой
+                    if let (Some(total), Some(ttfb)) = (
+                        map.get("duration_ms").and_then(Value::as_u64),
+                        map.get("ttfb_ms").and_then(Value::as_u64),
+                    ) {
+                        let timing = match map.get("body_ms").and_then(Value::as_u64) {
+                            Some(body) => format!("ttfb {} ms + body {} ms = {} ms", ttfb, body, total),
+                            None => format!("ttfb {} ms, total {} ms", ttfb, total),
+                        };
+                        map.insert("timing".to_string(), Value::String(timing));
+                    }
                     if let Some(Value::String(s)) = map.get_mut("cookies") {
                         *s = truncate(s, 500);
                     }
+                    // разобранные Set-Cookie нагляднее сырых строк
+                    if map.get("set_cookies_parsed").and_then(Value::as_array).is_some_and(|arr| !arr.is_empty()) {
+                        map.remove("set_cookies");
+                    }
                     if let Some(Value::Array(arr)) = map.get_mut("set_cookies") {
                         for item in arr {
                             if let Value::String(s) = item {
@@ -281,6 +2126,33 @@ impl TrackedClient {
         serde_json::to_string_pretty(&data).context("Failed to serialize pretty truncated data")
     }
 
+    // Записи, у которых в контексте есть name == value
+    pub async fn entries_with_context(&self, name: &str, value: &str) -> Vec<(String, RequestResponseData)> {
+        let coll = self.collector.lock().await;
+        coll.iter()
+            .filter(|(_, entry)| entry.request_data.context.get(name).map(String::as_str) == Some(value))
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect()
+    }
+
+    // Копия одной записи. Блокировка коллектора держится только на время клонирования,
+    // а подписчики и приёмники получают записи уже без неё, так что вызывать можно и оттуда
+    pub async fn get_entry(&self, key: &str) -> Option<RequestResponseData> {
+        self.collector.lock().await.get(key).cloned()
+    }
+
+    pub async fn get_entry_json(&self, key: &str) -> Result<Option<String>> {
+        let coll = self.collector.lock().await;
+        coll.get(key)
+            .map(|entry| serde_json::to_string(entry).context("Failed to serialize collector entry"))
+            .transpose()
+    }
+
+    // Порядок остальных записей сохраняется
+    pub async fn remove_entry(&self, key: &str) -> Option<RequestResponseData> {
+        self.collector.lock().await.shift_remove(key)
+    }
+
     pub async fn clear_collector(&self) {
         let mut coll = self.collector.lock().await;
         coll.clear();
@@ -297,4 +2169,328 @@ pub async fn example_step(client: &TrackedClient, step_id: &str) -> Result<()> {
     Ok(())
 }
 
-// dfasdfasdfdfs
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::time::Duration;
+
+    async fn echo_server() -> TestServer {
+        TestServer::start(|req| async move {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            TestResponse::ok(req.path)
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn typed_accessors_keep_order() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        for key in ["c", "a", "b"] {
+            client.tracked_send(key, client.inner.get(server.url(&format!("/{}", key)))).await.unwrap();
+        }
+        assert_eq!(client.collected().await.keys().collect::<Vec<_>>(), ["c", "a", "b"]);
+        let by_seq: Vec<String> = client.collected_vec().await.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(by_seq, ["c", "a", "b"]);
+        let taken = client.take_collected().await;
+        assert_eq!(taken.keys().collect::<Vec<_>>(), ["c", "a", "b"]);
+        assert_eq!(taken["a"].response_data.as_ref().unwrap().body, "/a");
+        assert!(client.collected().await.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn take_collected_mid_flight_loses_nothing() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        let sends: Vec<_> = (0..40)
+            .map(|n| {
+                let client = client.clone();
+                let url = server.url(&format!("/{}", n));
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(n % 8 * 5)).await;
+                    client.tracked_send(&format!("k{}", n), client.inner.get(url)).await.unwrap();
+                })
+            })
+            .collect();
+
+        let mut drained = IndexMap::new();
+        while !sends.iter().all(|send| send.is_finished()) {
+            drained.extend(client.take_collected().await);
+            tokio::time::sleep(Duration::from_millis(3)).await;
+        }
+        for send in sends {
+            send.await.unwrap();
+        }
+        drained.extend(client.take_collected().await);
+
+        assert!(client.collected().await.is_empty());
+        for (key, entry) in &drained {
+            assert!(entry.response_data.is_some() || entry.error.is_some(), "{} drained unfinished", key);
+        }
+        let mut drained: Vec<String> = drained.into_keys().collect();
+        drained.sort();
+        let mut expected: Vec<String> = (0..40).map(|n| format!("k{}", n)).collect();
+        expected.sort();
+        assert_eq!(drained, expected);
+    }
+
+    #[tokio::test]
+    async fn take_collected_leaves_pending_entries() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("done", client.inner.get(server.url("/done"))).await.unwrap();
+        let sender = client.clone();
+        let url = server.url("/slow");
+        let slow = tokio::spawn(async move { sender.tracked_send("slow", sender.inner.get(url)).await });
+        while !client.collector.lock().await.contains_key("slow") {
+            tokio::task::yield_now().await;
+        }
+
+        let taken = client.take_collected().await;
+        assert_eq!(taken.keys().collect::<Vec<_>>(), ["done"]);
+        slow.await.unwrap().unwrap();
+        let coll = client.collected().await;
+        assert_eq!(coll["slow"].response_data.as_ref().unwrap().body, "/slow");
+    }
+
+    #[tokio::test]
+    async fn overwrite_replaces_the_entry() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("k", client.inner.get(server.url("/first"))).await.unwrap();
+        client.tracked_send("k", client.inner.get(server.url("/second"))).await.unwrap();
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.len(), 1);
+        assert_eq!(coll["k"].response_data.as_ref().unwrap().body, "/second");
+    }
+
+    #[tokio::test]
+    async fn error_policy_refuses_before_sending() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        client.set_key_collision_policy(KeyCollisionPolicy::Error);
+        client.tracked_send("k", client.inner.get(server.url("/first"))).await.unwrap();
+        let err = client.tracked_send("k", client.inner.get(server.url("/second"))).await.unwrap_err();
+        assert_eq!(err.to_string(), "Collector key 'k' already exists");
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(client.collector.lock().await["k"].response_data.as_ref().unwrap().body, "/first");
+    }
+
+    #[tokio::test]
+    async fn suffix_policy_numbers_repeats() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        client.set_key_collision_policy(KeyCollisionPolicy::Suffix);
+        let mut keys = Vec::new();
+        for path in ["/a", "/b", "/c"] {
+            keys.push(client.tracked_send_text("k", client.inner.get(server.url(path))).await.unwrap().key);
+        }
+        assert_eq!(keys, ["k", "k#2", "k#3"]);
+        let coll = client.collector.lock().await;
+        assert_eq!(coll["k#3"].response_data.as_ref().unwrap().body, "/c");
+    }
+
+    #[tokio::test]
+    async fn racing_tasks_on_one_key() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+
+        client.set_key_collision_policy(KeyCollisionPolicy::Suffix);
+        let sends = (0..4).map(|_| client.tracked_send_text("race", client.inner.get(server.url("/"))));
+        let mut keys: Vec<String> = futures_util::future::join_all(sends)
+            .await
+            .into_iter()
+            .map(|r| r.unwrap().key)
+            .collect();
+        keys.sort();
+        assert_eq!(keys, ["race", "race#2", "race#3", "race#4"]);
+
+        client.set_key_collision_policy(KeyCollisionPolicy::Error);
+        let sends = (0..4).map(|_| client.tracked_send_text("solo", client.inner.get(server.url("/"))));
+        let results = futures_util::future::join_all(sends).await;
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert_eq!(client.collector.lock().await.keys().filter(|k| k.starts_with("solo")).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn overwrite_race_keeps_the_newer_entry_intact() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/slow" {
+                tokio::time::sleep(Duration::from_millis(150)).await;
+            }
+            TestResponse::ok(req.path)
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let sender = client.clone();
+        let url = server.url("/slow");
+        let slow = tokio::spawn(async move { sender.tracked_send_text("k", sender.inner.get(url)).await });
+        while !client.collector.lock().await.contains_key("k") {
+            tokio::task::yield_now().await;
+        }
+        let fast = client.tracked_send_text("k", client.inner.get(server.url("/fast"))).await.unwrap();
+        let slow = slow.await.unwrap().unwrap();
+        assert_eq!(slow.body, "/slow");
+        assert!(slow.seq < fast.seq);
+
+        // поздний ответ медленного запроса не попадает в запись, заменившую его
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.len(), 1);
+        assert!(coll["k"].request_data.endpoint.ends_with("/fast"));
+        assert_eq!(coll["k"].response_data.as_ref().unwrap().body, "/fast");
+        assert_eq!(coll["k"].seq, fast.seq);
+        assert_eq!(coll["k"].attempts.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn repeated_headers_survive_in_order() {
+        let long = "v".repeat(120);
+        let server_long = long.clone();
+        let server = TestServer::start(move |_| {
+            let long = server_long.clone();
+            async move {
+                TestResponse::ok("ok")
+                    .header("Set-Cookie", "a=1; Path=/")
+                    .header("Vary", "Accept")
+                    .header("Set-Cookie", "b=2; Path=/")
+                    .header("Set-Cookie", "c=3; Path=/")
+                    .header("Vary", "Origin")
+                    .header("X-Long", &long)
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let builder = client.inner.get(server.url("/")).header("X-Multi", "one").header("X-Multi", "two");
+        let logged = client.tracked_send_text("text", builder).await.unwrap();
+        assert_eq!(logged.status, 200);
+        client.tracked_send("data", client.inner.get(server.url("/"))).await.unwrap();
+
+        let coll = client.collector.lock().await.clone();
+        for key in ["text", "data"] {
+            let resp = coll[key].response_data.clone().unwrap();
+            let cookies: Vec<&str> = resp
+                .headers_ordered
+                .iter()
+                .filter(|(name, _)| name == "set-cookie")
+                .map(|(_, v)| v.as_str())
+                .collect();
+            assert_eq!(cookies, ["a=1; Path=/", "b=2; Path=/", "c=3; Path=/"]);
+            let vary: Vec<&str> = resp.headers_ordered.iter().filter(|(n, _)| n == "vary").map(|(_, v)| v.as_str()).collect();
+            assert_eq!(vary, ["Accept", "Origin"]);
+            assert_eq!(resp.set_cookies.len(), 3);
+        }
+        let sent: Vec<&str> = coll["text"]
+            .request_data
+            .headers_ordered
+            .iter()
+            .filter(|(n, _)| n == "x-multi")
+            .map(|(_, v)| v.as_str())
+            .collect();
+        assert_eq!(sent, ["one", "two"]);
+
+        // пары сериализуются массивами [имя, значение], в выводе значения обрезаются
+        let json: Value = serde_json::from_str(&client.get_collected_data().await.unwrap()).unwrap();
+        let pairs = json["text"]["response_data"]["headers_ordered"].as_array().unwrap();
+        assert_eq!(pairs[0], serde_json::json!(["set-cookie", "a=1; Path=/"]));
+        let pretty: Value = serde_json::from_str(&client.get_pretty_truncated_data().await.unwrap()).unwrap();
+        let long_pair = pretty["text"]["response_data"]["headers_ordered"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p[0] == "x-long")
+            .cloned()
+            .unwrap();
+        assert_eq!(long_pair[1].as_str().unwrap().len(), 50);
+        assert!(long_pair[1].as_str().unwrap().ends_with("..."));
+    }
+
+    #[tokio::test]
+    async fn concurrent_sends_get_unique_increasing_seq() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        let sends = (0..20).map(|i| {
+            let client = client.clone();
+            let url = server.url(&format!("/{}", i));
+            tokio::spawn(async move { client.tracked_send(&format!("k{}", i), client.inner.get(url)).await })
+        });
+        for handle in futures_util::future::join_all(sends).await {
+            handle.unwrap().unwrap();
+        }
+        let entries = client.collected_vec().await;
+        let seqs: Vec<u64> = entries.iter().map(|(_, e)| e.seq).collect();
+        assert_eq!(seqs.len(), 20);
+        assert!(seqs.windows(2).all(|w| w[0] < w[1]));
+        assert!(seqs.iter().all(|&seq| seq >= 1));
+
+        // сортированный вывод идёт в том же порядке seq
+        let sorted: Value = serde_json::from_str(&client.get_collected_data_sorted().await.unwrap()).unwrap();
+        let json_seqs: Vec<u64> = sorted.as_object().unwrap().values().map(|e| e["seq"].as_u64().unwrap()).collect();
+        assert_eq!(json_seqs, seqs);
+        let pretty: Value = serde_json::from_str(&client.get_pretty_truncated_data_sorted().await.unwrap()).unwrap();
+        let pretty_keys: Vec<&String> = pretty.as_object().unwrap().keys().collect();
+        let keys: Vec<&String> = entries.iter().map(|(k, _)| k).collect();
+        assert_eq!(pretty_keys, keys);
+    }
+
+    #[tokio::test]
+    async fn sequential_sends_serialize_in_send_order() {
+        let server = echo_server().await;
+        let client = TrackedClient::new().unwrap();
+        // ключи нарочно не в алфавитном порядке
+        let keys: Vec<String> = (0..10).map(|i| format!("step-{}", (i * 7) % 10)).collect();
+        for key in &keys {
+            client.tracked_send(key, client.inner.get(server.url(&format!("/{}", key)))).await.unwrap();
+        }
+        let json: Value = serde_json::from_str(&client.get_collected_data().await.unwrap()).unwrap();
+        let order: Vec<&String> = json.as_object().unwrap().keys().collect();
+        assert_eq!(order, keys.iter().collect::<Vec<_>>());
+
+        // дополнение по ключу не двигает запись
+        let seq = client.collector.lock().await["step-0"].issued_seq;
+        client
+            .update_entry("step-0", seq, |e| {
+                e.tags.insert("seen".to_string(), "yes".to_string());
+            })
+            .await;
+        let json: Value = serde_json::from_str(&client.get_collected_data().await.unwrap()).unwrap();
+        assert_eq!(json.as_object().unwrap().keys().next().map(String::as_str), Some("step-0"));
+        assert_eq!(json["step-0"]["tags"]["seen"], "yes");
+
+        let taken: Value = serde_json::from_str(&client.take_collected_data().await.unwrap()).unwrap();
+        assert_eq!(taken.as_object().unwrap().len(), 10);
+        assert!(client.collector.lock().await.is_empty());
+
+        client.tracked_send("again", client.inner.get(server.url("/again"))).await.unwrap();
+        client.clear_collector().await;
+        assert_eq!(client.get_collected_data().await.unwrap(), "{}");
+    }
+
+    #[tokio::test]
+    async fn redirects_are_recorded_without_fake_headers() {
+        let server = TestServer::start(|req| async move {
+            match req.path.as_str() {
+                "/start" => TestResponse::new(302).header("Location", "/end"),
+                _ => TestResponse::ok("landed"),
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let logged = client.tracked_send_text("moved", client.inner.get(server.url("/start"))).await.unwrap();
+        assert!(logged.redirected);
+        assert_eq!(logged.final_url, server.url("/end"));
+        client.tracked_send("direct", client.inner.get(server.url("/end"))).await.unwrap();
+
+        let coll = client.collector.lock().await;
+        let moved = &coll["moved"];
+        let resp = moved.response_data.as_ref().unwrap();
+        assert_eq!(moved.request_data.endpoint, server.url("/start"));
+        assert_eq!(resp.final_url.as_deref(), Some(server.url("/end").as_str()));
+        assert_eq!(resp.redirected, Some(true));
+        assert!(resp.header("x-final-url").is_none() && resp.header("x-orig-url").is_none());
+
+        let direct = coll["direct"].response_data.as_ref().unwrap();
+        assert_eq!(direct.final_url.as_deref(), Some(server.url("/end").as_str()));
+        assert_eq!(direct.redirected, Some(false));
+    }
+}
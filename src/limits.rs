@@ -0,0 +1,200 @@
+use crate::TrackedClient;
+use bytes::Bytes;
+use encoding_rs::{Encoding, UTF_8};
+use http_body_util::BodyExt;
+use reqwest::{Body, Response};
+use std::collections::HashMap;
+
+// Что делать, когда тело ответа больше max_response_bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizePolicy {
+    // вызов возвращает ResponseTooLarge
+    #[default]
+    Error,
+    // вызов успешен, тело обрезано (LoggedText.truncated)
+    Truncate,
+}
+
+// Тело ответа больше допустимого; declared — если это стало ясно по Content-Length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseTooLarge {
+    pub limit: usize,
+    pub declared: Option<u64>,
+}
+
+impl std::fmt::Display for ResponseTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.declared {
+            Some(len) => write!(f, "Response body of {} bytes exceeds limit of {} bytes", len, self.limit),
+            None => write!(f, "Response body exceeds limit of {} bytes", self.limit),
+        }
+    }
+}
+
+impl std::error::Error for ResponseTooLarge {}
+
+// Прочитанное тело: где обрезали по limit и трейлеры (если тело дочитано до конца)
+pub(crate) struct CappedBody {
+    pub(crate) bytes: Bytes,
+    pub(crate) truncated_at: Option<usize>,
+    pub(crate) trailers: HashMap<String, String>,
+}
+
+// Читает тело по фреймам и останавливается на limit. Фреймы, в отличие от chunk(),
+// отдают и трейлеры (chunked в HTTP/1.1, HTTP/2).
+pub(crate) async fn read_capped(resp: Response, limit: Option<usize>) -> reqwest::Result<CappedBody> {
+    let mut body = Body::from(resp);
+    let mut buf = Vec::new();
+    let mut trailers = HashMap::new();
+    while let Some(frame) = body.frame().await {
+        let chunk = match frame?.into_data() {
+            Ok(chunk) => chunk,
+            Err(frame) => {
+                if let Ok(map) = frame.into_trailers() {
+                    trailers.extend(map.iter().map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string())));
+                }
+                continue;
+            }
+        };
+        if let Some(limit) = limit {
+            let room = limit - buf.len();
+            if chunk.len() > room {
+                buf.extend_from_slice(&chunk[..room]);
+                return Ok(CappedBody { bytes: Bytes::from(buf), truncated_at: Some(limit), trailers });
+            }
+        }
+        buf.extend_from_slice(&chunk);
+    }
+    Ok(CappedBody { bytes: Bytes::from(buf), truncated_at: None, trailers })
+}
+
+// Тип без параметров и charset из Content-Type, оба в нижнем регистре
+pub(crate) fn parse_content_type(content_type: &str) -> (Option<String>, Option<String>) {
+    let mut params = content_type.split(';');
+    let mime = params
+        .next()
+        .map(|mime| mime.trim().to_ascii_lowercase())
+        .filter(|mime| !mime.is_empty());
+    let charset = params
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_ascii_lowercase())
+        .filter(|charset| !charset.is_empty());
+    (mime, charset)
+}
+
+fn is_binary_mime(mime: &str) -> bool {
+    ["image/", "audio/", "video/", "font/"].iter().any(|prefix| mime.starts_with(prefix))
+        || matches!(
+            mime,
+            "application/octet-stream"
+                | "application/pdf"
+                | "application/zip"
+                | "application/gzip"
+                | "application/wasm"
+                | "application/protobuf"
+                | "application/x-protobuf"
+                | "application/vnd.google.protobuf"
+                | "application/grpc"
+                | "application/msgpack"
+                | "application/x-msgpack"
+        )
+}
+
+fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime,
+            "application/json" | "application/xml" | "application/javascript" | "application/x-www-form-urlencoded"
+        )
+}
+
+// Бинарное ли тело: сначала по типу, затем по доле невалидного UTF-8 (больше 10%).
+// Объявленная не-UTF-8 кодировка означает текст.
+pub(crate) fn looks_binary(body: &[u8], content_type: Option<&str>) -> bool {
+    let (mime, charset) = content_type.map(parse_content_type).unwrap_or_default();
+    if let Some(mime) = mime.as_deref() {
+        if is_binary_mime(mime) {
+            return true;
+        }
+        if is_text_mime(mime) {
+            return false;
+        }
+    }
+    let declared = charset.and_then(|label| Encoding::for_label(label.as_bytes()));
+    if declared.is_some_and(|encoding| encoding != UTF_8) {
+        return false;
+    }
+
+    let mut invalid = 0;
+    let mut rest = body;
+    while let Err(e) = std::str::from_utf8(rest) {
+        let bad = e.error_len().unwrap_or(rest.len() - e.valid_up_to());
+        invalid += bad;
+        rest = &rest[e.valid_up_to() + bad..];
+    }
+    invalid * 10 > body.len()
+}
+
+// Как Response::text: кодировка из charset в Content-Type, по умолчанию UTF-8
+pub(crate) fn decode_text(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|ct| parse_content_type(ct).1)
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+impl TrackedClient {
+    // Предел тела ответа для всех вызовов; SendOptions.max_response_bytes перекрывает
+    pub fn set_max_response_bytes(&self, limit: Option<usize>, policy: OversizePolicy) {
+        let mut settings = self.settings_mut();
+        settings.max_response_bytes = limit;
+        settings.oversize_policy = policy;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+
+    #[test]
+    fn content_type_is_split_and_lowercased() {
+        assert_eq!(
+            parse_content_type("Text/HTML; Charset=\"Windows-1251\""),
+            (Some("text/html".to_string()), Some("windows-1251".to_string()))
+        );
+        assert_eq!(parse_content_type("application/json"), (Some("application/json".to_string()), None));
+        assert_eq!(parse_content_type(""), (None, None));
+    }
+
+    #[tokio::test]
+    async fn windows_1251_body_is_decoded_for_caller_and_log() {
+        let (fixture, _, _) = encoding_rs::WINDOWS_1251.encode("<p>Съешь же ещё этих мягких французских булок</p>");
+        let fixture = fixture.into_owned();
+        let server = TestServer::start(move |_| {
+            let body = fixture.clone();
+            async move { TestResponse::ok(body).header("Content-Type", "text/html; charset=windows-1251") }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let logged = client.tracked_send_text("k", client.inner.get(server.url("/"))).await.unwrap();
+        assert_eq!(logged.body, "<p>Съешь же ещё этих мягких французских булок</p>");
+
+        let resp = client.collector.lock().await["k"].response_data.clone().unwrap();
+        assert_eq!(resp.body, logged.body);
+        assert_eq!(resp.content_type.as_deref(), Some("text/html"));
+        assert_eq!(resp.charset.as_deref(), Some("windows-1251"));
+    }
+
+    #[test]
+    fn undeclared_or_unknown_charset_falls_back_to_utf8() {
+        assert_eq!(decode_text("ёж".as_bytes(), Some("text/plain")), "ёж");
+        assert_eq!(decode_text("ёж".as_bytes(), Some("text/plain; charset=no-such")), "ёж");
+        assert_eq!(decode_text(b"\xff", None), "\u{fffd}");
+    }
+}
@@ -1,4 +1,3 @@
-use std::error::Error;
 use reqwest_wrap_log::{example_step, TrackedClient};
 
 fn main() -> anyhow::Result<()> {
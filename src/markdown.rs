@@ -0,0 +1,179 @@
+use crate::TrackedClient;
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+// Дальше тело в блоке кода обрезается
+const MARKDOWN_BODY_LIMIT: usize = 4000;
+
+// Ячейка таблицы: без переводов строк и с экранированной "|"
+fn cell(s: &str) -> String {
+    s.replace(['\r', '\n'], " ").replace('|', "\\|")
+}
+
+// Ограждение длиннее любой серии ` внутри, чтобы блок не закрылся раньше
+fn fenced(lang: &str, text: &str) -> String {
+    let longest = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}\n", fence, lang, text.trim_end_matches('\n'), fence)
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> &'a str {
+    value.get(field).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn cut(text: String) -> String {
+    if text.len() <= MARKDOWN_BODY_LIMIT {
+        return text;
+    }
+    let mut end = MARKDOWN_BODY_LIMIT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}\n… ({} bytes more)", &text[..end], text.len() - end)
+}
+
+// Разобранное тело — отформатированным JSON, текст JSON тоже форматируется
+fn body_block(part: &Value) -> Option<String> {
+    let parsed = ["body_json", "body_parsed"]
+        .iter()
+        .filter_map(|field| part.get(*field))
+        .find(|v| !v.is_null());
+    if let Some(parsed) = parsed {
+        let pretty = serde_json::to_string_pretty(parsed).unwrap_or_default();
+        return Some(fenced("json", &cut(pretty)));
+    }
+    let body = part.get("body").and_then(Value::as_str).filter(|b| !b.is_empty())?;
+    match serde_json::from_str::<Value>(body) {
+        Ok(json @ (Value::Object(_) | Value::Array(_))) => {
+            Some(fenced("json", &cut(serde_json::to_string_pretty(&json).unwrap_or_default())))
+        }
+        _ => Some(fenced("", &cut(body.to_string()))),
+    }
+}
+
+fn headers_block(part: &Value) -> Option<String> {
+    let lines: Vec<String> = match part.get("headers_ordered").and_then(Value::as_array).filter(|a| !a.is_empty()) {
+        Some(pairs) => pairs
+            .iter()
+            .filter_map(|pair| Some(format!("{}: {}", pair.get(0)?.as_str()?, pair.get(1)?.as_str()?)))
+            .collect(),
+        None => part
+            .get("headers")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| format!("{}: {}", name, value.as_str().unwrap_or_default()))
+            .collect(),
+    };
+    (!lines.is_empty()).then(|| fenced("http", &lines.join("\n")))
+}
+
+fn entry_details(key: &str, entry: &Value) -> String {
+    let req = &entry["request_data"];
+    let resp = entry.get("response_data").filter(|r| !r.is_null());
+    let status = resp
+        .and_then(|r| r.get("status"))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "no response".to_string());
+
+    let mut out = format!(
+        "<details>\n<summary><code>{}</code> {} {} → {}</summary>\n\n",
+        html_escape(key),
+        html_escape(str_field(req, "method")),
+        html_escape(str_field(req, "endpoint")),
+        status
+    );
+    out.push_str("**Request**\n\n");
+    let request: Vec<String> = headers_block(req).into_iter().chain(body_block(req)).collect();
+    if request.is_empty() {
+        out.push_str("_No headers or body_\n");
+    }
+    out.extend(request);
+    match resp {
+        Some(resp) => {
+            out.push_str("\n**Response**\n\n");
+            out.extend(headers_block(resp));
+            out.extend(body_block(resp));
+        }
+        None => out.push_str("\n_No response_\n"),
+    }
+    if let Some(error) = entry.get("error").and_then(Value::as_str) {
+        out.push_str("\n**Error**\n\n");
+        out.push_str(&fenced("", error));
+    }
+    out.push_str("\n</details>\n\n");
+    out
+}
+
+impl TrackedClient {
+    // Отчёт для тикетов: сводка, таблица и <details> на запись. Данные проходят тот же
+    // путь, что get_pretty_truncated_data: с обрезкой и set_redacted_names.
+    pub async fn render_markdown(&self) -> Result<String> {
+        let raw = self.get_collected_data_sorted().await?;
+        let pretty = self.pretty_truncated(&raw)?;
+        let data: Value = serde_json::from_str(&pretty).context("Failed to parse pretty collected data")?;
+        let entries: Vec<(&String, &Value)> = data.as_object().into_iter().flatten().collect();
+
+        let failed = entries
+            .iter()
+            .filter(|(_, e)| matches!(str_field(e, "outcome"), "http_error" | "transport_error"))
+            .count();
+        let starts = entries.iter().map(|(_, e)| str_field(&e["request_data"], "request_time"));
+        let ends = entries.iter().map(|(_, e)| {
+            e.get("response_data")
+                .and_then(|r| r.get("response_time"))
+                .and_then(Value::as_str)
+                .unwrap_or_else(|| str_field(&e["request_data"], "request_time"))
+        });
+        // RFC3339 в одном поясе сравнивается как строка
+        let first = starts.filter(|s| !s.is_empty()).min();
+        let last = ends.filter(|s| !s.is_empty()).max();
+
+        let mut out = String::from("# Request log\n\n");
+        if let (Some(first), Some(last)) = (first, last) {
+            out.push_str(&format!("- **Time range:** {} — {}\n", first, last));
+        }
+        out.push_str(&format!("- **Requests:** {}\n", entries.len()));
+        out.push_str(&format!("- **Failed:** {}\n\n", failed));
+
+        out.push_str("| # | Key | Method | URL | Status | Time, ms | Bytes | Error |\n");
+        out.push_str("|---|---|---|---|---|---|---|---|\n");
+        for (n, (key, entry)) in entries.iter().enumerate() {
+            let req = &entry["request_data"];
+            let resp = entry.get("response_data").filter(|r| !r.is_null());
+            let number = |r: Option<&Value>, field: &str| {
+                r.and_then(|r| r.get(field)).filter(|v| !v.is_null()).map(|v| v.to_string()).unwrap_or_default()
+            };
+            let error = entry
+                .get("error")
+                .and_then(Value::as_str)
+                .and_then(|e| e.lines().next())
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "| {} | `{}` | {} | {} | {} | {} | {} | {} |\n",
+                n + 1,
+                cell(key).replace('`', "'"),
+                cell(str_field(req, "method")),
+                cell(str_field(req, "endpoint")),
+                number(resp, "status"),
+                number(resp, "duration_ms"),
+                number(resp, "response_body_bytes"),
+                cell(error)
+            ));
+        }
+        out.push('\n');
+
+        for (key, entry) in &entries {
+            out.push_str(&entry_details(key, entry));
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::auto_flush::take_finalized;
+use crate::schema::{next_seq, renumber_by_time};
+use crate::TrackedClient;
+use anyhow::{bail, Result};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+impl TrackedClient {
+    // Переносит записи other к себе: копией или, с take, забирая у other завершённые (запросы
+    // в пути остаются в other и завершатся там). key_prefix
+    // даёт ключи вида "prefix/key"; оставшиеся совпадения получают суффикс "#2", "#3"...
+    // Итоговый набор нумеруется заново по времени запроса. Возвращает число перенесённых.
+    pub async fn merge_from(&self, other: &TrackedClient, key_prefix: Option<&str>, take: bool) -> Result<usize> {
+        if Arc::ptr_eq(&self.collector, &other.collector) {
+            bail!("Cannot merge a collector into itself");
+        }
+        // other отпускается до блокировки своего коллектора: встречный merge_from не зависнет
+        let entries = {
+            let mut theirs = other.collector.lock().await;
+            if take {
+                take_finalized(&mut theirs)
+            } else {
+                theirs.clone()
+            }
+        };
+
+        let mut coll = self.collector.lock().await;
+        let count = entries.len();
+        for (key, entry) in entries {
+            let key = match key_prefix {
+                Some(prefix) => format!("{}/{}", prefix, key),
+                None => key,
+            };
+            let mut candidate = key.clone();
+            let mut n = 2;
+            while coll.contains_key(&candidate) {
+                candidate = format!("{}#{}", key, n);
+                n += 1;
+            }
+            coll.insert(candidate, entry);
+        }
+        renumber_by_time(&mut coll);
+        self.entry_seq.store(next_seq(&coll), Ordering::Relaxed);
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn take_leaves_entries_in_flight_with_other() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/slow" {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            TestResponse::ok(req.path.into_bytes())
+        })
+        .await;
+        let other = TrackedClient::new().unwrap();
+        other.tracked_send("done", other.inner.get(server.url("/done"))).await.unwrap();
+        let sender = other.clone();
+        let url = server.url("/slow");
+        let slow = tokio::spawn(async move { sender.tracked_send("slow", sender.inner.get(url)).await });
+        while !other.collector.lock().await.contains_key("slow") {
+            tokio::task::yield_now().await;
+        }
+
+        let client = TrackedClient::new().unwrap();
+        assert_eq!(client.merge_from(&other, Some("w1"), true).await.unwrap(), 1);
+        assert_eq!(client.collector.lock().await.keys().collect::<Vec<_>>(), ["w1/done"]);
+
+        slow.await.unwrap().unwrap();
+        let theirs = other.collector.lock().await;
+        assert_eq!(theirs.keys().collect::<Vec<_>>(), ["slow"]);
+        assert_eq!(theirs["slow"].response_data.as_ref().unwrap().body, "/slow");
+    }
+}
@@ -0,0 +1,45 @@
+use crate::har::{har_document, HarEntry, BASE64_BODY_COMMENT};
+use crate::TrackedClient;
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+
+// Собственный формат потоков mitmproxy меняется от версии к версии, поэтому выгрузка — HAR,
+// который читает `mitmproxy -r` (mitmproxy 10.1+), подогнанный под его загрузчик
+fn for_mitmproxy(entry: &mut HarEntry) {
+    // fromisoformat старых Python не понимает наносекунды
+    if let Ok(started) = DateTime::parse_from_rfc3339(&entry.started_date_time) {
+        entry.started_date_time = started.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Millis, false);
+    }
+    // ответ создаётся всегда, в том числе для записей без него
+    if entry.response.http_version.is_empty() {
+        entry.response.http_version = entry.request.http_version.clone();
+    }
+    // postData.text mitmproxy берёт как есть: бинарное тело точно не передать, а текстовое
+    // в base64 (например, из-за Content-Type) раскодируется
+    if let Some(post) = entry.request.post_data.as_mut().filter(|p| p.comment.as_deref() == Some(BASE64_BODY_COMMENT)) {
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(&post.text)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+        if let Some(text) = decoded {
+            post.text = text;
+            post.comment = None;
+        }
+    }
+}
+
+impl TrackedClient {
+    // Для просмотра в mitmproxy: сохранить в файл .har и открыть `mitmproxy -r file.har`.
+    // Заголовки по порядку отправки, бинарные тела ответов — в base64 (content.encoding),
+    // имена из set_redacted_names скрыты
+    pub async fn export_mitm_flows(&self) -> Result<Vec<u8>> {
+        let names = self.redacted_names();
+        let mut har = {
+            let coll = self.collector.lock().await;
+            har_document(&coll, &names)
+        };
+        har.log.entries.iter_mut().for_each(for_mitmproxy);
+        serde_json::to_vec_pretty(&har).context("Failed to serialize mitmproxy flows")
+    }
+}
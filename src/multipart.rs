@@ -0,0 +1,108 @@
+use crate::{sha256_hex, LoggedText, SendOptions, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::multipart::{Form, Part};
+use reqwest::IntoUrl;
+use serde::{Deserialize, Serialize};
+
+// Что сохраняется в RequestData про каждую часть формы: текст целиком, файлы — только метаданные
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MultipartPartInfo {
+    pub name: String,
+    pub filename: Option<String>,
+    pub mime: Option<String>,
+    pub size: u64,
+    pub sha256: Option<String>,
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+enum PartSpec {
+    Text { name: String, value: String },
+    File { name: String, filename: String, mime: String, data: Vec<u8> },
+}
+
+// Типизированное описание multipart-формы; из него строится и Form, и запись в коллекторе
+#[derive(Debug, Clone, Default)]
+pub struct MultipartSpec {
+    parts: Vec<PartSpec>,
+}
+
+impl MultipartSpec {
+    pub fn new() -> Self {
+        MultipartSpec::default()
+    }
+
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.parts.push(PartSpec::Text { name: name.to_string(), value: value.to_string() });
+        self
+    }
+
+    pub fn file(mut self, name: &str, filename: &str, mime: &str, data: Vec<u8>) -> Self {
+        self.parts.push(PartSpec::File {
+            name: name.to_string(),
+            filename: filename.to_string(),
+            mime: mime.to_string(),
+            data,
+        });
+        self
+    }
+
+    pub fn describe(&self) -> Vec<MultipartPartInfo> {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                PartSpec::Text { name, value } => MultipartPartInfo {
+                    name: name.clone(),
+                    filename: None,
+                    mime: None,
+                    size: value.len() as u64,
+                    sha256: None,
+                    text: Some(value.clone()),
+                },
+                PartSpec::File { name, filename, mime, data } => MultipartPartInfo {
+                    name: name.clone(),
+                    filename: Some(filename.clone()),
+                    mime: Some(mime.clone()),
+                    size: data.len() as u64,
+                    sha256: Some(sha256_hex(data)),
+                    text: None,
+                },
+            })
+            .collect()
+    }
+
+    pub fn into_form(self) -> Result<Form> {
+        let mut form = Form::new();
+        for part in self.parts {
+            form = match part {
+                PartSpec::Text { name, value } => form.text(name, value),
+                PartSpec::File { name, filename, mime, data } => {
+                    let part = Part::bytes(data)
+                        .file_name(filename)
+                        .mime_str(&mime)
+                        .with_context(|| format!("Invalid MIME type for part '{}': {}", name, mime))?;
+                    form.part(name, part)
+                }
+            };
+        }
+        Ok(form)
+    }
+}
+
+impl TrackedClient {
+    // POST multipart-формы; байты файлов в коллектор не попадают
+    pub async fn tracked_send_multipart<U: IntoUrl>(
+        &self,
+        key: &str,
+        url: U,
+        spec: MultipartSpec,
+    ) -> Result<LoggedText> {
+        let parts = spec.describe();
+        let builder = self.inner.post(url).multipart(spec.into_form()?);
+        let opts = SendOptions {
+            multipart: Some(parts),
+            ..SendOptions::default()
+        };
+        self.tracked_send_with(key, builder, &opts).await
+    }
+}
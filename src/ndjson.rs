@@ -0,0 +1,113 @@
+use crate::auto_flush::{finalized_entries, remove_taken};
+use crate::{MergeMode, RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+// Строка NDJSON: {"key":...,"entry":{...}}
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    key: &'a str,
+    entry: &'a RequestResponseData,
+}
+
+#[derive(Deserialize)]
+struct OwnedNdjsonLine {
+    key: String,
+    entry: RequestResponseData,
+}
+
+// С завершающим "\n"
+pub(crate) fn ndjson_line(key: &str, entry: &RequestResponseData) -> Result<String> {
+    let mut line = serde_json::to_string(&NdjsonLine { key, entry }).context("Failed to serialize NDJSON line")?;
+    line.push('\n');
+    Ok(line)
+}
+
+// Строки в порядке seq
+fn ndjson_lines(coll: &IndexMap<String, RequestResponseData>) -> Result<Vec<String>> {
+    let mut entries: Vec<(&String, &RequestResponseData)> = coll.iter().collect();
+    entries.sort_by_key(|(_, entry)| entry.seq);
+    entries.into_iter().map(|(key, entry)| ndjson_line(key, entry)).collect()
+}
+
+// Один ключ может встретиться в нескольких строках (FileSink дописывает запись заново после
+// каждого дополнения): побеждает последняя строка, место ключа — по первой. Пустые строки
+// пропускаются.
+pub fn from_ndjson(text: &str) -> Result<IndexMap<String, RequestResponseData>> {
+    let mut entries = IndexMap::new();
+    for (n, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line: OwnedNdjsonLine =
+            serde_json::from_str(line).with_context(|| format!("Invalid NDJSON line {}", n + 1))?;
+        entries.insert(line.key, line.entry);
+    }
+    Ok(entries)
+}
+
+impl TrackedClient {
+    // По строке на запись с flush после каждой; коллектор блокируется только на сериализацию,
+    // медленный w не задерживает запросы. Возвращает число строк.
+    pub async fn export_ndjson<W: AsyncWrite + Unpin>(&self, w: &mut W) -> Result<usize> {
+        let lines = {
+            let coll = self.collector.lock().await;
+            ndjson_lines(&coll)?
+        };
+        for line in &lines {
+            w.write_all(line.as_bytes()).await.context("Failed to write NDJSON line")?;
+            w.flush().await.context("Failed to flush NDJSON writer")?;
+        }
+        Ok(lines.len())
+    }
+
+    // Как take_collected_data, но в NDJSON: запросы в пути остаются в коллекторе
+    pub async fn take_ndjson(&self) -> Result<String> {
+        let mut coll = self.collector.lock().await;
+        let taken = finalized_entries(&coll);
+        let lines = ndjson_lines(&taken)?;
+        remove_taken(&mut coll, &taken);
+        Ok(lines.concat())
+    }
+
+    // Загрузка export_ndjson или файла FileSink по правилам from_ndjson и load_collected_data
+    pub async fn load_ndjson(&self, text: &str, mode: MergeMode) -> Result<usize> {
+        self.merge_loaded(from_ndjson(text)?.into_iter().collect(), mode).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn take_ndjson_keeps_entries_in_flight() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/slow" {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            TestResponse::ok(req.path.into_bytes())
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("done", client.inner.get(server.url("/done"))).await.unwrap();
+        let sender = client.clone();
+        let url = server.url("/slow");
+        let slow = tokio::spawn(async move { sender.tracked_send("slow", sender.inner.get(url)).await });
+        while !client.collector.lock().await.contains_key("slow") {
+            tokio::task::yield_now().await;
+        }
+
+        let taken = from_ndjson(&client.take_ndjson().await.unwrap()).unwrap();
+        assert_eq!(taken.keys().collect::<Vec<_>>(), ["done"]);
+
+        slow.await.unwrap().unwrap();
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.keys().collect::<Vec<_>>(), ["slow"]);
+        assert_eq!(coll["slow"].response_data.as_ref().unwrap().body, "/slow");
+    }
+}
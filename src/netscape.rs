@@ -0,0 +1,228 @@
+use crate::cookie_jar::{domain_url, set_cookie_header, CookieOpts, CookieRecord};
+use crate::TrackedClient;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::DateTime;
+use cookie_store::{CookieError, CookieStore, StoreAction};
+use reqwest::Client;
+use reqwest_cookie_store::CookieStoreMutex;
+use std::sync::Arc;
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File\n# https://curl.se/docs/http-cookies.html\n\n";
+// так curl и yt-dlp помечают HttpOnly-куки
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+fn flag(value: bool) -> &'static str {
+    if value {
+        "TRUE"
+    } else {
+        "FALSE"
+    }
+}
+
+// domain, поддомены, path, secure, истечение (0 — сессионная), name, value
+fn netscape_line(cookie: &CookieRecord) -> String {
+    let domain = if cookie.host_only {
+        cookie.domain.clone()
+    } else {
+        format!(".{}", cookie.domain)
+    };
+    format!(
+        "{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        if cookie.http_only { HTTP_ONLY_PREFIX } else { "" },
+        domain,
+        flag(!cookie.host_only),
+        cookie.path,
+        flag(cookie.secure),
+        cookie.expires.map(|at| at.timestamp()).unwrap_or(0),
+        cookie.name,
+        cookie.value,
+    )
+}
+
+impl TrackedClient {
+    // cookies.txt для curl -b, wget --load-cookies и yt-dlp --cookies; истёкшие пропускаются
+    pub fn dump_cookies_netscape(&self) -> Result<String> {
+        let store = self.lock_cookie_store()?;
+        let mut out = String::from(NETSCAPE_HEADER);
+        for cookie in store.iter_unexpired() {
+            out.push_str(&netscape_line(&CookieRecord::from_store(cookie)));
+        }
+        Ok(out)
+    }
+
+    // Дополняет хранилище куками из cookies.txt; строки, которые не удалось разобрать,
+    // возвращаются как "line N: причина", истёкшие пропускаются молча
+    pub fn import_netscape_cookies(&self, s: &str) -> Result<(usize, Vec<String>)> {
+        let mut store = self.lock_cookie_store()?;
+        Ok(import_into(&mut store, s))
+    }
+
+    // path_or_str — путь к cookies.txt или само содержимое (если в нём есть табуляция или
+    // перевод строки). Ошибочные строки пропускаются, но файл без единой годной куки и с
+    // ошибками отвергается
+    pub fn from_netscape_cookies(path_or_str: &str, proxy: Option<String>) -> Result<Self> {
+        let text = if path_or_str.contains('\t') || path_or_str.contains('\n') {
+            path_or_str.to_string()
+        } else {
+            std::fs::read_to_string(path_or_str)
+                .with_context(|| format!("Failed to read cookies file {}", path_or_str))?
+        };
+        let mut store = CookieStore::new(None);
+        let (imported, errors) = import_into(&mut store, &text);
+        if imported == 0 {
+            if let Some(first) = errors.first() {
+                bail!("Failed to load Netscape cookies: {}", first);
+            }
+        }
+        let jar = Arc::new(CookieStoreMutex::new(store));
+        let provider = jar.clone();
+        TrackedClient::assemble(move || Client::builder().cookie_provider(provider.clone()), jar, proxy.as_deref())
+            .context("Failed to build HTTP client with Netscape cookies")
+    }
+}
+
+fn import_into(store: &mut CookieStore, s: &str) -> (usize, Vec<String>) {
+    let mut imported = 0;
+    let mut errors = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        match import_line(store, line) {
+            Ok(true) => imported += 1,
+            Ok(false) => {}
+            Err(e) => errors.push(format!("line {}: {:#}", i + 1, e)),
+        }
+    }
+    (imported, errors)
+}
+
+// Ok(false) — комментарий, пустая строка или истёкшая кука
+fn import_line(store: &mut CookieStore, line: &str) -> Result<bool> {
+    let line = line.trim_end_matches('\r');
+    let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+        Some(rest) => (rest, true),
+        None => (line, false),
+    };
+    if line.trim().is_empty() || line.starts_with('#') {
+        return Ok(false);
+    }
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+        bail!("expected 7 tab-separated fields, got {}", fields.len());
+    };
+    let subdomains = parse_flag(subdomains).context("invalid include-subdomains flag")?;
+    let secure = parse_flag(secure).context("invalid secure flag")?;
+    let expires: i64 = expires.trim().parse().map_err(|_| anyhow!("invalid expiry '{}'", expires))?;
+    let opts = CookieOpts {
+        path: Some(if path.is_empty() { "/".to_string() } else { path.to_string() }),
+        expires: match expires {
+            0 => None,
+            at => Some(DateTime::from_timestamp(at, 0).ok_or_else(|| anyhow!("invalid expiry '{}'", at))?),
+        },
+        secure,
+        http_only,
+    };
+    // ведущая точка — кука для домена даже при FALSE во втором поле
+    let domain_cookie = subdomains || domain.starts_with('.');
+    let header = set_cookie_header(name, value, domain_cookie.then_some(domain), &opts)?;
+    match store.parse(&header, &domain_url(domain)?) {
+        Ok(StoreAction::ExpiredExisting) | Err(CookieError::Expired) => Ok(false),
+        Ok(_) => Ok(true),
+        Err(e) => Err(anyhow!("{}", e)),
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool> {
+    match value.trim() {
+        "TRUE" | "true" => Ok(true),
+        "FALSE" | "false" => Ok(false),
+        other => bail!("expected TRUE or FALSE, got '{}'", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use reqwest::Url;
+
+    fn sorted_lines(text: &str) -> Vec<&str> {
+        let mut lines: Vec<&str> = text.lines().filter(|l| !l.is_empty() && !l.starts_with("# ")).collect();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn dump_matches_cookies_txt_format_and_loads_back() {
+        let client = TrackedClient::new().unwrap();
+        let from = Url::parse("https://www.example.com/").unwrap();
+        {
+            let mut store = client.lock_cookie_store().unwrap();
+            let cookies = [
+                "sid=abc; Domain=example.com; Path=/; Secure; HttpOnly; Expires=Wed, 01 Jan 2120 00:00:00 GMT",
+                "pref=en; Path=/docs",
+                "keep=x; Expires=Thu, 01 Jan 2099 00:00:00 GMT",
+            ];
+            for cookie in cookies {
+                store.parse(cookie, &from).unwrap();
+            }
+        }
+
+        let dump = client.dump_cookies_netscape().unwrap();
+        assert!(dump.starts_with("# Netscape HTTP Cookie File\n"));
+        assert_eq!(
+            sorted_lines(&dump),
+            [
+                "#HttpOnly_.example.com\tTRUE\t/\tTRUE\t4733510400\tsid\tabc",
+                "www.example.com\tFALSE\t/\tFALSE\t4070908800\tkeep\tx",
+                "www.example.com\tFALSE\t/docs\tFALSE\t0\tpref\ten",
+            ]
+        );
+
+        let loaded = TrackedClient::from_netscape_cookies(&dump, None).unwrap();
+        let mut before = client.cookies().unwrap();
+        let mut after = loaded.cookies().unwrap();
+        before.sort_by(|a, b| a.name.cmp(&b.name));
+        after.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(after, before);
+        assert_eq!(sorted_lines(&loaded.dump_cookies_netscape().unwrap()), sorted_lines(&dump));
+
+        let (imported, errors) = client.import_netscape_cookies("bad line\n.example.com\tTRUE\t/\tFALSE\t0\tnew\t1\n").unwrap();
+        assert_eq!(imported, 1);
+        assert_eq!(errors, ["line 1: expected 7 tab-separated fields, got 1"]);
+    }
+
+    #[tokio::test]
+    async fn curl_sends_the_dumped_cookies() {
+        if std::process::Command::new("curl").arg("--version").output().is_err() {
+            return;
+        }
+        let server = TestServer::start(|req| async move {
+            match req.path.as_str() {
+                "/login" => TestResponse::ok("in")
+                    .header("Set-Cookie", "session=s1; Path=/; HttpOnly")
+                    .header("Set-Cookie", "theme=dark; Path=/"),
+                _ => TestResponse::ok("ok"),
+            }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("login", client.inner.get(server.url("/login"))).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("netscape-{}-cookies.txt", std::process::id()));
+        std::fs::write(&path, client.dump_cookies_netscape().unwrap()).unwrap();
+        let url = server.url("/me");
+        let file = path.clone();
+        let status = tokio::task::spawn_blocking(move || {
+            std::process::Command::new("curl").args(["-s", "-o", "/dev/null", "-b"]).arg(&file).arg(url).status()
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(status.success());
+
+        let sent = server.requests().last().unwrap().header("cookie").unwrap_or_default().to_string();
+        let mut pairs: Vec<&str> = sent.split("; ").collect();
+        pairs.sort();
+        assert_eq!(pairs, ["session=s1", "theme=dark"]);
+    }
+}
@@ -0,0 +1,24 @@
+use crate::TrackedClient;
+use anyhow::{anyhow, Result};
+
+impl TrackedClient {
+    // Заметка к уже записанному запросу: то, что стало известно после ответа
+    pub async fn annotate(&self, key: &str, note: &str) -> Result<()> {
+        let mut coll = self.collector.lock().await;
+        let entry = coll
+            .get_mut(key)
+            .ok_or_else(|| anyhow!("Cannot annotate: collector key '{}' not found", key))?;
+        entry.notes.push(note.to_string());
+        Ok(())
+    }
+
+    // Метка к уже записанному запросу; существующая с тем же именем перезаписывается
+    pub async fn set_tag(&self, key: &str, k: &str, v: &str) -> Result<()> {
+        let mut coll = self.collector.lock().await;
+        let entry = coll
+            .get_mut(key)
+            .ok_or_else(|| anyhow!("Cannot set tag: collector key '{}' not found", key))?;
+        entry.tags.insert(k.to_string(), v.to_string());
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{ErrorKind, RequestResponseData, TrackedClient};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Итог записи одним словом, чтобы отчётам не пересчитывать его из статуса и ошибки
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Success,
+    HttpError,
+    TransportError,
+    // ответа и ошибки ещё нет
+    #[default]
+    Pending,
+}
+
+impl RequestResponseData {
+    // expected_status пуст — успех любой 2xx
+    pub fn accepts_status(&self, status: u16) -> bool {
+        if self.expected_status.is_empty() {
+            (200..300).contains(&status)
+        } else {
+            self.expected_status.contains(&status)
+        }
+    }
+
+    // Пересчёт после записи ответа или ошибки. Логические ошибки (GraphQL, Endpoint)
+    // при принятом статусе дают HttpError: ответ есть, но он не тот.
+    pub(crate) fn refresh_outcome(&mut self) {
+        let transport = self.error_kind.is_some_and(|kind| kind != ErrorKind::Other);
+        self.outcome = match (&self.response_data, &self.error) {
+            (None, None) => Outcome::Pending,
+            (None, Some(_)) => Outcome::TransportError,
+            (Some(_), Some(_)) if transport => Outcome::TransportError,
+            (Some(resp), error) if self.accepts_status(resp.status) && error.is_none() => Outcome::Success,
+            (Some(_), _) => Outcome::HttpError,
+        };
+    }
+}
+
+impl TrackedClient {
+    pub async fn outcome_counts(&self) -> HashMap<Outcome, usize> {
+        let coll = self.collector.lock().await;
+        let mut counts = HashMap::new();
+        for entry in coll.values() {
+            *counts.entry(entry.outcome).or_insert(0) += 1;
+        }
+        counts
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{BySeq, TrackedClient};
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct CollectedPage<'a> {
+    total: usize,
+    offset: usize,
+    limit: usize,
+    entries: BySeq<'a>,
+}
+
+impl TrackedClient {
+    // {"total", "offset", "limit", "entries"}: entries — объект записей в порядке seq, как
+    // get_collected_data_sorted. Пока записи не добавляются, страницы не пересекаются;
+    // offset за концом даёт пустой entries
+    pub async fn get_collected_page(&self, offset: usize, limit: usize) -> Result<String> {
+        let coll = self.collector.lock().await;
+        let BySeq(all) = BySeq::new(&coll);
+        let page = CollectedPage {
+            total: all.len(),
+            offset,
+            limit,
+            entries: BySeq(all.into_iter().skip(offset).take(limit).collect()),
+        };
+        serde_json::to_string(&page).context("Failed to serialize collected page")
+    }
+
+    pub async fn collected_len(&self) -> usize {
+        self.collector.lock().await.len()
+    }
+}
@@ -0,0 +1,280 @@
+use crate::redact::{redact_cookie_value, redact_header_value};
+use crate::{BodyEncoding, RequestData, RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde::Serialize;
+
+const POSTMAN_SCHEMA: &str = "https://schema.getpostman.com/json/collection/v2.1.0/collection.json";
+
+// Postman считает их сам
+const POSTMAN_MANAGED_HEADERS: [&str; 3] = ["host", "content-length", "transfer-encoding"];
+
+// Postman Collection v2.1 (https://schema.postman.com/collection/json/v2.1.0/draft-07/docs/index.html)
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanCollection {
+    pub(crate) info: PostmanInfo,
+    pub(crate) item: Vec<PostmanNode>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanInfo {
+    pub(crate) name: String,
+    pub(crate) schema: String,
+}
+
+// Элемент item: запрос или папка
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub(crate) enum PostmanNode {
+    Item(Box<PostmanItem>),
+    Folder(PostmanFolder),
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanFolder {
+    pub(crate) name: String,
+    pub(crate) item: Vec<PostmanNode>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanItem {
+    pub(crate) name: String,
+    pub(crate) request: PostmanRequest,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanRequest {
+    pub(crate) method: String,
+    pub(crate) header: Vec<PostmanKeyValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) body: Option<PostmanBody>,
+    pub(crate) url: PostmanUrl,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanKeyValue {
+    pub(crate) key: String,
+    pub(crate) value: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub(crate) kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) src: Option<String>,
+}
+
+impl PostmanKeyValue {
+    fn text(key: &str, value: &str) -> Self {
+        PostmanKeyValue { key: key.to_string(), value: value.to_string(), kind: Some("text".to_string()), src: None }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanUrl {
+    pub(crate) raw: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) protocol: Option<String>,
+    pub(crate) host: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) port: Option<String>,
+    pub(crate) path: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) query: Vec<PostmanKeyValue>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanBody {
+    pub(crate) mode: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) raw: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) urlencoded: Option<Vec<PostmanKeyValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) formdata: Option<Vec<PostmanKeyValue>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) file: Option<PostmanFile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) options: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct PostmanFile {
+    pub(crate) src: String,
+}
+
+impl PostmanBody {
+    fn empty(mode: &str) -> Self {
+        PostmanBody {
+            mode: mode.to_string(),
+            raw: None,
+            urlencoded: None,
+            formdata: None,
+            file: None,
+            options: None,
+        }
+    }
+}
+
+fn postman_url(endpoint: &str) -> PostmanUrl {
+    let Ok(url) = Url::parse(endpoint) else {
+        return PostmanUrl { raw: endpoint.to_string(), protocol: None, host: Vec::new(), port: None, path: Vec::new(), query: Vec::new() };
+    };
+    PostmanUrl {
+        raw: endpoint.to_string(),
+        protocol: Some(url.scheme().to_string()),
+        host: url.host_str().map(|h| h.split('.').map(str::to_string).collect()).unwrap_or_default(),
+        port: url.port().map(|p| p.to_string()),
+        path: url
+            .path_segments()
+            .map(|segments| segments.filter(|s| !s.is_empty()).map(str::to_string).collect())
+            .unwrap_or_default(),
+        // как в URL, без раскодирования: Postman подставит строку обратно
+        query: url
+            .query()
+            .map(|q| {
+                q.split('&')
+                    .filter(|pair| !pair.is_empty())
+                    .map(|pair| {
+                        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                        PostmanKeyValue { key: key.to_string(), value: value.to_string(), kind: None, src: None }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    }
+}
+
+// Подсказка подсветки для raw по Content-Type
+fn raw_language(content_type: Option<&str>) -> &'static str {
+    let ct = content_type.unwrap_or_default().to_ascii_lowercase();
+    if ct.contains("json") {
+        "json"
+    } else if ct.contains("xml") {
+        "xml"
+    } else if ct.contains("html") {
+        "html"
+    } else if ct.contains("javascript") {
+        "javascript"
+    } else {
+        "text"
+    }
+}
+
+fn postman_body(req: &RequestData) -> (Option<PostmanBody>, Option<String>) {
+    if let Some(parts) = &req.multipart {
+        let formdata = parts
+            .iter()
+            .map(|part| match (&part.text, &part.filename) {
+                (Some(text), None) => PostmanKeyValue::text(&part.name, text),
+                // содержимое файлов не хранится: только имя
+                (_, filename) => PostmanKeyValue {
+                    key: part.name.clone(),
+                    value: String::new(),
+                    kind: Some("file".to_string()),
+                    src: filename.clone(),
+                },
+            })
+            .collect();
+        return (Some(PostmanBody { formdata: Some(formdata), ..PostmanBody::empty("formdata") }), None);
+    }
+    if let Some(upload) = &req.upload {
+        let file = PostmanFile { src: upload.path.clone() };
+        return (Some(PostmanBody { file: Some(file), ..PostmanBody::empty("file") }), None);
+    }
+    let Some(body) = &req.body else {
+        let note = req.body_streaming.then(|| "Request body was streamed and not stored".to_string());
+        return (None, note);
+    };
+    let content_type = req.content_type();
+    let mut note = req.body_truncated.then(|| "Request body is truncated".to_string());
+    if req.body_encoding == BodyEncoding::Base64 {
+        note = Some("Request body is base64-encoded".to_string());
+    } else if content_type.is_some_and(|ct| ct.to_ascii_lowercase().starts_with("application/x-www-form-urlencoded")) {
+        let pairs = url::form_urlencoded::parse(body.as_bytes())
+            .map(|(k, v)| PostmanKeyValue::text(&k, &v))
+            .collect();
+        return (Some(PostmanBody { urlencoded: Some(pairs), ..PostmanBody::empty("urlencoded") }), note);
+    }
+    let options = serde_json::json!({ "raw": { "language": raw_language(content_type) } });
+    (Some(PostmanBody { raw: Some(body.clone()), options: Some(options), ..PostmanBody::empty("raw") }), note)
+}
+
+fn postman_item(key: &str, entry: &RequestResponseData, names: &[String]) -> PostmanItem {
+    let req = &entry.request_data;
+    let headers: Vec<(&String, &String)> = if req.headers_ordered.is_empty() {
+        req.headers.iter().collect()
+    } else {
+        req.headers_ordered.iter().map(|(k, v)| (k, v)).collect()
+    };
+    let mut header = Vec::new();
+    let mut explicit_cookie = false;
+    for (name, value) in headers {
+        let lower = name.to_ascii_lowercase();
+        // boundary multipart Postman подставит свой
+        if POSTMAN_MANAGED_HEADERS.contains(&lower.as_str()) || (lower == "content-type" && req.multipart.is_some()) {
+            continue;
+        }
+        explicit_cookie |= lower == "cookie";
+        header.push(PostmanKeyValue::text(name, &redact_header_value(names, name, value)));
+    }
+    // хранилище кук у Postman своё, поэтому куки запроса — обычным заголовком
+    if !explicit_cookie && !req.cookies.is_empty() {
+        let mut cookies: Vec<String> = req
+            .cookies
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, redact_cookie_value(names, name, value)))
+            .collect();
+        cookies.sort();
+        header.push(PostmanKeyValue::text("Cookie", &cookies.join("; ")));
+    }
+    let (body, description) = postman_body(req);
+    PostmanItem {
+        name: key.to_string(),
+        request: PostmanRequest { method: req.method.clone(), header, body, url: postman_url(&req.endpoint) },
+        description,
+    }
+}
+
+// Папка по пути группы "a/b", создаётся при первом упоминании
+fn folder_for<'a>(items: &'a mut Vec<PostmanNode>, path: &[&str]) -> &'a mut Vec<PostmanNode> {
+    let Some((name, rest)) = path.split_first() else {
+        return items;
+    };
+    let pos = items
+        .iter()
+        .position(|node| matches!(node, PostmanNode::Folder(f) if f.name == *name))
+        .unwrap_or_else(|| {
+            items.push(PostmanNode::Folder(PostmanFolder { name: name.to_string(), item: Vec::new() }));
+            items.len() - 1
+        });
+    match &mut items[pos] {
+        PostmanNode::Folder(folder) => folder_for(&mut folder.item, rest),
+        PostmanNode::Item(_) => unreachable!("position matched a folder"),
+    }
+}
+
+impl TrackedClient {
+    // Коллекция Postman v2.1 в порядке отправки; записи с group — в папках по её пути,
+    // имена из set_redacted_names скрыты
+    pub async fn export_postman(&self, name: &str) -> Result<String> {
+        let names = self.redacted_names();
+        let coll = self.collector.lock().await;
+        let mut entries: Vec<(&String, &RequestResponseData)> = coll.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.seq);
+
+        let mut root = Vec::new();
+        for (key, entry) in entries {
+            let path: Vec<&str> = entry
+                .group
+                .as_deref()
+                .map(|g| g.split('/').filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default();
+            folder_for(&mut root, &path).push(PostmanNode::Item(Box::new(postman_item(key, entry, &names))));
+        }
+        let collection = PostmanCollection {
+            info: PostmanInfo { name: name.to_string(), schema: POSTMAN_SCHEMA.to_string() },
+            item: root,
+        };
+        serde_json::to_string_pretty(&collection).context("Failed to serialize Postman collection")
+    }
+}
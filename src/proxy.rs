@@ -0,0 +1,68 @@
+use crate::TrackedClient;
+use anyhow::{Context, Result};
+use reqwest::{redirect, Client, ClientBuilder, Proxy, Url};
+use std::sync::Arc;
+
+// Настройки клиента без прокси: из них собираются inner и no_redirect, в том числе заново в rotate_proxy
+pub(crate) type ClientFactory = Arc<dyn Fn() -> ClientBuilder + Send + Sync>;
+
+// Прокси для записи в лог: без логина и пароля
+pub(crate) fn strip_credentials(proxy: &str) -> String {
+    match Url::parse(proxy) {
+        Ok(mut url) => {
+            let _ = url.set_username("");
+            let _ = url.set_password(None);
+            // Url дописывает "/" к пустому пути, в исходной строке его могло не быть
+            match url.as_str().strip_suffix('/') {
+                Some(trimmed) if !proxy.ends_with('/') => trimmed.to_string(),
+                _ => url.to_string(),
+            }
+        }
+        // не URL: всё до последней '@' считаем учётными данными
+        Err(_) => proxy.rsplit('@').next().unwrap_or(proxy).to_string(),
+    }
+}
+
+// inner и клиент без редиректов с одним и тем же прокси
+pub(crate) fn build_clients(factory: &ClientFactory, proxy: Option<&str>) -> Result<(Client, Client)> {
+    let proxies = match proxy {
+        Some(proxy) => Some((
+            Proxy::http(proxy).context("Invalid HTTP proxy URL")?,
+            Proxy::https(proxy).context("Invalid HTTPS proxy URL")?,
+        )),
+        None => None,
+    };
+    let with_proxy = || {
+        let builder = factory();
+        match &proxies {
+            Some((http, https)) => builder.proxy(http.clone()).proxy(https.clone()),
+            None => builder,
+        }
+    };
+    let inner = with_proxy()
+        .build()
+        .context("Failed to build HTTP client")?;
+    let no_redirect = with_proxy()
+        .redirect(redirect::Policy::none())
+        .build()
+        .context("Failed to build non-redirecting HTTP client")?;
+    Ok((inner, no_redirect))
+}
+
+impl TrackedClient {
+    // Пересобирает клиент с другим прокси (None — без прокси); таймауты, user agent
+    // и хранилище кук остаются прежними, пул соединений — новый. Меняется только этот
+    // экземпляр: сделанные раньше клоны продолжают ходить через старый прокси.
+    pub fn rotate_proxy(&mut self, proxy: Option<&str>) -> Result<()> {
+        let (inner, no_redirect) = build_clients(&self.factory, proxy).context("Failed to rotate proxy")?;
+        self.inner = inner;
+        self.no_redirect = no_redirect;
+        self.proxy = proxy.map(strip_credentials);
+        Ok(())
+    }
+
+    // Текущий прокси без учётных данных
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+}
@@ -0,0 +1,132 @@
+use crate::TrackedClient;
+use serde_json::Value;
+
+// Чем заменяется скрытое значение в выводе
+pub(crate) const REDACTED: &str = "<redacted>";
+
+impl TrackedClient {
+    // Имена заголовков и кук (без учёта регистра), значения которых в выводе
+    // (get_pretty_truncated_data*, export_har, as_curl, render_markdown, export_postman) заменяются на "<redacted>".
+    // В коллекторе и get_collected_data* всё хранится как есть.
+    pub fn set_redacted_names(&self, names: &[&str]) {
+        self.settings_mut().redacted_names = names.iter().map(|n| n.to_ascii_lowercase()).collect();
+    }
+
+    pub(crate) fn redacted_names(&self) -> Vec<String> {
+        self.settings().redacted_names.clone()
+    }
+}
+
+pub(crate) fn is_redacted(names: &[String], name: &str) -> bool {
+    names.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
+
+pub(crate) fn redact_cookie_value(names: &[String], name: &str, value: &str) -> String {
+    if is_redacted(names, name) {
+        REDACTED.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// Маскирует значения в сериализованных записях: заголовки, куки запроса,
+// Set-Cookie и изменения хранилища кук. Полный дамп хранилища (cookies записи) не трогается.
+// Записи ищутся по request_data на любой глубине ({ключ: запись}, группы, конверт), чтобы
+// ключ коллектора вроде "cookies" не приняли за поле.
+pub(crate) fn redact_value(value: &mut Value, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    match value {
+        Value::Object(map) if map.contains_key("request_data") => redact_fields(value, names),
+        Value::Object(map) => map.values_mut().for_each(|v| redact_value(v, names)),
+        Value::Array(arr) => arr.iter_mut().for_each(|v| redact_value(v, names)),
+        _ => {}
+    }
+}
+
+fn redact_fields(value: &mut Value, names: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                match k.as_str() {
+                    "tags" | "body_json" | "body_parsed" => {}
+                    "headers" | "cookies" => {
+                        if let Value::Object(pairs) = v {
+                            for (name, val) in pairs.iter_mut() {
+                                redact_header(name, val, names);
+                            }
+                        }
+                    }
+                    "headers_ordered" => {
+                        for pair in v.as_array_mut().into_iter().flatten() {
+                            if let Some([Value::String(name), val]) = pair.as_array_mut().map(Vec::as_mut_slice) {
+                                redact_header(name, val, names);
+                            }
+                        }
+                    }
+                    "set_cookies" => {
+                        for raw in v.as_array_mut().into_iter().flatten() {
+                            redact_header("set-cookie", raw, names);
+                        }
+                    }
+                    "set_cookies_parsed" => mask_named(v, names, &["value"]),
+                    "cookie_changes" => {
+                        for list in v.as_object_mut().into_iter().flat_map(|m| m.values_mut()) {
+                            mask_named(list, names, &["old_value", "new_value"]);
+                        }
+                    }
+                    _ => redact_fields(v, names),
+                }
+            }
+        }
+        Value::Array(arr) => arr.iter_mut().for_each(|v| redact_fields(v, names)),
+        _ => {}
+    }
+}
+
+// Значение заголовка для вывода: целиком "<redacted>" или, для Cookie/Set-Cookie,
+// со скрытыми значениями отдельных кук
+pub(crate) fn redact_header_value(names: &[String], name: &str, value: &str) -> String {
+    if is_redacted(names, name) {
+        return REDACTED.to_string();
+    }
+    // в Cookie все пары — куки, в Set-Cookie только первая, дальше атрибуты
+    let pairs = match name.to_ascii_lowercase().as_str() {
+        "cookie" => usize::MAX,
+        "set-cookie" => 1,
+        _ => return value.to_string(),
+    };
+    let masked: Vec<String> = value
+        .split(';')
+        .enumerate()
+        .map(|(i, part)| match part.split_once('=') {
+            Some((cookie, _)) if i < pairs && is_redacted(names, cookie.trim()) => format!("{}={}", cookie, REDACTED),
+            _ => part.to_string(),
+        })
+        .collect();
+    masked.join(";")
+}
+
+fn redact_header(name: &str, val: &mut Value, names: &[String]) {
+    match val {
+        Value::String(s) => *s = redact_header_value(names, name, s),
+        _ if is_redacted(names, name) => *val = Value::from(REDACTED),
+        _ => {}
+    }
+}
+
+// Массив объектов с полем name: у совпавших заменяются непустые fields
+fn mask_named(list: &mut Value, names: &[String], fields: &[&str]) {
+    for item in list.as_array_mut().into_iter().flatten() {
+        let hidden = item.get("name").and_then(Value::as_str).is_some_and(|name| is_redacted(names, name));
+        if !hidden {
+            continue;
+        }
+        for field in fields {
+            if let Some(val) = item.get_mut(*field).filter(|v| !v.is_null()) {
+                *val = Value::from(REDACTED);
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use crate::summary::summarize;
+use crate::{EndpointSummary, Outcome, RequestResponseData, TrackedClient};
+use anyhow::Result;
+use reqwest::Url;
+use std::collections::BTreeMap;
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    // ANSI-цвета статусов
+    pub color: bool,
+    // URL длиннее обрезаются посередине
+    pub url_width: usize,
+    pub error_width: usize,
+    // сводка endpoint_summary под итогами
+    pub endpoints: bool,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        ReportOptions { color: false, url_width: 60, error_width: 60, endpoints: true }
+    }
+}
+
+impl ReportOptions {
+    pub fn new() -> Self {
+        ReportOptions::default()
+    }
+
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn url_width(mut self, width: usize) -> Self {
+        self.url_width = width;
+        self
+    }
+
+    pub fn error_width(mut self, width: usize) -> Self {
+        self.error_width = width;
+        self
+    }
+
+    pub fn endpoints(mut self, endpoints: bool) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+}
+
+// "начало…конец" длиной width символов
+fn truncate_middle(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    if len <= width || width < 3 {
+        return s.to_string();
+    }
+    let head = (width - 1) / 2;
+    let tail = width - 1 - head;
+    let start: String = s.chars().take(head).collect();
+    let end: String = s.chars().skip(len - tail).collect();
+    format!("{}…{}", start, end)
+}
+
+fn truncate_end(s: &str, width: usize) -> String {
+    if s.chars().count() <= width {
+        return s.to_string();
+    }
+    let mut out: String = s.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+// Без схемы: хост, путь и запрос
+fn short_url(endpoint: &str) -> String {
+    match Url::parse(endpoint) {
+        Ok(url) => {
+            let mut short = url.host_str().unwrap_or_default().to_string();
+            if let Some(port) = url.port() {
+                short.push_str(&format!(":{}", port));
+            }
+            short.push_str(url.path());
+            if let Some(query) = url.query() {
+                short.push('?');
+                short.push_str(query);
+            }
+            short
+        }
+        Err(_) => endpoint.to_string(),
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+fn status_color(status: Option<u16>) -> &'static str {
+    match status {
+        Some(200..=299) => "\x1b[32m",
+        Some(300..=399) => "\x1b[36m",
+        Some(400..=499) => "\x1b[33m",
+        _ => "\x1b[31m",
+    }
+}
+
+// Ширина по символам, а не байтам: в ключах и URL бывает кириллица
+fn pad(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    format!("{}{}", s, " ".repeat(width.saturating_sub(len)))
+}
+
+fn pad_left(s: &str, width: usize) -> String {
+    let len = s.chars().count();
+    format!("{}{}", " ".repeat(width.saturating_sub(len)), s)
+}
+
+struct Row {
+    key: String,
+    method: String,
+    url: String,
+    status: String,
+    status_code: Option<u16>,
+    duration: String,
+    size: String,
+    error: String,
+}
+
+fn row(key: &str, entry: &RequestResponseData, opts: &ReportOptions) -> Row {
+    let resp = entry.response_data.as_ref();
+    let status = match (resp, entry.outcome) {
+        (Some(r), _) => r.status.to_string(),
+        (None, Outcome::Pending) => "...".to_string(),
+        (None, _) => "ERR".to_string(),
+    };
+    let error = entry
+        .error
+        .as_deref()
+        .and_then(|e| e.lines().next())
+        .map(|e| truncate_end(e, opts.error_width))
+        .unwrap_or_default();
+    Row {
+        key: key.to_string(),
+        method: entry.request_data.method.clone(),
+        url: truncate_middle(&short_url(&entry.request_data.endpoint), opts.url_width),
+        status,
+        status_code: resp.map(|r| r.status),
+        duration: resp
+            .map(|r| r.duration_ms)
+            .or(entry.duration_ms)
+            .map(|d| format!("{} ms", d))
+            .unwrap_or_else(|| "-".to_string()),
+        size: resp.map(|r| human_size(r.response_body_bytes)).unwrap_or_else(|| "-".to_string()),
+        error,
+    }
+}
+
+// Сводка по хостам и шаблонам путей, как endpoint_summary
+fn endpoints_table(summary: &EndpointSummary, opts: &ReportOptions) -> String {
+    let header = ["HOST", "PATH", "COUNT", "METHODS", "STATUSES", "AVG", "MAX"];
+    let ms = |d: Option<u64>| d.map(|d| format!("{} ms", d)).unwrap_or_else(|| "-".to_string());
+    let rows: Vec<[String; 7]> = summary
+        .endpoints
+        .iter()
+        .map(|e| {
+            let statuses: Vec<String> = e.statuses.iter().map(|(status, n)| format!("{}: {}", status, n)).collect();
+            [
+                e.host.clone(),
+                truncate_middle(&e.path, opts.url_width),
+                e.count.to_string(),
+                e.methods.join(","),
+                statuses.join(", "),
+                ms(e.avg_duration_ms),
+                ms(e.max_duration_ms),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| rows.iter().map(|r| r[i].chars().count()).chain([header[i].chars().count()]).max().unwrap_or_default())
+        .collect();
+    let line = |cells: [&str; 7]| {
+        let mut line = String::new();
+        for (i, cell) in cells.iter().enumerate() {
+            if i > 0 {
+                line.push_str("  ");
+            }
+            // числа — вправо
+            let cell = if matches!(i, 2 | 5 | 6) { pad_left(cell, widths[i]) } else { pad(cell, widths[i]) };
+            line.push_str(&cell);
+        }
+        format!("{}\n", line.trim_end())
+    };
+    let mut out = String::from("Endpoints:\n");
+    out.push_str(&line(header));
+    for r in &rows {
+        out.push_str(&line([&r[0], &r[1], &r[2], &r[3], &r[4], &r[5], &r[6]]));
+    }
+    out
+}
+
+impl TrackedClient {
+    pub async fn render_report(&self) -> Result<String> {
+        self.render_report_with(&ReportOptions::default()).await
+    }
+
+    // Таблица записей в порядке seq и итоги
+    pub async fn render_report_with(&self, opts: &ReportOptions) -> Result<String> {
+        let coll = self.collector.lock().await;
+        let mut entries: Vec<(&String, &RequestResponseData)> = coll.iter().collect();
+        entries.sort_by_key(|(_, entry)| entry.seq);
+        let rows: Vec<Row> = entries.iter().map(|(key, entry)| row(key, entry, opts)).collect();
+
+        let header = ["KEY", "METHOD", "URL", "STATUS", "TIME", "SIZE", "ERROR"];
+        let width = |f: fn(&Row) -> &str, title: &str| {
+            rows.iter().map(|r| f(r).chars().count()).chain([title.chars().count()]).max().unwrap_or_default()
+        };
+        let w_key = width(|r| &r.key, header[0]);
+        let w_method = width(|r| &r.method, header[1]);
+        let w_url = width(|r| &r.url, header[2]);
+        let w_status = width(|r| &r.status, header[3]);
+        let w_duration = width(|r| &r.duration, header[4]);
+        let w_size = width(|r| &r.size, header[5]);
+
+        let mut out = String::new();
+        let line = format!(
+            "{}  {}  {}  {}  {}  {}  {}",
+            pad(header[0], w_key),
+            pad(header[1], w_method),
+            pad(header[2], w_url),
+            pad(header[3], w_status),
+            pad_left(header[4], w_duration),
+            pad_left(header[5], w_size),
+            header[6]
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+        for r in &rows {
+            // цвет вокруг уже выровненного поля, иначе escape-коды собьют ширину
+            let status = if opts.color {
+                format!("{}{}{}", status_color(r.status_code), pad(&r.status, w_status), RESET)
+            } else {
+                pad(&r.status, w_status)
+            };
+            let line = format!(
+                "{}  {}  {}  {}  {}  {}  {}",
+                pad(&r.key, w_key),
+                pad(&r.method, w_method),
+                pad(&r.url, w_url),
+                status,
+                pad_left(&r.duration, w_duration),
+                pad_left(&r.size, w_size),
+                r.error
+            );
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+
+        let errors = entries
+            .iter()
+            .filter(|(_, e)| matches!(e.outcome, Outcome::HttpError | Outcome::TransportError))
+            .count();
+        let total_ms: u64 = entries
+            .iter()
+            .filter_map(|(_, e)| e.response_data.as_ref().map(|r| r.duration_ms).or(e.duration_ms))
+            .sum();
+        let mut by_status: BTreeMap<String, usize> = BTreeMap::new();
+        for r in &rows {
+            *by_status.entry(r.status.clone()).or_default() += 1;
+        }
+        out.push('\n');
+        out.push_str(&format!("Requests: {}\n", entries.len()));
+        out.push_str(&format!("Errors: {}\n", errors));
+        out.push_str(&format!("Total time: {} ms\n", total_ms));
+        let statuses: Vec<String> = by_status
+            .iter()
+            .map(|(status, n)| match (opts.color, status.parse::<u16>().ok()) {
+                (true, code) => format!("{}{}{}: {}", status_color(code), status, RESET, n),
+                (false, _) => format!("{}: {}", status, n),
+            })
+            .collect();
+        out.push_str(&format!("By status: {}\n", statuses.join(", ")));
+        if opts.endpoints && !entries.is_empty() {
+            out.push('\n');
+            out.push_str(&endpoints_table(&summarize(entries.iter().map(|(_, e)| *e), true), opts));
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,210 @@
+use crate::auto_flush;
+use crate::headers::{lookup, parse_retry_after};
+use crate::{now_msk, BodyReadError, LoggedText, TrackedClient};
+use anyhow::Result;
+use reqwest::RequestBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+// Сколько раз и в каких случаях повторять запрос
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    // задержка перед второй попыткой, дальше удваивается
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // доля случайного разброса задержки, 0.0..=1.0
+    pub jitter: f64,
+    pub retry_connect: bool,
+    pub retry_timeout: bool,
+    pub retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.2,
+            retry_connect: true,
+            retry_timeout: true,
+            retry_statuses: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy { max_attempts, ..RetryPolicy::default() }
+    }
+
+    pub fn backoff(mut self, base_delay: Duration, max_delay: Duration, jitter: f64) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn retry_statuses(mut self, statuses: &[u16]) -> Self {
+        self.retry_statuses = statuses.to_vec();
+        self
+    }
+
+    pub fn retry_connect(mut self, enabled: bool) -> Self {
+        self.retry_connect = enabled;
+        self
+    }
+
+    pub fn retry_timeout(mut self, enabled: bool) -> Self {
+        self.retry_timeout = enabled;
+        self
+    }
+
+    fn retries_error(&self, err: &anyhow::Error) -> bool {
+        err.chain().any(|cause| {
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                (self.retry_connect && e.is_connect()) || (self.retry_timeout && e.is_timeout())
+            } else if let Some(BodyReadError::TimedOut(_)) = cause.downcast_ref::<BodyReadError>() {
+                self.retry_timeout
+            } else {
+                false
+            }
+        })
+    }
+
+    // Задержка перед попыткой attempt (со второй), с разбросом ±jitter
+    fn delay_before(&self, attempt: u32) -> Duration {
+        let exp = self.base_delay.saturating_mul(1u32 << (attempt - 2).min(16));
+        let delay = exp.min(self.max_delay);
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        let factor = 1.0 + self.jitter * (random * 2.0 - 1.0);
+        delay.mul_f64(factor.max(0.0)).min(self.max_delay)
+    }
+}
+
+// Пометка на записи попытки `key#N`; итоги — только у последней
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RetryInfo {
+    pub parent_key: String,
+    pub attempt: u32,
+    pub delay_before_ms: u64,
+    pub final_attempt: bool,
+    pub total_attempts: Option<u32>,
+    pub total_elapsed_ms: Option<u64>,
+}
+
+// Одна попытка отправки: когда началась, сколько длилась и чем кончилась
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AttemptInfo {
+    pub attempt: u32,
+    pub started_at: String,
+    pub duration_ms: u64,
+    // статус ответа, если он был получен
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetriedText {
+    pub response: LoggedText,
+    // ключ записи последней попытки
+    pub key: String,
+    pub attempts: u32,
+    pub elapsed_ms: u64,
+}
+
+impl TrackedClient {
+    // Каждая попытка пишется в коллектор под `key#1`, `key#2`, ...; в attempts записи
+    // попытки N — история попыток 1..=N.
+    // Билдер создаётся заново на попытку: RequestBuilder не всегда клонируется.
+    pub async fn tracked_send_with_retries(
+        &self,
+        key: &str,
+        make_builder: impl Fn() -> RequestBuilder,
+        policy: RetryPolicy,
+    ) -> Result<RetriedText> {
+        self.sealing(self.send_with_retries(key, make_builder, policy)).await
+    }
+
+    async fn send_with_retries(
+        &self,
+        key: &str,
+        make_builder: impl Fn() -> RequestBuilder,
+        policy: RetryPolicy,
+    ) -> Result<RetriedText> {
+        let started = Instant::now();
+        let max_attempts = policy.max_attempts.max(1);
+        let mut attempt = 1;
+        let mut delay = Duration::ZERO;
+        let mut history = Vec::new();
+        loop {
+            let attempt_key = format!("{}#{}", key, attempt);
+            let started_at = now_msk()?.to_rfc3339();
+            let attempt_started = Instant::now();
+            let result = self.tracked_send_text(&attempt_key, make_builder()).await;
+            history.push(AttemptInfo {
+                attempt,
+                started_at,
+                duration_ms: attempt_started.elapsed().as_millis() as u64,
+                status: result.as_ref().ok().map(|l| l.status),
+                error: result.as_ref().err().map(|e| format!("{:#}", e)),
+            });
+            // при KeyCollisionPolicy::Suffix запись могла лечь под другим ключом
+            let attempt_key = result.as_ref().map(|l| l.key.clone()).unwrap_or(attempt_key);
+            // неудачная попытка не вернула LoggedText, но заведена внутри этого же sealing
+            let seq = match &result {
+                Ok(logged) => Some(logged.seq),
+                Err(_) => auto_flush::scoped_seq(&attempt_key),
+            };
+
+            let retry_after = match &result {
+                Ok(logged) if policy.retry_statuses.contains(&logged.status) => {
+                    Some(lookup(&logged.headers, "retry-after").and_then(parse_retry_after))
+                }
+                Ok(_) => None,
+                Err(e) if policy.retries_error(e) => Some(None),
+                Err(_) => None,
+            };
+            let final_attempt = attempt >= max_attempts || retry_after.is_none();
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+
+            let info = RetryInfo {
+                parent_key: key.to_string(),
+                attempt,
+                delay_before_ms: delay.as_millis() as u64,
+                final_attempt,
+                total_attempts: final_attempt.then_some(attempt),
+                total_elapsed_ms: final_attempt.then_some(elapsed_ms),
+            };
+            let attempts = history.clone();
+            if let Some(seq) = seq {
+                self.update_entry(&attempt_key, seq, |entry| {
+                    entry.retry = Some(info);
+                    entry.attempts = attempts;
+                })
+                .await;
+            }
+
+            if final_attempt {
+                return match result {
+                    Ok(response) => Ok(RetriedText { response, key: attempt_key, attempts: attempt, elapsed_ms }),
+                    Err(e) => Err(e.context(format!(
+                        "Request '{}' failed after {} attempts in {} ms",
+                        key, attempt, elapsed_ms
+                    ))),
+                };
+            }
+
+            attempt += 1;
+            // Retry-After сервера важнее своей задержки, но не дольше max_delay
+            delay = policy.delay_before(attempt);
+            if let Some(Some(server_delay)) = retry_after {
+                delay = delay.max(server_delay.min(policy.max_delay));
+            }
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::auto_flush::{finalized_entries, remove_taken};
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+fn collected_json(coll: &IndexMap<String, RequestResponseData>, pretty: bool) -> Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(coll).context("Failed to serialize collected data")
+    } else {
+        serde_json::to_string(coll).context("Failed to serialize collected data")
+    }
+}
+
+// Временный файл рядом с целевым: rename атомарен только в пределах одной ФС
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), nanos))
+}
+
+// Пишет во временный файл, fsync и rename поверх path; при ошибке временный файл удаляется
+pub(crate) async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    let tmp = temp_path(path);
+    let written = async {
+        let mut file = tokio::fs::File::create(&tmp)
+            .await
+            .with_context(|| format!("Failed to create {}", tmp.display()))?;
+        file.write_all(contents)
+            .await
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        file.sync_all()
+            .await
+            .with_context(|| format!("Failed to sync {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, path)
+            .await
+            .with_context(|| format!("Failed to rename {} to {}", tmp.display(), path.display()))
+    }
+    .await;
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    written
+}
+
+impl TrackedClient {
+    // get_collected_data в файл целиком или никак: старый файл остаётся до успешного rename
+    pub async fn save_collected_to_file(&self, path: &Path, pretty: bool) -> Result<()> {
+        let json = {
+            let coll = self.collector.lock().await;
+            collected_json(&coll, pretty)?
+        };
+        write_atomic(path, json.as_bytes()).await
+    }
+
+    // Как save_collected_to_file, но завершённые записи убираются из коллектора после
+    // rename. Файл пишется без блокировки коллектора; запросы в пути остаются в нём
+    pub async fn take_collected_to_file(&self, path: &Path, pretty: bool) -> Result<()> {
+        let taken = finalized_entries(&*self.collector.lock().await);
+        let json = collected_json(&taken, pretty)?;
+        write_atomic(path, json.as_bytes()).await?;
+        remove_taken(&mut *self.collector.lock().await, &taken);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn take_to_file_keeps_entries_in_flight() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/slow" {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            TestResponse::ok(req.path.into_bytes())
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("done", client.inner.get(server.url("/done"))).await.unwrap();
+        let sender = client.clone();
+        let url = server.url("/slow");
+        let slow = tokio::spawn(async move { sender.tracked_send("slow", sender.inner.get(url)).await });
+        while !client.collector.lock().await.contains_key("slow") {
+            tokio::task::yield_now().await;
+        }
+
+        let path = std::env::temp_dir().join(format!("take-{}.json", std::process::id()));
+        client.take_collected_to_file(&path, false).await.unwrap();
+        let saved = crate::schema::from_collected_json(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.keys().collect::<Vec<_>>(), ["done"]);
+
+        slow.await.unwrap().unwrap();
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.keys().collect::<Vec<_>>(), ["slow"]);
+        assert_eq!(coll["slow"].response_data.as_ref().unwrap().body, "/slow");
+    }
+}
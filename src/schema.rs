@@ -0,0 +1,221 @@
+use crate::auto_flush::{finalized_entries, remove_taken};
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+
+// Версия формата записей в конверте; голый объект {ключ: запись} считается версией 1
+pub const COLLECTED_SCHEMA_VERSION: u64 = 2;
+
+// {"schema_version":2,"entries":{...}}
+#[derive(Serialize)]
+struct CollectedEnvelope<'a> {
+    schema_version: u64,
+    entries: &'a IndexMap<String, RequestResponseData>,
+}
+
+// Как load_collected_data обходится с записями, которые уже есть в коллекторе
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMode {
+    // коллектор очищается, остаются только загруженные записи
+    Replace,
+    // при совпадении ключа остаётся запись коллектора
+    KeepExisting,
+    // при совпадении ключа побеждает загруженная
+    Overwrite,
+}
+
+// seq должен быть уникален и отличен от 0 (старый формат)
+fn seq_conflicts(coll: &IndexMap<String, RequestResponseData>) -> bool {
+    let mut seen = HashSet::new();
+    coll.values().any(|entry| entry.seq == 0 || !seen.insert(entry.seq))
+}
+
+// Порядок и seq заново по времени запроса
+pub(crate) fn renumber_by_time(coll: &mut IndexMap<String, RequestResponseData>) {
+    coll.sort_by(|_, a, _, b| (a.request_data.request_time_ms, a.seq).cmp(&(b.request_data.request_time_ms, b.seq)));
+    for (n, entry) in coll.values_mut().enumerate() {
+        entry.seq = n as u64 + 1;
+    }
+}
+
+pub(crate) fn next_seq(coll: &IndexMap<String, RequestResponseData>) -> u64 {
+    coll.values().map(|entry| entry.seq).max().unwrap_or_default() + 1
+}
+
+fn envelope_json(entries: &IndexMap<String, RequestResponseData>) -> Result<String> {
+    let envelope = CollectedEnvelope { schema_version: COLLECTED_SCHEMA_VERSION, entries };
+    serde_json::to_string(&envelope).context("Failed to serialize collected envelope")
+}
+
+impl TrackedClient {
+    // get_collected_data в конверте с версией схемы
+    pub async fn get_collected_envelope(&self) -> Result<String> {
+        let coll = self.collector.lock().await;
+        envelope_json(&coll)
+    }
+
+    // Забирает завершённые записи: выгрузка и удаление под одной блокировкой, запросы в пути
+    // остаются в коллекторе и завершатся там
+    pub async fn take_collected_data(&self) -> Result<String> {
+        let mut coll = self.collector.lock().await;
+        let taken = finalized_entries(&coll);
+        let json = serde_json::to_string(&taken).context("Failed to serialize collected data")?;
+        remove_taken(&mut coll, &taken);
+        Ok(json)
+    }
+
+    pub async fn take_collected_envelope(&self) -> Result<String> {
+        let mut coll = self.collector.lock().await;
+        let taken = finalized_entries(&coll);
+        let json = envelope_json(&taken)?;
+        remove_taken(&mut coll, &taken);
+        Ok(json)
+    }
+
+    // Возвращает записи прошлого запуска (формат get_collected_data или конверт) и число
+    // загруженных. Если номера seq пересекаются с уже имеющимися, все записи нумеруются
+    // заново по времени запроса; новые запросы получают номера после загруженных.
+    pub async fn load_collected_data(&self, json: &str, mode: MergeMode) -> Result<usize> {
+        self.merge_loaded(from_collected_json(json)?.into_iter().collect(), mode).await
+    }
+
+    pub(crate) async fn merge_loaded(&self, mut loaded: Vec<(String, RequestResponseData)>, mode: MergeMode) -> Result<usize> {
+        loaded.sort_by(|(ka, a), (kb, b)| (a.seq, a.request_data.request_time_ms, ka).cmp(&(b.seq, b.request_data.request_time_ms, kb)));
+
+        let mut coll = self.collector.lock().await;
+        if mode == MergeMode::Replace {
+            coll.clear();
+        }
+        let mut count = 0;
+        for (key, entry) in loaded {
+            if mode == MergeMode::KeepExisting && coll.contains_key(&key) {
+                continue;
+            }
+            coll.insert(key, entry);
+            count += 1;
+        }
+
+        if seq_conflicts(&coll) {
+            renumber_by_time(&mut coll);
+        } else {
+            coll.sort_by(|_, a, _, b| a.seq.cmp(&b.seq));
+        }
+        // под блокировкой коллектора, как и выдача номеров в begin_entry
+        self.entry_seq.store(next_seq(&coll), Ordering::Relaxed);
+        Ok(count)
+    }
+}
+
+// Разбирает и голый объект записей, и конверт любой версии не новее текущей
+pub fn from_collected_json(json: &str) -> Result<HashMap<String, RequestResponseData>> {
+    let value: Value = serde_json::from_str(json).context("Failed to parse collected JSON")?;
+    let Value::Object(mut obj) = value else {
+        bail!("Collected JSON must be an object");
+    };
+    // у записи с ключом "schema_version" значение — объект, а не число
+    let entries = match (obj.get("schema_version").map(Value::as_u64), obj.get("entries")) {
+        (Some(Some(version)), Some(Value::Object(_))) => {
+            if version > COLLECTED_SCHEMA_VERSION {
+                bail!(
+                    "Unsupported collected schema version {} (supported up to {})",
+                    version,
+                    COLLECTED_SCHEMA_VERSION
+                );
+            }
+            obj.remove("entries").unwrap_or_default()
+        }
+        _ => Value::Object(obj),
+    };
+    serde_json::from_value(entries).context("Invalid collected entries format")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+
+    async fn session(server: &TestServer) -> TrackedClient {
+        let client = TrackedClient::new().unwrap();
+        for key in ["login", "list", "logout"] {
+            client.tracked_send(key, client.inner.get(server.url(&format!("/{}", key)))).await.unwrap();
+        }
+        client
+    }
+
+    #[tokio::test]
+    async fn dump_clear_load_dump_round_trips() {
+        let server = TestServer::start(|req| async move { TestResponse::ok(req.path.into_bytes()) }).await;
+        let client = session(&server).await;
+        let dumped = client.get_collected_data().await.unwrap();
+
+        client.clear_collector().await;
+        assert_eq!(client.load_collected_data(&dumped, MergeMode::Replace).await.unwrap(), 3);
+        let reloaded = client.get_collected_data().await.unwrap();
+        let as_value = |json: &str| serde_json::from_str::<Value>(json).unwrap();
+        assert_eq!(as_value(&reloaded), as_value(&dumped));
+
+        // конверт загружается так же, новые запросы нумеруются после загруженных
+        let fresh = TrackedClient::new().unwrap();
+        let envelope = client.get_collected_envelope().await.unwrap();
+        assert_eq!(fresh.load_collected_data(&envelope, MergeMode::Replace).await.unwrap(), 3);
+        assert_eq!(as_value(&fresh.get_collected_data().await.unwrap()), as_value(&dumped));
+        fresh.tracked_send("again", fresh.inner.get(server.url("/again"))).await.unwrap();
+        assert_eq!(fresh.collector.lock().await["again"].seq, 4);
+    }
+
+    #[tokio::test]
+    async fn merge_modes_decide_who_wins() {
+        let server = TestServer::start(|req| async move { TestResponse::ok(req.path.into_bytes()) }).await;
+        let saved = session(&server).await.get_collected_data().await.unwrap();
+
+        let client = TrackedClient::new().unwrap();
+        client.tracked_send("list", client.inner.get(server.url("/list?page=2"))).await.unwrap();
+
+        assert_eq!(client.load_collected_data(&saved, MergeMode::KeepExisting).await.unwrap(), 2);
+        assert!(client.collector.lock().await["list"].request_data.endpoint.ends_with("/list?page=2"));
+        assert_eq!(client.collector.lock().await.len(), 3);
+
+        assert_eq!(client.load_collected_data(&saved, MergeMode::Overwrite).await.unwrap(), 3);
+        assert!(client.collector.lock().await["list"].request_data.endpoint.ends_with("/list"));
+
+        client.tracked_send("extra", client.inner.get(server.url("/extra"))).await.unwrap();
+        assert_eq!(client.load_collected_data(&saved, MergeMode::Replace).await.unwrap(), 3);
+        assert!(!client.collector.lock().await.contains_key("extra"));
+
+        assert!(client.load_collected_data("[1]", MergeMode::Replace).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn take_keeps_entries_in_flight() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/slow" {
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+            TestResponse::ok(req.path.into_bytes())
+        })
+        .await;
+        let client = session(&server).await;
+        let sender = client.clone();
+        let url = server.url("/slow");
+        let slow = tokio::spawn(async move { sender.tracked_send("slow", sender.inner.get(url)).await });
+        while !client.collector.lock().await.contains_key("slow") {
+            tokio::task::yield_now().await;
+        }
+
+        let taken = from_collected_json(&client.take_collected_data().await.unwrap()).unwrap();
+        let mut keys: Vec<_> = taken.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["list", "login", "logout"]);
+        let envelope = from_collected_json(&client.take_collected_envelope().await.unwrap()).unwrap();
+        assert!(envelope.is_empty());
+
+        slow.await.unwrap().unwrap();
+        let coll = client.collector.lock().await;
+        assert_eq!(coll.len(), 1);
+        assert_eq!(coll["slow"].response_data.as_ref().unwrap().body, "/slow");
+    }
+}
@@ -0,0 +1,437 @@
+use crate::ndjson::{from_ndjson, ndjson_line};
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{anyhow, Context, Result};
+use indexmap::IndexMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::future::Future;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex as AsyncMutex;
+
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+// Когда приёмник получает запись
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SinkPhases {
+    // только завершённую (ответ или ошибка)
+    #[default]
+    Finished,
+    // ещё и сразу после отправки, без ответа (outcome = Pending)
+    Both,
+}
+
+// Хранилище записей помимо коллектора клиента. record по одному ключу может прийти
+// повторно: обёртки (retry, graphql, endpoint) дополняют уже завершённую запись,
+// поэтому приёмник должен заменять запись по ключу, а не дописывать вторую.
+pub trait CollectorSink: Send + Sync {
+    fn record<'a>(&'a self, key: &'a str, entry: &'a RequestResponseData) -> SinkFuture<'a>;
+
+    fn flush(&self) -> SinkFuture<'_> {
+        Box::pin(async {})
+    }
+
+    fn phases(&self) -> SinkPhases {
+        SinkPhases::Finished
+    }
+}
+
+impl fmt::Debug for dyn CollectorSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CollectorSink").field("phases", &self.phases()).finish_non_exhaustive()
+    }
+}
+
+// Записи в памяти, как коллектор клиента; TrackedClient::memory_sink — вид на сам коллектор
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    entries: Arc<AsyncMutex<IndexMap<String, RequestResponseData>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        MemorySink::default()
+    }
+
+    pub async fn snapshot(&self) -> IndexMap<String, RequestResponseData> {
+        self.entries.lock().await.clone()
+    }
+
+    pub async fn take(&self) -> IndexMap<String, RequestResponseData> {
+        std::mem::take(&mut *self.entries.lock().await)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.entries.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.entries.lock().await.is_empty()
+    }
+}
+
+impl CollectorSink for MemorySink {
+    fn record<'a>(&'a self, key: &'a str, entry: &'a RequestResponseData) -> SinkFuture<'a> {
+        Box::pin(async move {
+            self.entries.lock().await.insert(key.to_string(), entry.clone());
+        })
+    }
+}
+
+// Не реже чем раз в столько буфер сбрасывается на диск
+const SINK_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+enum SinkMessage {
+    Entry(String, Box<RequestResponseData>),
+    Flush(Sender<()>),
+}
+
+// Завершённые записи строками NDJSON ({"key":...,"entry":{...}}) в файл с ротацией по размеру:
+// name -> name.1 -> ... -> name.N. Пишет отдельный поток через буфер, так что медленный диск
+// не влияет на duration_ms; при Drop последнего клона буфер дописывается.
+// Файл только дописывается: дополненная обёрткой запись появляется ещё одной строкой с тем же
+// ключом, и действует последняя из них. Так читают read_entries, from_ndjson и load_ndjson.
+#[derive(Clone)]
+pub struct FileSink {
+    handle: Arc<SinkHandle>,
+}
+
+struct SinkHandle {
+    path: PathBuf,
+    tx: Option<Sender<SinkMessage>>,
+    worker: Option<JoinHandle<()>>,
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl Drop for SinkHandle {
+    fn drop(&mut self) {
+        // закрытый канал — сигнал потоку дописать всё и выйти
+        self.tx.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl fmt::Debug for FileSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileSink").field("path", &self.handle.path).finish()
+    }
+}
+
+impl FileSink {
+    // max_bytes — порог ротации текущего файла, keep — сколько старых файлов хранить
+    // (0 — текущий файл просто начинается заново)
+    pub fn open<P: AsRef<Path>>(path: P, max_bytes: u64, keep: usize) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+        let (writer, written) = open_append(&path)?;
+        let last_error = Arc::new(Mutex::new(None));
+        let worker = SinkWorker {
+            path: path.clone(),
+            max_bytes,
+            keep,
+            writer,
+            written,
+            last_error: last_error.clone(),
+        };
+        let (tx, rx) = mpsc::channel();
+        let worker = std::thread::Builder::new()
+            .name("reqwest-wrap-log-sink".to_string())
+            .spawn(move || worker.run(rx))
+            .context("Failed to start file sink thread")?;
+        Ok(FileSink {
+            handle: Arc::new(SinkHandle { path, tx: Some(tx), worker: Some(worker), last_error }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.handle.path
+    }
+
+    // Ждёт, пока всё отправленное до вызова окажется в файле (блокирует поток)
+    pub fn flush(&self) -> Result<()> {
+        let (ack_tx, ack_rx) = mpsc::channel();
+        self.handle
+            .tx
+            .as_ref()
+            .and_then(|tx| tx.send(SinkMessage::Flush(ack_tx)).ok())
+            .ok_or_else(|| anyhow!("File sink {} is closed", self.handle.path.display()))?;
+        ack_rx
+            .recv()
+            .map_err(|_| anyhow!("File sink {} is closed", self.handle.path.display()))?;
+        match self.last_error() {
+            Some(error) => Err(anyhow!("File sink {} failed: {}", self.handle.path.display(), error)),
+            None => Ok(()),
+        }
+    }
+
+    // Последняя ошибка записи или ротации; строки, на которых она случилась, потеряны
+    pub fn last_error(&self) -> Option<String> {
+        self.handle.last_error.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    // Записи из name.N, ..., name.1 и name (от старых к новым), последняя строка по ключу
+    // побеждает; записи, ещё не сброшенные из буфера, не видны — перед чтением нужен flush
+    pub fn read_entries<P: AsRef<Path>>(path: P) -> Result<IndexMap<String, RequestResponseData>> {
+        let path = path.as_ref();
+        let mut files: Vec<PathBuf> = (1..).map(|n| rotated_path(path, n)).take_while(|p| p.exists()).collect();
+        files.reverse();
+        files.push(path.to_path_buf());
+        let mut text = String::new();
+        for file in &files {
+            match std::fs::read_to_string(file) {
+                Ok(part) => text.push_str(&part),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("Failed to read sink file {}", file.display())),
+            }
+        }
+        from_ndjson(&text).with_context(|| format!("Failed to parse sink file {}", path.display()))
+    }
+
+    fn send(&self, key: &str, entry: &RequestResponseData) {
+        if let Some(tx) = &self.handle.tx {
+            let _ = tx.send(SinkMessage::Entry(key.to_string(), Box::new(entry.clone())));
+        }
+    }
+}
+
+// record только ставит строку в очередь потока записи
+impl CollectorSink for FileSink {
+    fn record<'a>(&'a self, key: &'a str, entry: &'a RequestResponseData) -> SinkFuture<'a> {
+        self.send(key, entry);
+        Box::pin(async {})
+    }
+
+    fn flush(&self) -> SinkFuture<'_> {
+        let sink = self.clone();
+        Box::pin(async move {
+            let _ = tokio::task::spawn_blocking(move || sink.flush()).await;
+        })
+    }
+}
+
+fn open_append(path: &Path) -> Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open sink file {}", path.display()))?;
+    let written = file
+        .metadata()
+        .with_context(|| format!("Failed to stat sink file {}", path.display()))?
+        .len();
+    Ok((BufWriter::new(file), written))
+}
+
+// name.N
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to rename {} to {}", from.display(), to.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+struct SinkWorker {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    writer: BufWriter<File>,
+    // размер текущего файла вместе с буфером
+    written: u64,
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl SinkWorker {
+    fn run(mut self, rx: Receiver<SinkMessage>) {
+        let mut last_flush = Instant::now();
+        loop {
+            let wait = SINK_FLUSH_INTERVAL.saturating_sub(last_flush.elapsed());
+            match rx.recv_timeout(wait) {
+                Ok(SinkMessage::Entry(key, entry)) => {
+                    let result = self.write_entry(&key, &entry);
+                    self.record(result);
+                }
+                Ok(SinkMessage::Flush(ack)) => {
+                    self.flush();
+                    last_flush = Instant::now();
+                    let _ = ack.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush();
+                    return;
+                }
+            }
+            if last_flush.elapsed() >= SINK_FLUSH_INTERVAL {
+                self.flush();
+                last_flush = Instant::now();
+            }
+        }
+    }
+
+    fn record(&self, result: Result<()>) {
+        if let Err(e) = result {
+            *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(format!("{:#}", e));
+        }
+    }
+
+    fn flush(&mut self) {
+        let result = self
+            .writer
+            .flush()
+            .with_context(|| format!("Failed to flush sink file {}", self.path.display()));
+        self.record(result);
+    }
+
+    fn write_entry(&mut self, key: &str, entry: &RequestResponseData) -> Result<()> {
+        let line = ndjson_line(key, entry)?;
+        // строка длиннее порога всё равно пишется целиком, в пустой файл
+        if self.written > 0 && self.written + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        self.writer
+            .write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write sink file {}", self.path.display()))?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .with_context(|| format!("Failed to flush sink file {}", self.path.display()))?;
+        if self.keep == 0 {
+            File::create(&self.path).with_context(|| format!("Failed to truncate sink file {}", self.path.display()))?;
+        } else {
+            let oldest = rotated_path(&self.path, self.keep);
+            if let Err(e) = std::fs::remove_file(&oldest) {
+                if e.kind() != ErrorKind::NotFound {
+                    return Err(e).with_context(|| format!("Failed to remove {}", oldest.display()));
+                }
+            }
+            for n in (1..self.keep).rev() {
+                rename_if_exists(&rotated_path(&self.path, n), &rotated_path(&self.path, n + 1))?;
+            }
+            rename_if_exists(&self.path, &rotated_path(&self.path, 1))?;
+        }
+        (self.writer, self.written) = open_append(&self.path)?;
+        Ok(())
+    }
+}
+
+impl TrackedClient {
+    // Каждая завершённая запись (ответ или ошибка) уходит во все приёмники по порядку
+    // добавления; записи в коллекторе остаются. Дополненная после завершения запись
+    // (retry, graphql, endpoint и др.) приходит повторно с тем же ключом и заменяет прежнюю.
+    pub fn with_sink(self, sink: impl CollectorSink + 'static) -> Self {
+        self.add_sink(Arc::new(sink));
+        self
+    }
+
+    pub fn add_sink(&self, sink: Arc<dyn CollectorSink>) {
+        self.settings_mut().sinks.push(sink);
+    }
+
+    pub fn sinks(&self) -> Vec<Arc<dyn CollectorSink>> {
+        self.settings().sinks.clone()
+    }
+
+    pub fn clear_sinks(&self) -> Vec<Arc<dyn CollectorSink>> {
+        std::mem::take(&mut self.settings_mut().sinks)
+    }
+
+    // Коллектор клиента как MemorySink; get_collected_data читает его же
+    pub fn memory_sink(&self) -> MemorySink {
+        MemorySink { entries: self.collector.clone() }
+    }
+
+    pub async fn flush_sinks(&self) {
+        let sinks = self.sinks();
+        for sink in &sinks {
+            sink.flush().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use crate::MergeMode;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sink-{}-{}", std::process::id(), name))
+    }
+
+    fn cleanup(path: &Path) {
+        let _ = std::fs::remove_file(path);
+        for n in 1..4 {
+            let _ = std::fs::remove_file(rotated_path(path, n));
+        }
+    }
+
+    #[tokio::test]
+    async fn updated_entry_replaces_earlier_line_on_load() {
+        let server = TestServer::start(|_| async { TestResponse::ok("ok") }).await;
+        let path = temp_path("update.ndjson");
+        cleanup(&path);
+        let sink = FileSink::open(&path, 1 << 20, 2).unwrap();
+        let client = TrackedClient::new().unwrap().with_sink(sink.clone());
+
+        let a = client.tracked_send_text("a", client.inner.get(server.url("/a"))).await.unwrap();
+        client.tracked_send("b", client.inner.get(server.url("/b"))).await.unwrap();
+        client.recorder().update("a", a.seq, |entry| entry.notes.push("updated".to_string())).await;
+        sink.flush().unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 3);
+
+        let entries = FileSink::read_entries(&path).unwrap();
+        assert_eq!(entries.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(entries["a"].notes, ["updated"]);
+
+        let loaded = TrackedClient::new().unwrap();
+        assert_eq!(loaded.load_ndjson(&text, MergeMode::Replace).await.unwrap(), 2);
+        assert_eq!(loaded.collector.lock().await["a"].notes, ["updated"]);
+        cleanup(&path);
+    }
+
+    #[tokio::test]
+    async fn read_entries_spans_rotated_files() {
+        let server = TestServer::start(|_| async { TestResponse::ok("ok") }).await;
+        let path = temp_path("rotate.ndjson");
+        cleanup(&path);
+        // каждая строка заметно длиннее порога, так что каждая открывает новый файл
+        let sink = FileSink::open(&path, 10, 3).unwrap();
+        let client = TrackedClient::new().unwrap().with_sink(sink.clone());
+
+        let a = client.tracked_send_text("a", client.inner.get(server.url("/a"))).await.unwrap();
+        client.tracked_send("b", client.inner.get(server.url("/b"))).await.unwrap();
+        client.recorder().update("a", a.seq, |entry| entry.notes.push("late".to_string())).await;
+        sink.flush().unwrap();
+        assert!(rotated_path(&path, 2).exists());
+
+        let entries = FileSink::read_entries(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries["a"].notes, ["late"]);
+        cleanup(&path);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+
+// Порядок записей в *_sorted_by; при равенстве — по seq
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    // по времени отправки
+    #[default]
+    RequestTime,
+    // самые медленные первыми, записи без ответа в конце
+    Duration,
+    // сначала без ответа (ошибка транспорта или ещё в полёте), затем по убыванию статуса
+    Status,
+    Key,
+}
+
+// Элемент массива: ключ и поля записи на одном уровне
+#[derive(Serialize)]
+struct KeyedEntry<'a> {
+    key: &'a str,
+    #[serde(flatten)]
+    entry: &'a RequestResponseData,
+}
+
+fn sorted(entries: &mut [(&String, &RequestResponseData)], sort: SortBy) {
+    match sort {
+        SortBy::RequestTime => entries.sort_by_key(|(_, e)| (e.request_data.request_time_ms, e.seq)),
+        SortBy::Duration => entries.sort_by_key(|(_, e)| {
+            (Reverse(e.response_data.as_ref().map(|r| r.duration_ms)), e.seq)
+        }),
+        SortBy::Status => entries.sort_by_key(|(_, e)| {
+            (e.response_data.as_ref().map(|r| Reverse(r.status)), e.seq)
+        }),
+        SortBy::Key => entries.sort_by_key(|(key, _)| *key),
+    }
+}
+
+impl TrackedClient {
+    // Массив {"key": ..., ...поля записи}: порядок в JSON-объекте читатели не обязаны сохранять
+    pub async fn get_collected_data_sorted_by(&self, sort: SortBy) -> Result<String> {
+        let coll = self.collector.lock().await;
+        let mut entries: Vec<(&String, &RequestResponseData)> = coll.iter().collect();
+        sorted(&mut entries, sort);
+        let keyed: Vec<KeyedEntry> = entries.into_iter().map(|(key, entry)| KeyedEntry { key, entry }).collect();
+        serde_json::to_string(&keyed).context("Failed to serialize collected data")
+    }
+
+    // То же с обрезкой и скрытием, как get_pretty_truncated_data
+    pub async fn get_pretty_truncated_data_sorted_by(&self, sort: SortBy) -> Result<String> {
+        let raw = self.get_collected_data_sorted_by(sort).await?;
+        self.pretty_truncated(&raw)
+    }
+}
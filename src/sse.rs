@@ -0,0 +1,182 @@
+use crate::{now_msk, truncate, Recorder, TrackedClient, TrackedResponse};
+use anyhow::Result;
+use futures_util::Stream;
+use reqwest::header::{HeaderValue, ACCEPT};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+
+// Сколько событий хранить в записи (счётчик идёт дальше) и сколько символов data
+const SSE_LOGGED_EVENTS_MAX: usize = 1000;
+const SSE_DATA_EXCERPT: usize = 200;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    pub retry: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SseEventRecord {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+    pub received_at: String,
+}
+
+// Сводка по событиям потока в записи коллектора
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseLog {
+    pub count: u64,
+    pub first_at: Option<String>,
+    pub last_at: Option<String>,
+    pub events: Vec<SseEventRecord>,
+}
+
+// Разбор text/event-stream поверх TrackedResponse; каждое событие сразу попадает в запись
+pub struct SseStream {
+    resp: TrackedResponse,
+    recorder: Recorder,
+    buffer: Vec<u8>,
+    // поля события, которое ещё не закончилось пустой строкой
+    id: Option<String>,
+    event: Option<String>,
+    data: Vec<String>,
+    retry: Option<u64>,
+    done: bool,
+}
+
+impl SseStream {
+    pub fn key(&self) -> &str {
+        self.resp.key()
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.resp.status()
+    }
+
+    // Следующее событие; None — поток закрыт сервером. Недописанное событие в конце отбрасывается.
+    pub async fn next_event(&mut self) -> Result<Option<SseEvent>> {
+        loop {
+            while let Some(line) = self.take_line() {
+                if let Some(event) = self.feed_line(&line) {
+                    self.record(&event).await;
+                    return Ok(Some(event));
+                }
+            }
+            if self.done {
+                return Ok(None);
+            }
+            match self.resp.chunk().await? {
+                Some(chunk) => self.buffer.extend_from_slice(&chunk),
+                None => self.done = true,
+            }
+        }
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = Result<SseEvent>> {
+        futures_util::stream::unfold(self, |mut sse| async move {
+            match sse.next_event().await {
+                Ok(Some(event)) => Some((Ok(event), sse)),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), sse)),
+            }
+        })
+    }
+
+    // Строка до \n, \r\n или \r; одиночный \r в конце буфера ждёт следующего чанка
+    fn take_line(&mut self) -> Option<String> {
+        let pos = self.buffer.iter().position(|&b| b == b'\n' || b == b'\r')?;
+        let mut skip = 1;
+        if self.buffer[pos] == b'\r' {
+            match self.buffer.get(pos + 1) {
+                Some(b'\n') => skip = 2,
+                None if !self.done => return None,
+                _ => {}
+            }
+        }
+        let line = String::from_utf8_lossy(&self.buffer[..pos]).into_owned();
+        self.buffer.drain(..pos + skip);
+        Some(line)
+    }
+
+    fn feed_line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                self.event = None;
+                return None;
+            }
+            return Some(SseEvent {
+                id: self.id.clone(),
+                event: self.event.take(),
+                data: std::mem::take(&mut self.data).join("\n"),
+                retry: self.retry,
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(ms);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    async fn record(&self, event: &SseEvent) {
+        let received_at = now_msk().map(|t| t.to_rfc3339()).unwrap_or_default();
+        let record = SseEventRecord {
+            id: event.id.clone(),
+            event: event.event.clone(),
+            data: truncate(&event.data, SSE_DATA_EXCERPT),
+            received_at: received_at.clone(),
+        };
+        self.recorder
+            .update(self.resp.key(), self.resp.seq(), |entry| {
+                let log = entry.sse.get_or_insert_with(SseLog::default);
+                log.count += 1;
+                if log.first_at.is_none() {
+                    log.first_at = Some(received_at.clone());
+                }
+                log.last_at = Some(received_at);
+                if log.events.len() < SSE_LOGGED_EVENTS_MAX {
+                    log.events.push(record);
+                }
+            })
+            .await;
+    }
+}
+
+impl TrackedClient {
+    // Поток событий без ожидания конца ответа. Ответ записывается при закрытии потока,
+    // ошибке или Drop — с тем, что успело прийти.
+    pub async fn tracked_send_sse(&self, key: &str, builder: RequestBuilder) -> Result<SseStream> {
+        let mut req = self.build_tracked(key, builder).await?;
+        req.headers_mut()
+            .entry(ACCEPT)
+            .or_insert(HeaderValue::from_static("text/event-stream"));
+        let resp = self.start_streamed(key, req).await?;
+        Ok(SseStream {
+            resp,
+            recorder: self.recorder(),
+            buffer: Vec::new(),
+            id: None,
+            event: None,
+            data: Vec::new(),
+            retry: None,
+            done: false,
+        })
+    }
+}
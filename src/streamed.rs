@@ -0,0 +1,287 @@
+use crate::errors::EntryError;
+use crate::limits::{decode_text, looks_binary};
+use crate::{FailedPhase, ResponseHead, timestamp, BodyEncoding, BodyTruncation, Recorder, ResponseData, SendOptions, TrackedClient};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use bytes::Bytes;
+use futures_util::Stream;
+use reqwest::header::HeaderMap;
+use reqwest::{Request, RequestBuilder, Response, StatusCode};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Instant;
+
+// Ответ, тело которого дочитывает вызывающий. По мере чтения чанки копируются
+// (до лимита) в буфер, а по окончании потока или при Drop всё пишется в коллектор.
+pub struct TrackedResponse {
+    resp: Response,
+    key: String,
+    seq: u64,
+    // id строки начала в WAL, для строки завершения
+    wal_id: Option<String>,
+    recorder: Recorder,
+    status: u16,
+    headers: HashMap<String, String>,
+    headers_ordered: Vec<(String, String)>,
+    set_cookies: Vec<String>,
+    http_version: String,
+    remote_addr: Option<String>,
+    local_addr: Option<String>,
+    connection_reused: Option<bool>,
+    content_length_header: Option<u64>,
+    // size_hint тела уменьшается по мере чтения, поэтому запоминаем исходную длину
+    content_length: Option<u64>,
+    start: Instant,
+    headers_at: Instant,
+    cap: usize,
+    captured: Vec<u8>,
+    // хэш всех прочитанных байт, не только сохранённых; None — хэширование выключено
+    hasher: Option<Sha256>,
+    total: u64,
+    capped: bool,
+    // поток закончился или оборвался
+    done: bool,
+    // запись в коллекторе уже сделана
+    recorded: bool,
+}
+
+impl TrackedResponse {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    // seq записи (LoggedText::seq)
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.resp.status()
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        self.resp.headers()
+    }
+
+    pub fn url(&self) -> &reqwest::Url {
+        self.resp.url()
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    // Следующий чанк тела; None — поток закончился
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        if self.done {
+            return Ok(None);
+        }
+        match self.resp.chunk().await {
+            Ok(Some(chunk)) => {
+                self.tee(&chunk);
+                Ok(Some(chunk))
+            }
+            Ok(None) => {
+                self.done = true;
+                self.finish(None, false);
+                Ok(None)
+            }
+            Err(e) => {
+                self.done = true;
+                self.finish(Some(EntryError::from_error(&e).in_phase(FailedPhase::BodyRead)), false);
+                Err(anyhow!("Failed to read response body: {}", e))
+            }
+        }
+    }
+
+    pub fn bytes_stream(self) -> impl Stream<Item = Result<Bytes>> {
+        futures_util::stream::unfold(self, |mut resp| async move {
+            match resp.chunk().await {
+                Ok(Some(chunk)) => Some((Ok(chunk), resp)),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), resp)),
+            }
+        })
+    }
+
+    pub async fn bytes(mut self) -> Result<Bytes> {
+        let mut buf = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            buf.extend_from_slice(&chunk);
+        }
+        Ok(Bytes::from(buf))
+    }
+
+    // Как reqwest::Response::text: кодировка из charset в Content-Type, по умолчанию UTF-8
+    pub async fn text(self) -> Result<String> {
+        let content_type = self
+            .resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = self.bytes().await?;
+        Ok(decode_text(&body, content_type.as_deref()))
+    }
+
+    pub async fn json<T: serde::de::DeserializeOwned>(self) -> Result<T> {
+        let body = self.bytes().await?;
+        serde_json::from_slice(&body).context("Failed to parse response body as JSON")
+    }
+
+    fn tee(&mut self, chunk: &[u8]) {
+        if self.recorded {
+            return;
+        }
+        self.total += chunk.len() as u64;
+        if let Some(hasher) = self.hasher.as_mut() {
+            hasher.update(chunk);
+        }
+        let room = self.cap.saturating_sub(self.captured.len());
+        if chunk.len() > room {
+            self.capped = true;
+        }
+        self.captured.extend_from_slice(&chunk[..chunk.len().min(room)]);
+    }
+
+    fn finish(&mut self, error: Option<EntryError>, dropped: bool) {
+        if self.recorded {
+            return;
+        }
+        self.recorded = true;
+
+        // Вызывающий мог прочитать ровно Content-Length байт и не дождаться конца потока
+        let read_all = self.content_length.is_some_and(|len| self.total >= len);
+        let truncated_by = if dropped && !read_all {
+            Some(BodyTruncation::Caller)
+        } else if self.capped {
+            Some(BodyTruncation::CaptureLimit)
+        } else {
+            None
+        };
+        let content_type = self.headers.get("content-type").map(String::as_str);
+        let (body, body_encoding) = if looks_binary(&self.captured, content_type) {
+            (base64::engine::general_purpose::STANDARD.encode(&self.captured), BodyEncoding::Base64)
+        } else {
+            (decode_text(&self.captured, content_type), BodyEncoding::Utf8)
+        };
+        let (response_time, response_time_ms) = timestamp().unwrap_or_default();
+        let resp_data = ResponseData {
+            status: self.status,
+            headers: std::mem::take(&mut self.headers),
+            body,
+            body_encoding,
+            set_cookies: std::mem::take(&mut self.set_cookies),
+            headers_ordered: std::mem::take(&mut self.headers_ordered),
+            http_version: std::mem::take(&mut self.http_version),
+            remote_addr: self.remote_addr.take(),
+            local_addr: self.local_addr.take(),
+            connection_reused: self.connection_reused,
+            content_length_header: self.content_length_header,
+            final_url: Some(self.resp.url().to_string()),
+            response_time,
+            response_time_ms,
+            duration_ms: self.start.elapsed().as_millis() as u64,
+            response_body_bytes: self.total,
+            response_body_sha256: self
+                .hasher
+                .take()
+                .filter(|_| self.total > 0)
+                .map(|hasher| format!("{:x}", hasher.finalize())),
+            ttfb_ms: Some(self.headers_at.duration_since(self.start).as_millis() as u64),
+            body_ms: Some(self.headers_at.elapsed().as_millis() as u64),
+            body_truncated: truncated_by.is_some(),
+            truncated_by,
+            ..Default::default()
+        };
+        self.recorder.finish_detached(self.key.clone(), self.seq, self.wal_id.take(), resp_data, error);
+    }
+}
+
+impl Drop for TrackedResponse {
+    fn drop(&mut self) {
+        self.finish(None, true);
+    }
+}
+
+impl TrackedClient {
+    // Отдаёт ответ без чтения тела; тело логируется по мере того, как его читает вызывающий
+    pub async fn tracked_send_streamed(&self, key: &str, builder: RequestBuilder) -> Result<TrackedResponse> {
+        let req = self.build_tracked(key, builder).await?;
+        self.start_streamed(key, req).await
+    }
+
+    pub(crate) async fn start_streamed(&self, key: &str, req: Request) -> Result<TrackedResponse> {
+        let (resp, start, key, seq, wal_id) = self.start_tracked(key, req, &SendOptions::default()).await?;
+        let ResponseHead {
+            status,
+            headers,
+            headers_ordered,
+            set_cookies,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+        } = Self::response_head(&resp);
+        let (stream_cap, hash_bodies) = {
+            let settings = self.settings();
+            (settings.stream_capture_limit, settings.hash_bodies)
+        };
+
+        Ok(TrackedResponse {
+            key,
+            seq,
+            wal_id,
+            recorder: self.recorder(),
+            status,
+            headers,
+            set_cookies,
+            headers_ordered,
+            http_version,
+            remote_addr,
+            local_addr,
+            connection_reused,
+            content_length_header,
+            content_length: resp.content_length(),
+            start,
+            headers_at: Instant::now(),
+            cap: stream_cap,
+            captured: Vec::new(),
+            hasher: hash_bodies.then(Sha256::new),
+            total: 0,
+            capped: false,
+            done: false,
+            recorded: false,
+            resp,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{TestResponse, TestServer};
+    use crate::TrackedClient;
+
+    #[tokio::test]
+    async fn text_decodes_declared_charset() {
+        let (cp1251, _, _) = encoding_rs::WINDOWS_1251.encode("Привет, мир");
+        let body = cp1251.into_owned();
+        let server = TestServer::start(move |_| {
+            let body = body.clone();
+            async move { TestResponse::ok(body).header("Content-Type", "text/plain; charset=windows-1251") }
+        })
+        .await;
+        let client = TrackedClient::new().unwrap();
+        let resp = client.tracked_send_streamed("k", client.inner.get(server.url("/"))).await.unwrap();
+        assert_eq!(resp.text().await.unwrap(), "Привет, мир");
+    }
+
+    #[tokio::test]
+    async fn text_defaults_to_utf8() {
+        let server = TestServer::start(|_| async { TestResponse::ok("héllo") }).await;
+        let client = TrackedClient::new().unwrap();
+        let resp = client.tracked_send_streamed("k", client.inner.get(server.url("/"))).await.unwrap();
+        assert_eq!(resp.text().await.unwrap(), "héllo");
+    }
+}
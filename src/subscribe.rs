@@ -0,0 +1,206 @@
+use crate::{RequestResponseData, TrackedClient};
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+pub const DEFAULT_SUBSCRIPTION_CAPACITY: usize = 1024;
+
+// Что делать, когда подписчик не успевает
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Backpressure {
+    // выкинуть самую старую запись из очереди и посчитать её в dropped
+    #[default]
+    DropOldest,
+    // ждать места: завершение запроса задерживается, пока подписчик не заберёт запись
+    Block,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubscriberStats {
+    pub subscribers: usize,
+    pub delivered: u64,
+    pub dropped: u64,
+}
+
+type Finished = (String, RequestResponseData);
+
+struct Queue {
+    items: VecDeque<Finished>,
+    // подписка отброшена получателем
+    unsubscribed: bool,
+    // клиент и все его клоны отброшены
+    closed: bool,
+}
+
+struct Channel {
+    queue: Mutex<Queue>,
+    capacity: usize,
+    policy: Backpressure,
+    items: Notify,
+    space: Notify,
+    dropped: AtomicU64,
+}
+
+impl Channel {
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // None — запись принята (или подписчика уже нет); Some — очередь полна, нужно ждать
+    fn offer(&self, item: Finished, wait: bool) -> Option<Finished> {
+        let mut queue = self.queue();
+        if queue.unsubscribed {
+            return None;
+        }
+        if queue.items.len() >= self.capacity {
+            if wait {
+                return Some(item);
+            }
+            queue.items.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        queue.items.push_back(item);
+        drop(queue);
+        self.items.notify_one();
+        None
+    }
+}
+
+// Завершённые записи (ответ или ошибка) по мере попадания в коллектор
+pub struct Subscription {
+    channel: Arc<Channel>,
+}
+
+impl Subscription {
+    // None — клиент отброшен и очередь пуста
+    pub async fn recv(&mut self) -> Option<(String, RequestResponseData)> {
+        loop {
+            {
+                let mut queue = self.channel.queue();
+                if let Some(item) = queue.items.pop_front() {
+                    drop(queue);
+                    self.channel.space.notify_one();
+                    return Some(item);
+                }
+                if queue.closed {
+                    return None;
+                }
+            }
+            self.channel.items.notified().await;
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<(String, RequestResponseData)> {
+        let item = self.channel.queue().items.pop_front();
+        if item.is_some() {
+            self.channel.space.notify_one();
+        }
+        item
+    }
+
+    pub fn into_stream(self) -> impl Stream<Item = (String, RequestResponseData)> {
+        futures_util::stream::unfold(self, |mut sub| async move { sub.recv().await.map(|item| (item, sub)) })
+    }
+
+    // Сколько записей этот подписчик потерял при DropOldest
+    pub fn dropped(&self) -> u64 {
+        self.channel.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut queue = self.channel.queue();
+        queue.unsubscribed = true;
+        queue.items.clear();
+        drop(queue);
+        // ожидающие места в Block больше не ждут
+        self.channel.space.notify_waiters();
+        self.channel.space.notify_one();
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    channels: Vec<Arc<Channel>>,
+    delivered: u64,
+    dropped_by_gone: u64,
+}
+
+impl Drop for Subscribers {
+    fn drop(&mut self) {
+        for channel in &self.channels {
+            channel.queue().closed = true;
+            channel.items.notify_one();
+        }
+    }
+}
+
+impl Subscribers {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    // Живые каналы; отписавшиеся убираются, их потери остаются в общем счётчике
+    fn active(&mut self) -> Vec<Arc<Channel>> {
+        let (gone, live): (Vec<_>, Vec<_>) = self.channels.drain(..).partition(|c| c.queue().unsubscribed);
+        self.dropped_by_gone += gone.iter().map(|c| c.dropped.load(Ordering::Relaxed)).sum::<u64>();
+        self.channels = live;
+        self.channels.clone()
+    }
+}
+
+pub(crate) fn lock_subscribers(subscribers: &Mutex<Subscribers>) -> MutexGuard<'_, Subscribers> {
+    subscribers.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) async fn publish(subscribers: &Mutex<Subscribers>, key: &str, entry: RequestResponseData) {
+    let channels = {
+        let mut subs = lock_subscribers(subscribers);
+        subs.delivered += 1;
+        subs.active()
+    };
+    for channel in channels {
+        let mut item = (key.to_string(), entry.clone());
+        // notify_one копит разрешение, так что место, освободившееся между offer и ожиданием, не теряется
+        while let Some(back) = channel.offer(item, channel.policy == Backpressure::Block) {
+            item = back;
+            channel.space.notified().await;
+        }
+    }
+}
+
+impl TrackedClient {
+    pub fn subscribe(&self) -> Subscription {
+        self.subscribe_with(DEFAULT_SUBSCRIPTION_CAPACITY, Backpressure::default())
+    }
+
+    // capacity — сколько записей ждёт получателя, прежде чем сработает политика
+    pub fn subscribe_with(&self, capacity: usize, policy: Backpressure) -> Subscription {
+        let channel = Arc::new(Channel {
+            queue: Mutex::new(Queue { items: VecDeque::new(), unsubscribed: false, closed: false }),
+            capacity: capacity.max(1),
+            policy,
+            items: Notify::new(),
+            space: Notify::new(),
+            dropped: AtomicU64::new(0),
+        });
+        lock_subscribers(&self.subscribers).channels.push(channel.clone());
+        Subscription { channel }
+    }
+
+    // delivered — сколько завершённых записей разослано, dropped — потери всех подписчиков
+    pub fn subscriber_stats(&self) -> SubscriberStats {
+        let mut subs = lock_subscribers(&self.subscribers);
+        let channels = subs.active();
+        SubscriberStats {
+            subscribers: channels.len(),
+            delivered: subs.delivered,
+            dropped: subs.dropped_by_gone + channels.iter().map(|c| c.dropped.load(Ordering::Relaxed)).sum::<u64>(),
+        }
+    }
+}
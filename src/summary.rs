@@ -0,0 +1,121 @@
+use crate::{Outcome, RequestResponseData, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+// Одна пара хост + шаблон пути
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EndpointStats {
+    // с портом, если он не по умолчанию
+    pub host: String,
+    pub path: String,
+    pub count: usize,
+    pub methods: Vec<String>,
+    // статус -> число записей; без ответа — "error" или "pending"
+    pub statuses: BTreeMap<String, usize>,
+    // по записям, у которых известна длительность
+    pub avg_duration_ms: Option<u64>,
+    pub max_duration_ms: Option<u64>,
+}
+
+// Хосты и пути по алфавиту
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EndpointSummary {
+    pub endpoints: Vec<EndpointStats>,
+}
+
+impl EndpointSummary {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("Failed to serialize endpoint summary")
+    }
+}
+
+// 8-4-4-4-12 шестнадцатеричных
+fn is_uuid(segment: &str) -> bool {
+    let groups: Vec<&str> = segment.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(g, len)| g.len() == len && g.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+// Числовые сегменты и UUID -> {id}: /users/123 и /users/456 дают /users/{id}
+pub(crate) fn path_template(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            let numeric = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+            if numeric || is_uuid(segment) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn host_and_path(endpoint: &str, normalize: bool) -> (String, String) {
+    let Ok(url) = Url::parse(endpoint) else {
+        return (String::new(), endpoint.to_string());
+    };
+    let mut host = url.host_str().unwrap_or_default().to_string();
+    if let Some(port) = url.port() {
+        host.push_str(&format!(":{}", port));
+    }
+    let path = if normalize { path_template(url.path()) } else { url.path().to_string() };
+    (host, path)
+}
+
+#[derive(Default)]
+struct Acc {
+    count: usize,
+    methods: BTreeSet<String>,
+    statuses: BTreeMap<String, usize>,
+    durations: Vec<u64>,
+}
+
+pub(crate) fn summarize<'a>(
+    entries: impl IntoIterator<Item = &'a RequestResponseData>,
+    normalize: bool,
+) -> EndpointSummary {
+    let mut groups: BTreeMap<(String, String), Acc> = BTreeMap::new();
+    for entry in entries {
+        let acc = groups.entry(host_and_path(&entry.request_data.endpoint, normalize)).or_default();
+        acc.count += 1;
+        acc.methods.insert(entry.request_data.method.clone());
+        let resp = entry.response_data.as_ref();
+        let status = match (resp, entry.outcome) {
+            (Some(r), _) => r.status.to_string(),
+            (None, Outcome::Pending) => "pending".to_string(),
+            (None, _) => "error".to_string(),
+        };
+        *acc.statuses.entry(status).or_default() += 1;
+        acc.durations.extend(resp.map(|r| r.duration_ms).or(entry.duration_ms));
+    }
+    let endpoints = groups
+        .into_iter()
+        .map(|((host, path), acc)| EndpointStats {
+            host,
+            path,
+            count: acc.count,
+            methods: acc.methods.into_iter().collect(),
+            statuses: acc.statuses,
+            avg_duration_ms: (!acc.durations.is_empty())
+                .then(|| acc.durations.iter().sum::<u64>() / acc.durations.len() as u64),
+            max_duration_ms: acc.durations.iter().copied().max(),
+        })
+        .collect();
+    EndpointSummary { endpoints }
+}
+
+impl TrackedClient {
+    // С нормализацией путей, см. endpoint_summary_with
+    pub async fn endpoint_summary(&self) -> Result<EndpointSummary> {
+        self.endpoint_summary_with(true).await
+    }
+
+    // normalize_paths — числовые сегменты и UUID сводятся к {id}
+    pub async fn endpoint_summary_with(&self, normalize_paths: bool) -> Result<EndpointSummary> {
+        let coll = self.collector.lock().await;
+        Ok(summarize(coll.values(), normalize_paths))
+    }
+}
@@ -0,0 +1,193 @@
+// Минимальный HTTP/1.1-сервер для тестов: каждый запрос отдаётся обработчику,
+// соединение закрывается после ответа
+// не каждому тесту нужны все помощники
+#![allow(dead_code)]
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Clone)]
+pub(crate) struct TestRequest {
+    pub method: String,
+    // путь вместе с query
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl TestRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // Content-Length полного тела, но отправить только столько байт и оборвать соединение
+    pub cut_after: Option<usize>,
+}
+
+impl TestResponse {
+    pub fn new(status: u16) -> Self {
+        TestResponse { status, headers: Vec::new(), body: Vec::new(), cut_after: None }
+    }
+
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        TestResponse::new(200).body(body)
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn cut_after(mut self, bytes: usize) -> Self {
+        self.cut_after = Some(bytes);
+        self
+    }
+}
+
+pub(crate) struct TestServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<TestRequest>>>,
+}
+
+impl TestServer {
+    pub async fn start<F, Fut>(handler: F) -> TestServer
+    where
+        F: Fn(TestRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = TestResponse> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler = Arc::new(handler);
+        let seen = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let seen = seen.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, handler, seen).await;
+                });
+            }
+        });
+        TestServer { addr, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn requests(&self) -> Vec<TestRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<TestRequest>> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(n, v)| (n.trim().to_string(), v.trim().to_string()))
+        .collect();
+    let mut rest = buf[head_end + 4..].to_vec();
+    let header = |name: &str| headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.clone());
+    let body = if header("transfer-encoding").is_some_and(|v| v.eq_ignore_ascii_case("chunked")) {
+        let mut body = Vec::new();
+        loop {
+            let line_end = loop {
+                if let Some(pos) = rest.windows(2).position(|w| w == b"\r\n") {
+                    break pos;
+                }
+                let n = stream.read(&mut chunk).await?;
+                if n == 0 {
+                    return Ok(None);
+                }
+                rest.extend_from_slice(&chunk[..n]);
+            };
+            let size_line = String::from_utf8_lossy(&rest[..line_end]).to_string();
+            let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("0").trim(), 16).unwrap_or(0);
+            while rest.len() < line_end + 2 + size + 2 {
+                let n = stream.read(&mut chunk).await?;
+                if n == 0 {
+                    return Ok(None);
+                }
+                rest.extend_from_slice(&chunk[..n]);
+            }
+            body.extend_from_slice(&rest[line_end + 2..line_end + 2 + size]);
+            rest.drain(..line_end + 2 + size + 2);
+            if size == 0 {
+                break body;
+            }
+        }
+    } else {
+        let len: usize = header("content-length").and_then(|v| v.parse().ok()).unwrap_or(0);
+        while rest.len() < len {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            rest.extend_from_slice(&chunk[..n]);
+        }
+        rest.truncate(len);
+        rest
+    };
+    Ok(Some(TestRequest { method, path, headers, body }))
+}
+
+async fn serve<F, Fut>(mut stream: TcpStream, handler: Arc<F>, seen: Arc<Mutex<Vec<TestRequest>>>) -> std::io::Result<()>
+where
+    F: Fn(TestRequest) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = TestResponse> + Send + 'static,
+{
+    let Some(request) = read_request(&mut stream).await? else {
+        return Ok(());
+    };
+    seen.lock().unwrap().push(request.clone());
+    let response = handler(request).await;
+    let mut head = format!("HTTP/1.1 {} Test\r\n", response.status);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len()));
+    stream.write_all(head.as_bytes()).await?;
+    let sent = response.cut_after.unwrap_or(response.body.len()).min(response.body.len());
+    stream.write_all(&response.body[..sent]).await?;
+    stream.flush().await?;
+    if response.cut_after.is_some() {
+        // даём клиенту прочитать отправленное, прежде чем оборвать
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use crate::{LoggedText, SendOptions, TrackedClient};
+use anyhow::{Context, Result};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use reqwest::{Body, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::fs::File;
+use tokio_util::io::ReaderStream;
+
+// Что пишется в RequestData вместо байтов загружаемого файла
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UploadInfo {
+    pub path: String,
+    pub filename: Option<String>,
+    pub size: u64,
+    pub content_type: String,
+    // по полному времени запроса, до прочитанного ответа
+    pub duration_ms: Option<u64>,
+    pub bytes_per_sec: Option<f64>,
+}
+
+impl TrackedClient {
+    // Тело запроса читается из файла потоком; ответ — как в tracked_send_text
+    pub async fn tracked_upload(
+        &self,
+        key: &str,
+        builder: RequestBuilder,
+        path: &Path,
+        content_type: &str,
+    ) -> Result<LoggedText> {
+        let file = File::open(path)
+            .await
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let size = file
+            .metadata()
+            .await
+            .with_context(|| format!("Failed to read metadata of {}", path.display()))?
+            .len();
+
+        let builder = builder
+            .header(CONTENT_TYPE, content_type)
+            .header(CONTENT_LENGTH, size)
+            .body(Body::wrap_stream(ReaderStream::new(file)));
+        let opts = SendOptions {
+            upload: Some(UploadInfo {
+                path: path.display().to_string(),
+                filename: path.file_name().map(|n| n.to_string_lossy().into_owned()),
+                size,
+                content_type: content_type.to_string(),
+                duration_ms: None,
+                bytes_per_sec: None,
+            }),
+            ..SendOptions::default()
+        };
+        self.sealing(async {
+            let logged = self.tracked_send_with(key, builder, &opts).await?;
+
+            self.update_entry(&logged.key, logged.seq, |entry| {
+                let duration_ms = entry.response_data.as_ref().map(|r| r.duration_ms);
+                if let (Some(upload), Some(ms)) = (entry.request_data.upload.as_mut(), duration_ms) {
+                    upload.duration_ms = Some(ms);
+                    upload.bytes_per_sec = Some(size as f64 * 1000.0 / ms.max(1) as f64);
+                }
+            })
+            .await;
+            Ok(logged)
+        })
+        .await
+    }
+}
@@ -0,0 +1,276 @@
+use crate::{now_msk, TrackedClient};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// Куда писать журнал намерений: файл (append) или свой обработчик строки
+#[derive(Clone)]
+pub enum WalSink {
+    File(Arc<File>),
+    Callback(Arc<dyn Fn(&str) + Send + Sync>),
+}
+
+// Счётчики накладных расходов журнала
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WalStats {
+    pub lines: u64,
+    pub bytes: u64,
+    pub total_us: u64,
+    pub max_us: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+// Запрос, для которого в журнале есть начало, но нет завершения
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IncompleteRecord {
+    pub id: String,
+    pub timestamp: String,
+    pub key: String,
+    pub method: String,
+    pub endpoint: String,
+    pub proxy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "t", rename_all = "lowercase")]
+enum WalLine {
+    Begin {
+        id: String,
+        ts: String,
+        key: String,
+        method: String,
+        endpoint: String,
+        proxy: Option<String>,
+    },
+    End {
+        id: String,
+        ts: String,
+        status: Option<u16>,
+        error: Option<String>,
+    },
+}
+
+pub(crate) struct WriteAheadLog {
+    sink: WalSink,
+    // префикс запуска, чтобы id не пересекались при дописывании в тот же файл
+    run: String,
+    next_id: u64,
+    stats: WalStats,
+}
+
+impl WriteAheadLog {
+    fn new(sink: WalSink) -> Self {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        WriteAheadLog {
+            sink,
+            run: format!("{}-{}", std::process::id(), millis),
+            next_id: 1,
+            stats: WalStats::default(),
+        }
+    }
+
+    // Пишет строку сразу, без буфера: после kill процесса она уже в ядре.
+    // sync — ещё и дождаться диска, чтобы строка пережила и падение машины
+    fn write(&mut self, line: &WalLine, sync: bool) {
+        let started = Instant::now();
+        let result = serde_json::to_string(line)
+            .context("Failed to serialize write-ahead record")
+            .and_then(|mut text| {
+                match &self.sink {
+                    WalSink::File(file) => {
+                        text.push('\n');
+                        (&**file)
+                            .write_all(text.as_bytes())
+                            .context("Failed to append write-ahead record")?;
+                        if sync {
+                            file.sync_data().context("Failed to sync write-ahead log")?;
+                        }
+                    }
+                    WalSink::Callback(f) => f(&text),
+                }
+                Ok(text.len())
+            });
+        let elapsed = started.elapsed().as_micros() as u64;
+        self.stats.total_us += elapsed;
+        self.stats.max_us = self.stats.max_us.max(elapsed);
+        match result {
+            Ok(len) => {
+                self.stats.lines += 1;
+                self.stats.bytes += len as u64;
+            }
+            Err(e) => {
+                self.stats.errors += 1;
+                self.stats.last_error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    // id записи начала: его же вызывающий передаёт в end, ключ коллектора для этого
+    // не годится — с KeyCollisionPolicy::Overwrite под одним ключом летят несколько запросов
+    pub(crate) fn begin(&mut self, key: &str, method: &str, endpoint: &str, proxy: Option<String>) -> String {
+        let id = format!("{}-{}", self.run, self.next_id);
+        self.next_id += 1;
+        let line = WalLine::Begin {
+            id: id.clone(),
+            ts: timestamp(),
+            key: key.to_string(),
+            method: method.to_string(),
+            endpoint: endpoint.to_string(),
+            proxy,
+        };
+        self.write(&line, true);
+        id
+    }
+
+    pub(crate) fn end(&mut self, id: &str, status: Option<u16>, error: Option<&str>) {
+        let line = WalLine::End {
+            id: id.to_string(),
+            ts: timestamp(),
+            status,
+            error: error.map(str::to_string),
+        };
+        self.write(&line, false);
+    }
+}
+
+fn timestamp() -> String {
+    now_msk().map(|t| t.to_rfc3339()).unwrap_or_default()
+}
+
+// Разбирает журнал и возвращает запросы без строки завершения, в порядке начала.
+// Обрезанная последняя строка (процесс убит посреди записи) пропускается.
+pub fn recover_incomplete<P: AsRef<Path>>(path: P) -> Result<Vec<IncompleteRecord>> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut pending: Vec<IncompleteRecord> = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        match serde_json::from_str::<WalLine>(&line) {
+            Ok(WalLine::Begin { id, ts, key, method, endpoint, proxy }) => pending.push(IncompleteRecord {
+                id,
+                timestamp: ts,
+                key,
+                method,
+                endpoint,
+                proxy,
+            }),
+            Ok(WalLine::End { id, .. }) => pending.retain(|r| r.id != id),
+            Err(_) => continue,
+        }
+    }
+    Ok(pending)
+}
+
+impl TrackedClient {
+    // Включает журнал намерений в файл (дописывается в конец)
+    pub fn enable_write_ahead_log<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open write-ahead log {}", path.display()))?;
+        self.set_write_ahead_sink(WalSink::File(Arc::new(file)));
+        Ok(())
+    }
+
+    pub fn set_write_ahead_sink(&self, sink: WalSink) {
+        *self.write_ahead() = Some(WriteAheadLog::new(sink));
+    }
+
+    pub fn disable_write_ahead_log(&self) {
+        *self.write_ahead() = None;
+    }
+
+    pub fn write_ahead_stats(&self) -> Option<WalStats> {
+        self.write_ahead().as_ref().map(|wal| wal.stats.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestResponse, TestServer};
+    use std::time::Duration;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("wal-{}-{}.ndjson", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn same_key_concurrent_sends_each_get_an_end() {
+        let server = TestServer::start(|req| async move {
+            if req.path == "/slow" {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+            }
+            TestResponse::ok("ok")
+        })
+        .await;
+        let path = temp_path("same-key");
+        let client = TrackedClient::new().unwrap();
+        client.enable_write_ahead_log(&path).unwrap();
+
+        let slow = client.tracked_send("k", client.inner.get(server.url("/slow")));
+        let fast = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            client.tracked_send("k", client.inner.get(server.url("/fast"))).await
+        };
+        let (slow, fast) = tokio::join!(slow, fast);
+        slow.unwrap();
+        fast.unwrap();
+
+        assert_eq!(recover_incomplete(&path).unwrap(), Vec::new());
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 4);
+        assert_eq!(client.write_ahead_stats().unwrap().lines, 4);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_completed_too() {
+        let path = temp_path("failed");
+        let client = TrackedClient::new().unwrap();
+        client.enable_write_ahead_log(&path).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        drop(listener);
+        assert!(client.tracked_send("down", client.inner.get(url)).await.is_err());
+        assert!(recover_incomplete(&path).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn recover_lists_begins_without_end_and_skips_torn_tail() {
+        let path = temp_path("recover");
+        let mut wal = WriteAheadLog::new(WalSink::File(Arc::new(
+            OpenOptions::new().create(true).append(true).open(&path).unwrap(),
+        )));
+        let first = wal.begin("a", "GET", "https://example.com/a", None);
+        let second = wal.begin("a", "POST", "https://example.com/b", Some("http://proxy:8080".to_string()));
+        wal.end(&first, Some(200), None);
+        assert_ne!(first, second);
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(br#"{"t":"begin","id":"torn"#)
+            .unwrap();
+
+        let incomplete = recover_incomplete(&path).unwrap();
+        assert_eq!(incomplete.len(), 1);
+        assert_eq!(incomplete[0].id, second);
+        assert_eq!(incomplete[0].method, "POST");
+        assert_eq!(incomplete[0].proxy.as_deref(), Some("http://proxy:8080"));
+        let _ = std::fs::remove_file(&path);
+    }
+}